@@ -286,6 +286,24 @@ fn func_with_option_schema_check() {
     assert_eq!(TEST_METHOD, API_METHOD_FUNC_WITH_OPTION);
 }
 
+#[api(cacheable: true)]
+/// Cacheable call
+pub fn cacheable_call() -> Result<(), Error> {
+    Ok(())
+}
+
+#[test]
+fn cacheable_call_schema_check() {
+    const TEST_METHOD: ::proxmox_router::ApiMethod = ::proxmox_router::ApiMethod::new(
+        &::proxmox_router::ApiHandler::Sync(&api_function_cacheable_call),
+        &::proxmox_schema::ObjectSchema::new("Cacheable call", &[]),
+    )
+    .protected(false)
+    .cacheable(true);
+
+    assert_eq!(TEST_METHOD, API_METHOD_CACHEABLE_CALL);
+}
+
 struct RpcEnv;
 impl proxmox_router::RpcEnvironment for RpcEnv {
     fn result_attrib_mut(&mut self) -> &mut Value {
@@ -78,6 +78,28 @@ impl ApiPathBuilder {
     }
 }
 
+/// Percent-encode a single path component, for example a storage name.
+///
+/// ```rust
+/// use proxmox_client::{encode_path_component, ApiPathBuilder};
+///
+/// let volume = "local:iso/debian.iso";
+/// let query = ApiPathBuilder::new(format!(
+///     "/api2/extjs/nodes/pve01/storage/local/content/{}",
+///     encode_path_component(volume),
+/// ))
+/// .build();
+///
+/// assert_eq!(
+///     &query,
+///     "/api2/extjs/nodes/pve01/storage/local/content/local%3Aiso%2Fdebian%2Eiso",
+/// );
+/// ```
+pub fn encode_path_component(value: &str) -> String {
+    percent_encoding::percent_encode(value.as_bytes(), percent_encoding::NON_ALPHANUMERIC)
+        .to_string()
+}
+
 #[cfg(feature = "perl-api-path-builder")]
 impl ApiPathBuilder {
     /// Adds a boolean arg in a perl-friendly fashion.
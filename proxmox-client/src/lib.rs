@@ -15,7 +15,7 @@ pub use proxmox_login::tfa::TfaChallenge;
 pub use proxmox_login::{Authentication, Ticket};
 
 mod api_path_builder;
-pub use api_path_builder::ApiPathBuilder;
+pub use api_path_builder::{encode_path_component, ApiPathBuilder};
 
 pub(crate) mod auth;
 pub use auth::{AuthenticationKind, Token};
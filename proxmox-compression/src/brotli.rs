@@ -0,0 +1,132 @@
+//! brotli helper
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use ::brotli::CompressorWriter;
+use bytes::Bytes;
+use futures::stream::Stream;
+
+use crate::writer_encoder::{Compressor, WriterEncoder};
+
+const BUFFER_SIZE: usize = 8192;
+
+impl Compressor for CompressorWriter<Vec<u8>> {
+    fn output(&mut self) -> &mut Vec<u8> {
+        self.get_mut()
+    }
+
+    fn finish(self) -> io::Result<Vec<u8>> {
+        Ok(self.into_inner())
+    }
+}
+
+/// An async brotli encoder that implements [Stream] for another [Stream]
+///
+/// Useful for on-the-fly compression of HTTP responses (`Content-Encoding: br`).
+pub struct BrotliEncoder<T>(WriterEncoder<T, CompressorWriter<Vec<u8>>>);
+
+pub struct BrotliEncoderBuilder<T> {
+    inner: T,
+    quality: u32,
+    window_size: u32,
+    flush_window: Option<usize>,
+}
+
+impl<T> BrotliEncoderBuilder<T> {
+    /// Compression quality from 0 (fastest) to 11 (best), default is 5.
+    pub const fn quality(mut self, quality: u32) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    /// Base 2 logarithm of the sliding window size, from 10 to 24, default is 22.
+    pub const fn window_size(mut self, window_size: u32) -> Self {
+        self.window_size = window_size;
+        self
+    }
+
+    /// If set, this is the number of bytes after which the compressor will be notified to flush
+    /// some data, so the compression produces more steady output and a little earlier.
+    pub const fn flush_window(mut self, flush_window: Option<usize>) -> Self {
+        self.flush_window = flush_window;
+        self
+    }
+
+    pub fn build(self) -> BrotliEncoder<T> {
+        let compressor = CompressorWriter::new(
+            Vec::new(),
+            BUFFER_SIZE,
+            self.quality.min(11),
+            self.window_size.clamp(10, 24),
+        );
+        BrotliEncoder(WriterEncoder::new(
+            self.inner,
+            compressor,
+            self.flush_window,
+        ))
+    }
+}
+
+impl<T> BrotliEncoder<T> {
+    pub fn new(inner: T) -> Self {
+        Self::builder(inner).build()
+    }
+
+    pub fn builder(inner: T) -> BrotliEncoderBuilder<T> {
+        BrotliEncoderBuilder {
+            inner,
+            quality: 5,
+            window_size: 22,
+            flush_window: None,
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+}
+
+impl<T, O> Stream for BrotliEncoder<T>
+where
+    T: Stream<Item = Result<O, io::Error>> + Unpin,
+    O: Into<Bytes>,
+{
+    type Item = Result<Bytes, io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().0).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+
+    use futures::TryStreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_brotli_encoder_against_decompressor() {
+        let body = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. ".repeat(100);
+        let chunks: Vec<Result<Vec<u8>, io::Error>> = body
+            .as_bytes()
+            .chunks(100)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect();
+
+        let encoder = BrotliEncoder::builder(futures::stream::iter(chunks))
+            .flush_window(Some(1000))
+            .build();
+        let encoded: Vec<Bytes> = encoder.try_collect().await.unwrap();
+        let encoded = encoded.concat();
+        assert!(encoded.len() < body.len());
+
+        let mut decoded = String::new();
+        ::brotli::Decompressor::new(&encoded[..], BUFFER_SIZE)
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
+    }
+}
@@ -0,0 +1,117 @@
+//! gzip helper
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use flate2::write::GzEncoder;
+use futures::stream::Stream;
+
+use crate::writer_encoder::{Compressor, WriterEncoder};
+use crate::Level;
+
+impl Compressor for GzEncoder<Vec<u8>> {
+    fn output(&mut self) -> &mut Vec<u8> {
+        self.get_mut()
+    }
+
+    fn finish(self) -> io::Result<Vec<u8>> {
+        GzEncoder::finish(self)
+    }
+}
+
+/// An async gzip encoder that implements [Stream] for another [Stream]
+///
+/// Useful for on-the-fly compression of HTTP responses (`Content-Encoding: gzip`).
+pub struct GzipEncoder<T>(WriterEncoder<T, GzEncoder<Vec<u8>>>);
+
+pub struct GzipEncoderBuilder<T> {
+    inner: T,
+    level: Level,
+    flush_window: Option<usize>,
+}
+
+impl<T> GzipEncoderBuilder<T> {
+    pub const fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// If set, this is the number of bytes after which the compressor will be notified to flush
+    /// some data, so the compression produces more steady output and a little earlier.
+    pub const fn flush_window(mut self, flush_window: Option<usize>) -> Self {
+        self.flush_window = flush_window;
+        self
+    }
+
+    pub fn build(self) -> GzipEncoder<T> {
+        let compressor = GzEncoder::new(Vec::new(), self.level.compression());
+        GzipEncoder(WriterEncoder::new(
+            self.inner,
+            compressor,
+            self.flush_window,
+        ))
+    }
+}
+
+impl<T> GzipEncoder<T> {
+    pub fn new(inner: T) -> Self {
+        Self::builder(inner).build()
+    }
+
+    pub fn builder(inner: T) -> GzipEncoderBuilder<T> {
+        GzipEncoderBuilder {
+            inner,
+            level: Level::Default,
+            flush_window: None,
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0.into_inner()
+    }
+}
+
+impl<T, O> Stream for GzipEncoder<T>
+where
+    T: Stream<Item = Result<O, io::Error>> + Unpin,
+    O: Into<Bytes>,
+{
+    type Item = Result<Bytes, io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().0).poll_next(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Read;
+
+    use futures::TryStreamExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_gzip_encoder_against_flate2() {
+        let body = "Lorem ipsum dolor sit amet, consectetur adipiscing elit. ".repeat(100);
+        let chunks: Vec<Result<Vec<u8>, io::Error>> = body
+            .as_bytes()
+            .chunks(100)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect();
+
+        let encoder = GzipEncoder::builder(futures::stream::iter(chunks))
+            .flush_window(Some(1000))
+            .build();
+        let encoded: Vec<Bytes> = encoder.try_collect().await.unwrap();
+        let encoded = encoded.concat();
+        assert!(encoded.len() < body.len());
+
+        let mut decoded = String::new();
+        flate2::read::GzDecoder::new(&encoded[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, body);
+    }
+}
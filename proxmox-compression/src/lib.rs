@@ -1,10 +1,15 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+pub use brotli::{BrotliEncoder, BrotliEncoderBuilder};
 pub use deflate::{
     DeflateDecoder, DeflateDecoderBuilder, DeflateEncoder, DeflateEncoderBuilder, Level,
 };
+pub use gzip::{GzipEncoder, GzipEncoderBuilder};
 
+mod brotli;
 mod deflate;
+mod gzip;
 pub mod tar;
+mod writer_encoder;
 pub mod zip;
 pub mod zstd;
@@ -0,0 +1,85 @@
+//! Stream adapter for compressors implementing [Write]
+use std::io::{self, Write};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::ready;
+use futures::stream::Stream;
+
+/// A compressor writing its output into a [Vec]
+pub(crate) trait Compressor: Write {
+    /// Returns the compressed data produced so far.
+    fn output(&mut self) -> &mut Vec<u8>;
+
+    /// Finishes the compressed stream and returns the remaining data.
+    fn finish(self) -> io::Result<Vec<u8>>;
+}
+
+/// Compresses a [Stream] using a [Compressor]
+pub(crate) struct WriterEncoder<T, C> {
+    inner: T,
+    compressor: Option<C>,
+    /// This is the current amount of consumed data and the window size used for intermittent
+    /// flushing of the compressor.
+    flush_window: Option<(usize, usize)>,
+}
+
+impl<T, C> WriterEncoder<T, C> {
+    pub fn new(inner: T, compressor: C, flush_window: Option<usize>) -> Self {
+        Self {
+            inner,
+            compressor: Some(compressor),
+            flush_window: flush_window.map(|n| (0, n)),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+}
+
+impl<T, O, C> Stream for WriterEncoder<T, C>
+where
+    T: Stream<Item = Result<O, io::Error>> + Unpin,
+    O: Into<Bytes>,
+    C: Compressor + Unpin,
+{
+    type Item = Result<Bytes, io::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        loop {
+            let Some(compressor) = this.compressor.as_mut() else {
+                return Poll::Ready(None);
+            };
+
+            match ready!(Pin::new(&mut this.inner).poll_next(cx)) {
+                Some(data) => {
+                    let data: Bytes = data?.into();
+                    compressor.write_all(&data)?;
+
+                    if let Some((at, window)) = &mut this.flush_window {
+                        *at = at.saturating_add(data.len());
+                        if *at >= *window {
+                            *at = 0;
+                            compressor.flush()?;
+                        }
+                    }
+
+                    let output = compressor.output();
+                    if !output.is_empty() {
+                        return Poll::Ready(Some(Ok(std::mem::take(output).into())));
+                    }
+                }
+                None => {
+                    let output = this.compressor.take().unwrap().finish()?;
+                    if !output.is_empty() {
+                        return Poll::Ready(Some(Ok(output.into())));
+                    }
+                }
+            }
+        }
+    }
+}
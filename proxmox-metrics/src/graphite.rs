@@ -0,0 +1,244 @@
+use std::fmt::Write;
+use std::sync::Arc;
+
+use anyhow::{bail, Error};
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+
+use proxmox_async::net::udp;
+
+use crate::udp::udp_line_metrics;
+use crate::{Metrics, MetricsData};
+
+/// Maximum amount of data buffered before it is written to a TCP connection.
+const TCP_BUFFER_SIZE: usize = 64 * 1024;
+
+struct GraphiteTcp {
+    address: String,
+    prefix: Option<String>,
+    conn: Option<TcpStream>,
+    data: String,
+    channel: mpsc::Receiver<Arc<MetricsData>>,
+}
+
+/// Tests the connection to the given graphite server via TCP.
+pub async fn test_graphite_tcp(address: &str) -> Result<(), Error> {
+    TcpStream::connect(address).await?;
+    Ok(())
+}
+
+/// Tests the connection to the given graphite server via UDP.
+pub async fn test_graphite_udp(address: &str) -> Result<(), Error> {
+    udp::connect(address).await?;
+    Ok(())
+}
+
+/// Get a [`Metrics`] handle for a graphite server accessed via TCP, using the plaintext
+/// protocol.
+///
+/// `address` must be in the format of `ip_or_hostname:port`. If set, `prefix` is prepended to
+/// all metric paths.
+pub fn graphite_tcp(address: &str, prefix: Option<&str>) -> Metrics {
+    let (tx, rx) = mpsc::channel(1024);
+
+    let this = GraphiteTcp {
+        address: address.to_string(),
+        prefix: prefix.map(String::from),
+        conn: None,
+        data: String::new(),
+        channel: rx,
+    };
+
+    let join_handle = Some(tokio::spawn(this.finish()));
+
+    Metrics {
+        join_handle,
+        channel: Some(tx),
+    }
+}
+
+/// Get a [`Metrics`] handle for a graphite server accessed via UDP, using the plaintext
+/// protocol.
+///
+/// `address` must be in the format of `ip_or_hostname:port`. If set, `prefix` is prepended to
+/// all metric paths.
+pub fn graphite_udp(address: &str, prefix: Option<&str>, mtu: Option<u16>) -> Metrics {
+    let prefix = prefix.map(String::from);
+    udp_line_metrics(
+        address,
+        mtu,
+        Box::new(move |data| format_graphite_lines(data, prefix.as_deref())),
+    )
+}
+
+impl GraphiteTcp {
+    async fn add_data(&mut self, data: Arc<MetricsData>) -> Result<(), Error> {
+        let new_data = format_graphite_lines(&data, self.prefix.as_deref())?;
+
+        self.data.push_str(&new_data);
+
+        if self.data.len() >= TCP_BUFFER_SIZE {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        if self.data.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = match self.conn.take() {
+            Some(conn) => conn,
+            None => TcpStream::connect(&self.address).await?,
+        };
+
+        conn.write_all(self.data.split_off(0).as_bytes()).await?;
+        self.conn = Some(conn);
+        Ok(())
+    }
+
+    async fn finish(mut self) -> Result<(), Error> {
+        while let Some(data) = self.channel.recv().await {
+            self.add_data(data).await?;
+        }
+
+        self.flush().await?;
+
+        if let Some(mut conn) = self.conn.take() {
+            conn.shutdown().await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Return the metric path of `field` in `data`.
+///
+/// The path consists of the prefix, the measurement, the tag values ordered by tag name and
+/// finally the field, e.g. `prefix.cpustat.node1.host.cpu` for a measurement `cpustat` tagged
+/// with `object=host` and `host=node1`.
+pub(crate) fn metric_path(data: &MetricsData, prefix: Option<&str>, field: &str) -> String {
+    let mut path = String::new();
+
+    if let Some(prefix) = prefix.map(|prefix| prefix.trim_matches('.')) {
+        if !prefix.is_empty() {
+            path.push_str(prefix);
+            path.push('.');
+        }
+    }
+
+    path.push_str(&escape_component(&data.measurement));
+
+    let mut tags: Vec<_> = data.tags.iter().collect();
+    tags.sort();
+
+    for (_, value) in tags {
+        path.push('.');
+        path.push_str(&escape_component(value));
+    }
+
+    path.push('.');
+    path.push_str(&escape_component(field));
+
+    path
+}
+
+/// Replace the path separator and all other characters with special meaning.
+fn escape_component(component: &str) -> String {
+    component
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' | ':' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Return the numeric values of `data` with their field name. Booleans are mapped to 0 and 1,
+/// strings are skipped.
+pub(crate) fn numeric_values(data: &MetricsData) -> Result<Vec<(&str, String)>, Error> {
+    let Some(values) = data.values.as_object() else {
+        bail!("invalid data");
+    };
+
+    let mut result = Vec::with_capacity(values.len());
+
+    for (key, value) in values {
+        let value = match value {
+            Value::Object(_) => bail!("objects not supported"),
+            Value::Array(_) => bail!("arrays not supported"),
+            Value::Number(number) => number.to_string(),
+            Value::Bool(value) => (*value as u8).to_string(),
+            Value::String(_) | Value::Null => continue,
+        };
+        result.push((key.as_str(), value));
+    }
+
+    Ok(result)
+}
+
+fn format_graphite_lines(data: &MetricsData, prefix: Option<&str>) -> Result<String, Error> {
+    let mut lines = String::new();
+
+    for (field, value) in numeric_values(data)? {
+        writeln!(
+            lines,
+            "{} {value} {}",
+            metric_path(data, prefix, field),
+            data.ctime
+        )?;
+    }
+
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn graphite_lines() {
+        let data = MetricsData::new(
+            "cpu.stat",
+            1000,
+            json!({ "cpu": 0.5, "up": true, "name": "x", "mem used": 100 }),
+        )
+        .unwrap()
+        .tag("object", "host")
+        .tag("host", "node 1.example");
+
+        assert_eq!(
+            format_graphite_lines(&data, Some("proxmox.")).unwrap(),
+            "proxmox.cpu_stat.node_1_example.host.cpu 0.5 1000\n\
+             proxmox.cpu_stat.node_1_example.host.mem_used 100 1000\n\
+             proxmox.cpu_stat.node_1_example.host.up 1 1000\n"
+        );
+
+        assert_eq!(
+            metric_path(&data, None, "cpu"),
+            "cpu_stat.node_1_example.host.cpu"
+        );
+
+        let data = MetricsData::new("x", 0, json!({ "a": [1] })).unwrap();
+        assert!(format_graphite_lines(&data, None).is_err());
+    }
+
+    #[test]
+    fn metric_path_orders_tags_by_name() {
+        let data = MetricsData::new("cpustat", 0, json!({ "cpu": 0.5 }))
+            .unwrap()
+            .tag("object", "host")
+            .tag("host", "node1");
+
+        assert_eq!(
+            metric_path(&data, Some("prefix"), "cpu"),
+            "prefix.cpustat.node1.host.cpu"
+        );
+    }
+}
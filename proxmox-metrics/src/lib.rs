@@ -9,10 +9,28 @@ use serde::Serialize;
 use serde_json::Value;
 use tokio::sync::mpsc;
 
+mod udp;
+
+mod graphite;
+#[doc(inline)]
+pub use graphite::{graphite_tcp, graphite_udp, test_graphite_tcp, test_graphite_udp};
+
 mod influxdb;
 #[doc(inline)]
 pub use influxdb::{influxdb_http, influxdb_udp, test_influxdb_http, test_influxdb_udp};
 
+mod otlp;
+#[doc(inline)]
+pub use otlp::{otlp_http, test_otlp_http};
+
+mod prometheus;
+#[doc(inline)]
+pub use prometheus::{prometheus, PrometheusExporter, PROMETHEUS_CONTENT_TYPE};
+
+mod statsd;
+#[doc(inline)]
+pub use statsd::{statsd_udp, test_statsd_udp};
+
 #[derive(Clone)]
 /// Structured data for the metric server.
 pub struct MetricsData {
@@ -0,0 +1,293 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::{bail, format_err, Error};
+use hyper::Body;
+use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+use proxmox_http::client::Client;
+use proxmox_http::HttpOptions;
+
+use crate::{Metrics, MetricsData};
+
+/// Name of the instrumentation scope reported with every batch.
+const SCOPE_NAME: &str = "proxmox-metrics";
+
+struct OtlpHttp {
+    client: Client,
+    uri: http::Uri,
+    headers: http::HeaderMap,
+    max_batch_size: usize,
+    /// Pending data points with the name of their metric
+    data: Vec<(String, Value)>,
+    channel: mpsc::Receiver<Arc<MetricsData>>,
+}
+
+/// Tests the connection to the given OTLP/HTTP collector by sending an empty export request.
+pub async fn test_otlp_http(
+    uri: &str,
+    headers: &[(&str, &str)],
+    verify_tls: bool,
+) -> Result<(), Error> {
+    let (_tx, rx) = mpsc::channel(1);
+
+    let this = OtlpHttp::new(uri, headers, verify_tls, 1, rx)?;
+
+    this.send(json!({ "resourceMetrics": [] })).await
+}
+
+/// Get a [`Metrics`] handle for an OpenTelemetry collector accessed via OTLP/HTTP.
+///
+/// `uri` is the base URI of the collector, `/v1/metrics` is appended unless already present.
+/// `headers` are added to every request, e.g. for authentication. At most `max_batch_size`
+/// data points are sent per request.
+pub fn otlp_http(
+    uri: &str,
+    headers: &[(&str, &str)],
+    verify_tls: bool,
+    max_batch_size: usize,
+) -> Result<Metrics, Error> {
+    let (tx, rx) = mpsc::channel(1024);
+
+    let this = OtlpHttp::new(uri, headers, verify_tls, max_batch_size, rx)?;
+
+    let join_handle = Some(tokio::spawn(this.finish()));
+
+    Ok(Metrics {
+        join_handle,
+        channel: Some(tx),
+    })
+}
+
+impl OtlpHttp {
+    fn new(
+        uri: &str,
+        headers: &[(&str, &str)],
+        verify_tls: bool,
+        max_batch_size: usize,
+        channel: mpsc::Receiver<Arc<MetricsData>>,
+    ) -> Result<Self, Error> {
+        let client = if verify_tls {
+            Client::with_options(HttpOptions::default())
+        } else {
+            let mut ssl_connector = SslConnector::builder(SslMethod::tls()).unwrap();
+            ssl_connector.set_verify(SslVerifyMode::NONE);
+            Client::with_ssl_connector(ssl_connector.build(), HttpOptions::default())
+        };
+
+        let mut header_map = http::HeaderMap::new();
+        for (name, value) in headers {
+            let name: http::HeaderName = name
+                .parse()
+                .map_err(|err| format_err!("invalid header name '{name}': {err}"))?;
+            let value: http::HeaderValue = value
+                .parse()
+                .map_err(|err| format_err!("invalid value for header '{name}': {err}"))?;
+            header_map.append(name, value);
+        }
+
+        Ok(OtlpHttp {
+            client,
+            uri: Self::create_uri(uri)?,
+            headers: header_map,
+            max_batch_size: max_batch_size.max(1),
+            data: Vec::new(),
+            channel,
+        })
+    }
+
+    /// Return the metrics export uri for the given base uri
+    fn create_uri(uri: &str) -> Result<http::Uri, Error> {
+        let uri: http::uri::Uri = uri.parse()?;
+        let uri_parts = uri.into_parts();
+
+        let base_path = if let Some(ref p) = uri_parts.path_and_query {
+            p.path().trim_end_matches('/')
+        } else {
+            ""
+        };
+
+        let path = if base_path.ends_with("/v1/metrics") {
+            base_path.to_string()
+        } else {
+            format!("{base_path}/v1/metrics")
+        };
+
+        let (Some(scheme), Some(authority)) = (uri_parts.scheme, uri_parts.authority) else {
+            bail!("uri must contain scheme and authority");
+        };
+
+        Ok(http::uri::Builder::new()
+            .scheme(scheme)
+            .authority(authority)
+            .path_and_query(path)
+            .build()?)
+    }
+
+    async fn send(&self, body: Value) -> Result<(), Error> {
+        let mut request = http::Request::builder()
+            .method("POST")
+            .uri(&self.uri)
+            .header(http::header::CONTENT_TYPE, "application/json");
+
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+
+        let request = request.body(Body::from(body.to_string()))?;
+
+        let res = self.client.request(request).await?;
+
+        let status = res.status();
+        if !status.is_success() {
+            bail!("got bad status: {}", status);
+        }
+        Ok(())
+    }
+
+    async fn add_data(&mut self, data: Arc<MetricsData>) -> Result<(), Error> {
+        for point in format_data_points(&data)? {
+            self.data.push(point);
+
+            if self.data.len() >= self.max_batch_size {
+                self.flush().await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        if self.data.is_empty() {
+            return Ok(());
+        }
+
+        let body = format_export_request(self.data.split_off(0));
+
+        self.send(body).await
+    }
+
+    async fn finish(mut self) -> Result<(), Error> {
+        while let Some(data) = self.channel.recv().await {
+            self.add_data(data).await?;
+        }
+
+        self.flush().await?;
+
+        Ok(())
+    }
+}
+
+/// Convert `data` into OTLP gauge data points, one per value, with the name of their metric.
+fn format_data_points(data: &MetricsData) -> Result<Vec<(String, Value)>, Error> {
+    let Some(values) = data.values.as_object() else {
+        bail!("invalid data");
+    };
+
+    let mut tags: Vec<_> = data.tags.iter().collect();
+    tags.sort();
+
+    let attributes: Vec<Value> = tags
+        .into_iter()
+        .map(|(key, value)| json!({ "key": key, "value": { "stringValue": value } }))
+        .collect();
+
+    // 64 bit integers are encoded as strings in the JSON mapping of OTLP
+    let time = (data.ctime * 1_000_000_000).to_string();
+
+    let mut points = Vec::with_capacity(values.len());
+
+    for (key, value) in values {
+        let mut point = json!({
+            "attributes": attributes,
+            "timeUnixNano": time,
+        });
+
+        match value {
+            Value::Object(_) => bail!("objects not supported"),
+            Value::Array(_) => bail!("arrays not supported"),
+            Value::Number(number) => match number.as_i64() {
+                Some(number) => point["asInt"] = number.to_string().into(),
+                None => point["asDouble"] = number.as_f64().unwrap_or(f64::NAN).into(),
+            },
+            Value::Bool(value) => point["asInt"] = (*value as i64).to_string().into(),
+            // strings and null cannot be represented as data points
+            Value::String(_) | Value::Null => continue,
+        }
+
+        points.push((format!("{}.{}", data.measurement, key), point));
+    }
+
+    Ok(points)
+}
+
+/// Create an `ExportMetricsServiceRequest`, grouping the data points by metric.
+fn format_export_request(points: Vec<(String, Value)>) -> Value {
+    let mut metrics: BTreeMap<String, Vec<Value>> = BTreeMap::new();
+    for (name, point) in points {
+        metrics.entry(name).or_default().push(point);
+    }
+
+    let metrics: Vec<Value> = metrics
+        .into_iter()
+        .map(|(name, points)| json!({ "name": name, "gauge": { "dataPoints": points } }))
+        .collect();
+
+    json!({
+        "resourceMetrics": [{
+            "resource": { "attributes": [] },
+            "scopeMetrics": [{
+                "scope": { "name": SCOPE_NAME },
+                "metrics": metrics,
+            }],
+        }],
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn uri() {
+        let uri = OtlpHttp::create_uri("http://localhost:4318").unwrap();
+        assert_eq!(uri.to_string(), "http://localhost:4318/v1/metrics");
+
+        let uri = OtlpHttp::create_uri("https://collector/otlp/v1/metrics/").unwrap();
+        assert_eq!(uri.to_string(), "https://collector/otlp/v1/metrics");
+
+        assert!(OtlpHttp::create_uri("/v1/metrics").is_err());
+    }
+
+    #[test]
+    fn export_request() {
+        let data = MetricsData::new("cpu", 10, json!({ "usage": 0.5, "count": 4, "name": "x" }))
+            .unwrap()
+            .tag("host", "node1");
+
+        let request = format_export_request(format_data_points(&data).unwrap());
+
+        let metrics = &request["resourceMetrics"][0]["scopeMetrics"][0]["metrics"];
+        assert_eq!(metrics.as_array().unwrap().len(), 2);
+
+        assert_eq!(metrics[0]["name"], "cpu.count");
+        let point = &metrics[0]["gauge"]["dataPoints"][0];
+        assert_eq!(point["asInt"], "4");
+        assert_eq!(point["timeUnixNano"], "10000000000");
+        assert_eq!(
+            point["attributes"],
+            json!([{ "key": "host", "value": { "stringValue": "node1" } }])
+        );
+
+        assert_eq!(metrics[1]["name"], "cpu.usage");
+        assert_eq!(metrics[1]["gauge"]["dataPoints"][0]["asDouble"], 0.5);
+
+        assert!(
+            format_data_points(&MetricsData::new("x", 0, json!({ "a": {} })).unwrap()).is_err()
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Error};
+use hyper::Body;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::{Metrics, MetricsData};
+
+/// Content type of the Prometheus text exposition format.
+pub const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Identifies a series by its measurement and (sorted) tags.
+type SeriesKey = (String, Vec<(String, String)>);
+
+/// Keeps the latest [`MetricsData`] per measurement and tag set, to be scraped by Prometheus.
+///
+/// Data is fed via the [`Metrics`] handles returned by [`Self::metrics()`], and rendered in the
+/// Prometheus text exposition format with [`Self::render()`] or [`Self::response()`], for
+/// instance from a `proxmox-rest-server` route using an `ApiHandler::AsyncHttp` handler.
+///
+/// Cloning the exporter yields a handle to the same data.
+#[derive(Clone, Default)]
+pub struct PrometheusExporter {
+    data: Arc<Mutex<BTreeMap<SeriesKey, Arc<MetricsData>>>>,
+    max_age: Option<i64>,
+}
+
+/// Get a [`Metrics`] handle feeding the given [`PrometheusExporter`].
+pub fn prometheus(exporter: &PrometheusExporter) -> Metrics {
+    exporter.metrics()
+}
+
+impl PrometheusExporter {
+    /// Create a new, empty exporter.
+    ///
+    /// If `max_age` (in seconds) is set, series which were not updated for longer are dropped
+    /// when rendering, so that vanished objects do not get reported forever.
+    pub fn new(max_age: Option<i64>) -> Self {
+        Self {
+            data: Default::default(),
+            max_age,
+        }
+    }
+
+    /// Get a [`Metrics`] handle which stores all data sent to it in this exporter.
+    pub fn metrics(&self) -> Metrics {
+        let (tx, mut rx) = mpsc::channel::<Arc<MetricsData>>(1024);
+
+        let this = self.clone();
+        let join_handle = Some(tokio::spawn(async move {
+            while let Some(data) = rx.recv().await {
+                this.update(data)?;
+            }
+            Ok(())
+        }));
+
+        Metrics {
+            join_handle,
+            channel: Some(tx),
+        }
+    }
+
+    /// Store `data`, replacing earlier data of the same series.
+    pub fn update(&self, data: Arc<MetricsData>) -> Result<(), Error> {
+        let Some(values) = data.values.as_object() else {
+            bail!("invalid data");
+        };
+        if values
+            .values()
+            .any(|value| value.is_object() || value.is_array())
+        {
+            bail!("nested values not supported");
+        }
+
+        let mut tags: Vec<(String, String)> = data
+            .tags
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        tags.sort();
+
+        let key = (data.measurement.to_string(), tags);
+
+        let mut map = self.data.lock().unwrap();
+        match map.get(&key) {
+            // keep newer data if the updates arrive out of order
+            Some(old) if old.ctime > data.ctime => {}
+            _ => {
+                map.insert(key, data);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Render the current data in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        self.render_at(epoch_now())
+    }
+
+    fn render_at(&self, now: i64) -> String {
+        let mut map = self.data.lock().unwrap();
+
+        if let Some(max_age) = self.max_age {
+            map.retain(|_, data| now - data.ctime <= max_age);
+        }
+
+        // samples grouped by metric name, a metric must not be split over multiple groups
+        let mut metrics: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+        for ((measurement, tags), data) in map.iter() {
+            let mut labels = String::new();
+            for (key, value) in tags {
+                if !labels.is_empty() {
+                    labels.push(',');
+                }
+                let _ = write!(
+                    labels,
+                    "{}=\"{}\"",
+                    sanitize_label_name(key),
+                    escape_label_value(value)
+                );
+            }
+
+            let Some(values) = data.values.as_object() else {
+                continue;
+            };
+
+            for (field, value) in values {
+                let value = match value {
+                    Value::Number(number) => number.to_string(),
+                    Value::Bool(true) => "1".to_string(),
+                    Value::Bool(false) => "0".to_string(),
+                    // strings and null cannot be represented as samples
+                    _ => continue,
+                };
+
+                let name = sanitize_metric_name(&format!("{measurement}_{field}"));
+                let sample = if labels.is_empty() {
+                    format!("{name} {value}")
+                } else {
+                    format!("{name}{{{labels}}} {value}")
+                };
+
+                metrics.entry(name).or_default().push(sample);
+            }
+        }
+
+        let mut output = String::new();
+        for (name, samples) in metrics {
+            let _ = writeln!(output, "# TYPE {name} gauge");
+            for sample in samples {
+                output.push_str(&sample);
+                output.push('\n');
+            }
+        }
+
+        output
+    }
+
+    /// Render the current data as HTTP response.
+    pub fn response(&self) -> Result<http::Response<Body>, Error> {
+        Ok(http::Response::builder()
+            .status(http::StatusCode::OK)
+            .header(http::header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)
+            .body(Body::from(self.render()))?)
+    }
+}
+
+fn epoch_now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|time| time.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Metric names must match `[a-zA-Z_:][a-zA-Z0-9_:]*`.
+fn sanitize_metric_name(name: &str) -> String {
+    sanitize(name, |c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// Label names must match `[a-zA-Z_][a-zA-Z0-9_]*`.
+fn sanitize_label_name(name: &str) -> String {
+    sanitize(name, |c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn sanitize(name: &str, valid: impl Fn(char) -> bool) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| if valid(c) { c } else { '_' })
+        .collect();
+
+    if sanitized.is_empty() || sanitized.starts_with(|c: char| c.is_ascii_digit()) {
+        sanitized.insert(0, '_');
+    }
+
+    sanitized
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::PrometheusExporter;
+    use crate::MetricsData;
+
+    #[test]
+    fn render() {
+        let exporter = PrometheusExporter::new(Some(60));
+
+        let data = |ctime, host: &'static str, value| {
+            Arc::new(
+                MetricsData::new(
+                    "cpu-stat",
+                    ctime,
+                    json!({ "cpu": value, "up": true, "name": "x" }),
+                )
+                .unwrap()
+                .tag("host", host)
+                .tag("1st tag", "a \"quoted\"\\value"),
+            )
+        };
+
+        exporter.update(data(100, "node1", json!(0.5))).unwrap();
+        exporter.update(data(110, "node2", json!(2))).unwrap();
+        // older data must not replace newer data
+        exporter.update(data(90, "node2", json!(1))).unwrap();
+        // newer data replaces the series
+        exporter.update(data(120, "node1", json!(0.25))).unwrap();
+
+        assert!(exporter
+            .update(Arc::new(
+                MetricsData::new("x", 0, json!({ "a": [1] })).unwrap()
+            ))
+            .is_err());
+
+        assert_eq!(
+            exporter.render_at(150),
+            "# TYPE cpu_stat_cpu gauge\n\
+             cpu_stat_cpu{_1st_tag=\"a \\\"quoted\\\"\\\\value\",host=\"node1\"} 0.25\n\
+             cpu_stat_cpu{_1st_tag=\"a \\\"quoted\\\"\\\\value\",host=\"node2\"} 2\n\
+             # TYPE cpu_stat_up gauge\n\
+             cpu_stat_up{_1st_tag=\"a \\\"quoted\\\"\\\\value\",host=\"node1\"} 1\n\
+             cpu_stat_up{_1st_tag=\"a \\\"quoted\\\"\\\\value\",host=\"node2\"} 1\n"
+        );
+
+        // node2 is outdated
+        assert_eq!(exporter.render_at(175).lines().count(), 4);
+    }
+}
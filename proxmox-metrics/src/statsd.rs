@@ -0,0 +1,65 @@
+use std::fmt::Write;
+
+use anyhow::Error;
+
+use proxmox_async::net::udp;
+
+use crate::graphite::{metric_path, numeric_values};
+use crate::udp::udp_line_metrics;
+use crate::{Metrics, MetricsData};
+
+/// Tests the connection to the given statsd server.
+pub async fn test_statsd_udp(address: &str) -> Result<(), Error> {
+    udp::connect(address).await?;
+    Ok(())
+}
+
+/// Get a [`Metrics`] handle for a statsd server accessed via UDP.
+///
+/// All values are sent as gauges, with the same dotted metric paths as for graphite. If set,
+/// `prefix` is prepended to all metric paths.
+pub fn statsd_udp(address: &str, prefix: Option<&str>, mtu: Option<u16>) -> Metrics {
+    let prefix = prefix.map(String::from);
+    udp_line_metrics(
+        address,
+        mtu,
+        Box::new(move |data| format_statsd_lines(data, prefix.as_deref())),
+    )
+}
+
+fn format_statsd_lines(data: &MetricsData, prefix: Option<&str>) -> Result<String, Error> {
+    let mut lines = String::new();
+
+    for (field, value) in numeric_values(data)? {
+        let path = metric_path(data, prefix, field);
+
+        // a signed gauge value modifies the current value, so it has to be reset first
+        if value.starts_with('-') {
+            writeln!(lines, "{path}:0|g")?;
+        }
+        writeln!(lines, "{path}:{value}|g")?;
+    }
+
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn statsd_lines() {
+        let data = MetricsData::new("temp", 0, json!({ "value": -2.5, "max": 80 }))
+            .unwrap()
+            .tag("sensor", "cpu0");
+
+        assert_eq!(
+            format_statsd_lines(&data, Some("pve")).unwrap(),
+            "pve.temp.cpu0.max:80|g\n\
+             pve.temp.cpu0.value:0|g\n\
+             pve.temp.cpu0.value:-2.5|g\n"
+        );
+    }
+}
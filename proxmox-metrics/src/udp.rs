@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use anyhow::Error;
+use tokio::sync::mpsc;
+
+use proxmox_async::net::udp;
+
+use crate::{Metrics, MetricsData};
+
+/// Formats a single [`MetricsData`] entry as one or more newline terminated lines.
+pub(crate) type FormatFn = Box<dyn Fn(&MetricsData) -> Result<String, Error> + Send>;
+
+/// Sends line based metrics via UDP, batching as many lines per packet as fit into the MTU.
+struct UdpLineSender {
+    address: String,
+    conn: Option<tokio::net::UdpSocket>,
+    mtu: u16,
+    data: String,
+    format: FormatFn,
+    channel: mpsc::Receiver<Arc<MetricsData>>,
+}
+
+/// Get a [`Metrics`] handle sending the lines produced by `format` to `address` via UDP.
+pub(crate) fn udp_line_metrics(address: &str, mtu: Option<u16>, format: FormatFn) -> Metrics {
+    let (tx, rx) = mpsc::channel(1024);
+
+    let this = UdpLineSender {
+        address: address.to_string(),
+        conn: None,
+        // empty ipv6 udp package needs 48 bytes, subtract 50 for safety
+        mtu: mtu.unwrap_or(1500) - 50,
+        data: String::new(),
+        format,
+        channel: rx,
+    };
+
+    let join_handle = Some(tokio::spawn(async { this.finish().await }));
+
+    Metrics {
+        join_handle,
+        channel: Some(tx),
+    }
+}
+
+impl UdpLineSender {
+    async fn add_data(&mut self, data: Arc<MetricsData>) -> Result<(), Error> {
+        let new_data = (self.format)(&data)?;
+
+        if self.data.len() + new_data.len() >= (self.mtu as usize) {
+            self.flush().await?;
+        }
+
+        self.data.push_str(&new_data);
+
+        if self.data.len() >= (self.mtu as usize) {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), Error> {
+        if self.data.is_empty() {
+            return Ok(());
+        }
+
+        let conn = match self.conn.take() {
+            Some(conn) => conn,
+            None => udp::connect(&self.address).await?,
+        };
+
+        conn.send(self.data.split_off(0).as_bytes()).await?;
+        self.conn = Some(conn);
+        Ok(())
+    }
+
+    async fn finish(mut self) -> Result<(), Error> {
+        while let Some(data) = self.channel.recv().await {
+            self.add_data(data).await?;
+        }
+
+        self.flush().await?;
+
+        Ok(())
+    }
+}
@@ -42,6 +42,43 @@ pub fn test_target(config: &Config, endpoint: &str) -> Result<(), HttpError> {
     Ok(())
 }
 
+/// Send digests of suppressed notifications whose deduplication window or rate limiting
+/// interval has passed.
+///
+/// This is supposed to be called periodically by the product.
+#[cfg(feature = "throttle")]
+pub fn send_suppression_digests(config: &Config) -> Result<(), HttpError> {
+    let bus = Bus::from_config(config).map_err(|err| {
+        http_err!(
+            INTERNAL_SERVER_ERROR,
+            "Could not instantiate notification bus: {err}"
+        )
+    })?;
+
+    bus.send_suppression_digests().map_err(|err| {
+        http_err!(
+            INTERNAL_SERVER_ERROR,
+            "Could not send suppression digests: {err}"
+        )
+    })
+}
+
+/// Send digests for all matchers whose digest schedule has fired.
+///
+/// This is supposed to be called periodically by the product.
+#[cfg(feature = "digest")]
+pub fn send_digests(config: &Config) -> Result<(), HttpError> {
+    let bus = Bus::from_config(config).map_err(|err| {
+        http_err!(
+            INTERNAL_SERVER_ERROR,
+            "Could not instantiate notification bus: {err}"
+        )
+    })?;
+
+    bus.send_digests()
+        .map_err(|err| http_err!(INTERNAL_SERVER_ERROR, "Could not send digests: {err}"))
+}
+
 /// Return all entities (targets, groups, filters) that are linked to the entity.
 /// For instance, if a group 'grp1' contains the targets 'a', 'b' and 'c',
 /// where grp1 has 'filter1' and 'a' has 'filter2' as filters, then
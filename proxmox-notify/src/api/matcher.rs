@@ -2,7 +2,7 @@ use proxmox_http_error::HttpError;
 
 use crate::api::http_err;
 use crate::matcher::{
-    DeleteableMatcherProperty, MatcherConfig, MatcherConfigUpdater, MATCHER_TYPENAME,
+    self, DeleteableMatcherProperty, MatcherConfig, MatcherConfigUpdater, MATCHER_TYPENAME,
 };
 use crate::Config;
 
@@ -29,16 +29,38 @@ pub fn get_matcher(config: &Config, name: &str) -> Result<MatcherConfig, HttpErr
         .map_err(|_| http_err!(NOT_FOUND, "matcher '{name}' not found"))
 }
 
+/// Make sure that `matcher` only references existing matchers and does not create a cycle.
+fn ensure_valid_matcher_references(
+    config: &Config,
+    matcher: &MatcherConfig,
+) -> Result<(), HttpError> {
+    let mut matchers: Vec<MatcherConfig> = get_matchers(config)?
+        .into_iter()
+        .filter(|other| other.name != matcher.name)
+        .collect();
+
+    matchers.push(MatcherConfig {
+        name: matcher.name.clone(),
+        match_matcher: matcher.match_matcher.clone(),
+        ..Default::default()
+    });
+
+    matcher::verify_matcher_references(&matchers)
+        .map_err(|err| http_err!(BAD_REQUEST, "invalid match-matcher: {err}"))
+}
+
 /// Add new notification matcher.
 ///
 /// The caller is responsible for any needed permission checks.
 /// The caller also responsible for locking the configuration files.
 /// Returns a `HttpError` if:
 ///   - an entity with the same name already exists (`400 Bad request`)
+///   - a referenced matcher does not exist or references create a cycle (`400 Bad request`)
 ///   - the configuration could not be saved (`500 Internal server error`)
 pub fn add_matcher(config: &mut Config, matcher_config: MatcherConfig) -> Result<(), HttpError> {
     super::ensure_unique(config, &matcher_config.name)?;
     super::ensure_endpoints_exist(config, &matcher_config.target)?;
+    ensure_valid_matcher_references(config, &matcher_config)?;
 
     config
         .config
@@ -61,6 +83,7 @@ pub fn add_matcher(config: &mut Config, matcher_config: MatcherConfig) -> Result
 /// Returns a `HttpError` if:
 ///   - the configuration could not be saved (`500 Internal server error`)
 ///   - an invalid digest was passed (`400 Bad request`)
+///   - a referenced matcher does not exist or references create a cycle (`400 Bad request`)
 pub fn update_matcher(
     config: &mut Config,
     name: &str,
@@ -78,11 +101,16 @@ pub fn update_matcher(
                 DeleteableMatcherProperty::MatchSeverity => matcher.match_severity.clear(),
                 DeleteableMatcherProperty::MatchField => matcher.match_field.clear(),
                 DeleteableMatcherProperty::MatchCalendar => matcher.match_calendar.clear(),
+                DeleteableMatcherProperty::MatchMatcher => matcher.match_matcher.clear(),
                 DeleteableMatcherProperty::Target => matcher.target.clear(),
                 DeleteableMatcherProperty::Mode => matcher.mode = None,
                 DeleteableMatcherProperty::InvertMatch => matcher.invert_match = None,
                 DeleteableMatcherProperty::Comment => matcher.comment = None,
                 DeleteableMatcherProperty::Disable => matcher.disable = None,
+                DeleteableMatcherProperty::DedupWindow => matcher.dedup_window = None,
+                DeleteableMatcherProperty::DedupField => matcher.dedup_field.clear(),
+                DeleteableMatcherProperty::RateLimit => matcher.rate_limit = None,
+                DeleteableMatcherProperty::DigestSchedule => matcher.digest_schedule = None,
             }
         }
     }
@@ -99,6 +127,11 @@ pub fn update_matcher(
         matcher.match_calendar = match_calendar;
     }
 
+    if let Some(match_matcher) = matcher_updater.match_matcher {
+        matcher.match_matcher = match_matcher;
+        ensure_valid_matcher_references(config, &matcher)?;
+    }
+
     if let Some(mode) = matcher_updater.mode {
         matcher.mode = Some(mode);
     }
@@ -115,6 +148,22 @@ pub fn update_matcher(
         matcher.disable = Some(disable);
     }
 
+    if let Some(dedup_window) = matcher_updater.dedup_window {
+        matcher.dedup_window = Some(dedup_window);
+    }
+
+    if let Some(dedup_field) = matcher_updater.dedup_field {
+        matcher.dedup_field = dedup_field;
+    }
+
+    if let Some(rate_limit) = matcher_updater.rate_limit {
+        matcher.rate_limit = Some(rate_limit);
+    }
+
+    if let Some(digest_schedule) = matcher_updater.digest_schedule {
+        matcher.digest_schedule = Some(digest_schedule);
+    }
+
     if let Some(target) = matcher_updater.target {
         super::ensure_endpoints_exist(config, target.as_slice())?;
         matcher.target = target;
@@ -139,9 +188,11 @@ pub fn update_matcher(
 /// The caller also responsible for locking the configuration files.
 /// Returns a `HttpError` if:
 ///   - the entity does not exist (`404 Not found`)
+///   - the matcher is still referenced by another matcher (`400 Bad request`)
 pub fn delete_matcher(config: &mut Config, name: &str) -> Result<(), HttpError> {
     // Check if the matcher exists
     let _ = get_matcher(config, name)?;
+    super::ensure_safe_to_delete(config, name)?;
 
     config.config.sections.remove(name);
 
@@ -259,4 +310,31 @@ matcher: matcher2
 
         Ok(())
     }
+
+    #[test]
+    fn test_matcher_references() -> Result<(), HttpError> {
+        let mut config = config_with_two_matchers();
+
+        let reference = |name: &str| MatcherConfigUpdater {
+            match_matcher: Some(vec![name.into()]),
+            ..Default::default()
+        };
+
+        assert!(update_matcher(&mut config, "matcher1", reference("unknown"), None, None).is_err());
+        assert!(
+            update_matcher(&mut config, "matcher1", reference("matcher1"), None, None).is_err()
+        );
+
+        update_matcher(&mut config, "matcher1", reference("matcher2"), None, None)?;
+        assert!(
+            update_matcher(&mut config, "matcher2", reference("matcher1"), None, None).is_err()
+        );
+
+        // referenced matchers cannot be deleted
+        assert!(delete_matcher(&mut config, "matcher2").is_err());
+        delete_matcher(&mut config, "matcher1")?;
+        delete_matcher(&mut config, "matcher2")?;
+
+        Ok(())
+    }
 }
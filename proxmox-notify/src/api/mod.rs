@@ -4,15 +4,27 @@ use serde::{Deserialize, Serialize};
 
 use proxmox_http_error::HttpError;
 use proxmox_schema::api;
+#[cfg(feature = "history")]
+use proxmox_uuid::Uuid;
 
+#[cfg(feature = "history")]
+use crate::history::{DeliveryRecord, NotificationHistory};
 use crate::{Config, Origin};
 
 pub mod common;
 #[cfg(feature = "gotify")]
 pub mod gotify;
 pub mod matcher;
+#[cfg(feature = "matrix")]
+pub mod matrix;
+#[cfg(feature = "ntfy")]
+pub mod ntfy;
+#[cfg(feature = "queue")]
+pub mod queue;
 #[cfg(feature = "sendmail")]
 pub mod sendmail;
+#[cfg(feature = "slack")]
+pub mod slack;
 #[cfg(feature = "smtp")]
 pub mod smtp;
 #[cfg(feature = "webhook")]
@@ -59,6 +71,15 @@ pub enum EndpointType {
     /// Webhook endpoint
     #[cfg(feature = "webhook")]
     Webhook,
+    /// Matrix endpoint
+    #[cfg(feature = "matrix")]
+    Matrix,
+    /// Slack-compatible incoming webhook endpoint
+    #[cfg(feature = "slack")]
+    Slack,
+    /// ntfy endpoint
+    #[cfg(feature = "ntfy")]
+    Ntfy,
 }
 
 #[api]
@@ -129,9 +150,85 @@ pub fn get_targets(config: &Config) -> Result<Vec<Target>, HttpError> {
         })
     }
 
+    #[cfg(feature = "matrix")]
+    for endpoint in matrix::get_endpoints(config)? {
+        targets.push(Target {
+            name: endpoint.name,
+            origin: endpoint.origin.unwrap_or(Origin::UserCreated),
+            endpoint_type: EndpointType::Matrix,
+            disable: endpoint.disable,
+            comment: endpoint.comment,
+        })
+    }
+
+    #[cfg(feature = "slack")]
+    for endpoint in slack::get_endpoints(config)? {
+        targets.push(Target {
+            name: endpoint.name,
+            origin: endpoint.origin.unwrap_or(Origin::UserCreated),
+            endpoint_type: EndpointType::Slack,
+            disable: endpoint.disable,
+            comment: endpoint.comment,
+        })
+    }
+
+    #[cfg(feature = "ntfy")]
+    for endpoint in ntfy::get_endpoints(config)? {
+        targets.push(Target {
+            name: endpoint.name,
+            origin: endpoint.origin.unwrap_or(Origin::UserCreated),
+            endpoint_type: EndpointType::Ntfy,
+            disable: endpoint.disable,
+            comment: endpoint.comment,
+        })
+    }
+
     Ok(targets)
 }
 
+/// Get recorded notification delivery attempts, newest first.
+///
+/// If `target` is set, only attempts to deliver via this target are returned. If `id` is set,
+/// only attempts to deliver the notification with this ID are returned.
+///
+/// The caller is responsible for any needed permission checks.
+/// Returns a `HttpError` if:
+///   - the delivery history is not available (`400 Bad request`)
+///   - `id` is not a valid notification ID (`400 Bad request`)
+///   - the history could not be read (`500 Internal server error`)
+#[cfg(feature = "history")]
+pub fn get_delivery_history(
+    target: Option<&str>,
+    id: Option<&str>,
+    limit: Option<usize>,
+) -> Result<Vec<DeliveryRecord>, HttpError> {
+    let history = match NotificationHistory::from_context() {
+        Some(history) => history,
+        None => http_bail!(BAD_REQUEST, "notification history is not available"),
+    };
+
+    let id = id
+        .map(|id| {
+            Uuid::parse_str(id)
+                .map(|id| id.to_string())
+                .map_err(|err| http_err!(BAD_REQUEST, "invalid notification id: {err}"))
+        })
+        .transpose()?;
+
+    let mut records = history
+        .query(|record| {
+            target.is_none_or(|target| record.target == target)
+                && id.as_ref().is_none_or(|id| &record.id == id)
+        })
+        .map_err(|err| http_err!(INTERNAL_SERVER_ERROR, "Could not read history: {err}"))?;
+
+    if let Some(limit) = limit {
+        records.truncate(limit);
+    }
+
+    Ok(records)
+}
+
 fn verify_digest(config: &Config, digest: Option<&[u8]>) -> Result<(), HttpError> {
     if let Some(digest) = digest {
         if config.digest != *digest {
@@ -165,6 +262,18 @@ fn ensure_endpoint_exists(#[allow(unused)] config: &Config, name: &str) -> Resul
     {
         exists = exists || webhook::get_endpoint(config, name).is_ok();
     }
+    #[cfg(feature = "matrix")]
+    {
+        exists = exists || matrix::get_endpoint(config, name).is_ok();
+    }
+    #[cfg(feature = "slack")]
+    {
+        exists = exists || slack::get_endpoint(config, name).is_ok();
+    }
+    #[cfg(feature = "ntfy")]
+    {
+        exists = exists || ntfy::get_endpoint(config, name).is_ok();
+    }
 
     if !exists {
         http_bail!(NOT_FOUND, "endpoint '{name}' does not exist")
@@ -199,7 +308,9 @@ fn get_referrers(config: &Config, entity: &str) -> Result<HashSet<String>, HttpE
     let mut referrers = HashSet::new();
 
     for matcher in matcher::get_matchers(config)? {
-        if matcher.target.iter().any(|target| target == entity) {
+        if matcher.target.iter().any(|target| target == entity)
+            || matcher.match_matcher.iter().any(|nested| nested == entity)
+        {
             referrers.insert(matcher.name.clone());
         }
     }
@@ -248,6 +359,9 @@ fn get_referenced_entities(config: &Config, entity: &str) -> HashSet<String> {
                 for target in matcher.target {
                     new.insert(target.clone());
                 }
+                for nested in matcher.match_matcher {
+                    new.insert(nested.clone());
+                }
             }
         }
 
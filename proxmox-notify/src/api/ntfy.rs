@@ -0,0 +1,315 @@
+use proxmox_http_error::HttpError;
+
+use crate::api::http_err;
+use crate::endpoints::ntfy::{
+    DeleteableNtfyProperty, NtfyConfig, NtfyConfigUpdater, NtfyPrivateConfig,
+    NtfyPrivateConfigUpdater, NTFY_TYPENAME,
+};
+use crate::Config;
+
+/// Get a list of all ntfy endpoints.
+///
+/// The caller is responsible for any needed permission checks.
+/// Returns a list of all ntfy endpoints or a `HttpError` if the config is
+/// erroneous (`500 Internal server error`).
+pub fn get_endpoints(config: &Config) -> Result<Vec<NtfyConfig>, HttpError> {
+    config
+        .config
+        .convert_to_typed_array(NTFY_TYPENAME)
+        .map_err(|e| http_err!(NOT_FOUND, "Could not fetch endpoints: {e}"))
+}
+
+/// Get ntfy endpoint with given `name`
+///
+/// The caller is responsible for any needed permission checks.
+/// Returns the endpoint or a `HttpError` if the endpoint was not found (`404 Not found`).
+pub fn get_endpoint(config: &Config, name: &str) -> Result<NtfyConfig, HttpError> {
+    config
+        .config
+        .lookup(NTFY_TYPENAME, name)
+        .map_err(|_| http_err!(NOT_FOUND, "endpoint '{name}' not found"))
+}
+
+/// Add a new ntfy endpoint.
+///
+/// The caller is responsible for any needed permission checks.
+/// The caller also responsible for locking the configuration files.
+/// Returns a `HttpError` if:
+///   - an entity with the same name already exists (`400 Bad request`)
+///   - the configuration could not be saved (`500 Internal server error`)
+///
+/// Panics if the names of the private config and the public config do not match.
+pub fn add_endpoint(
+    config: &mut Config,
+    endpoint_config: NtfyConfig,
+    private_endpoint_config: NtfyPrivateConfig,
+) -> Result<(), HttpError> {
+    if endpoint_config.name != private_endpoint_config.name {
+        // Programming error by the user of the crate, thus we panic
+        panic!("name for endpoint config and private config must be identical");
+    }
+
+    super::ensure_unique(config, &endpoint_config.name)?;
+
+    set_private_config_entry(config, &private_endpoint_config)?;
+
+    config
+        .config
+        .set_data(&endpoint_config.name, NTFY_TYPENAME, &endpoint_config)
+        .map_err(|e| {
+            http_err!(
+                INTERNAL_SERVER_ERROR,
+                "could not save endpoint '{}': {e}",
+                endpoint_config.name
+            )
+        })
+}
+
+/// Update existing ntfy endpoint
+///
+/// The caller is responsible for any needed permission checks.
+/// The caller also responsible for locking the configuration files.
+/// Returns a `HttpError` if:
+///   - an entity with the same name already exists (`400 Bad request`)
+///   - the configuration could not be saved (`500 Internal server error`)
+pub fn update_endpoint(
+    config: &mut Config,
+    name: &str,
+    endpoint_config_updater: NtfyConfigUpdater,
+    private_endpoint_config_updater: NtfyPrivateConfigUpdater,
+    delete: Option<&[DeleteableNtfyProperty]>,
+    digest: Option<&[u8]>,
+) -> Result<(), HttpError> {
+    super::verify_digest(config, digest)?;
+
+    let mut endpoint = get_endpoint(config, name)?;
+
+    if let Some(delete) = delete {
+        for deletable_property in delete {
+            match deletable_property {
+                DeleteableNtfyProperty::Comment => endpoint.comment = None,
+                DeleteableNtfyProperty::Disable => endpoint.disable = None,
+                DeleteableNtfyProperty::Token => {
+                    set_private_config_entry(
+                        config,
+                        &NtfyPrivateConfig {
+                            name: name.into(),
+                            token: None,
+                        },
+                    )?;
+                }
+            }
+        }
+    }
+
+    if let Some(server) = endpoint_config_updater.server {
+        endpoint.server = server;
+    }
+
+    if let Some(topic) = endpoint_config_updater.topic {
+        endpoint.topic = topic;
+    }
+
+    if let Some(token) = private_endpoint_config_updater.token {
+        set_private_config_entry(
+            config,
+            &NtfyPrivateConfig {
+                name: name.into(),
+                token: Some(token),
+            },
+        )?;
+    }
+
+    if let Some(comment) = endpoint_config_updater.comment {
+        endpoint.comment = Some(comment)
+    }
+
+    if let Some(disable) = endpoint_config_updater.disable {
+        endpoint.disable = Some(disable);
+    }
+
+    config
+        .config
+        .set_data(name, NTFY_TYPENAME, &endpoint)
+        .map_err(|e| {
+            http_err!(
+                INTERNAL_SERVER_ERROR,
+                "could not save endpoint '{name}': {e}"
+            )
+        })
+}
+
+/// Delete existing ntfy endpoint
+///
+/// The caller is responsible for any needed permission checks.
+/// The caller also responsible for locking the configuration files.
+/// Returns a `HttpError` if:
+///   - the entity does not exist (`404 Not found`)
+///   - the endpoint is still referenced by another entity (`400 Bad request`)
+pub fn delete_ntfy_endpoint(config: &mut Config, name: &str) -> Result<(), HttpError> {
+    // Check if the endpoint exists
+    let _ = get_endpoint(config, name)?;
+    super::ensure_safe_to_delete(config, name)?;
+
+    remove_private_config_entry(config, name)?;
+    config.config.sections.remove(name);
+
+    Ok(())
+}
+
+fn set_private_config_entry(
+    config: &mut Config,
+    private_config: &NtfyPrivateConfig,
+) -> Result<(), HttpError> {
+    config
+        .private_config
+        .set_data(&private_config.name, NTFY_TYPENAME, private_config)
+        .map_err(|e| {
+            http_err!(
+                INTERNAL_SERVER_ERROR,
+                "could not save private config for endpoint '{}': {e}",
+                private_config.name
+            )
+        })
+}
+
+fn remove_private_config_entry(config: &mut Config, name: &str) -> Result<(), HttpError> {
+    config.private_config.sections.remove(name);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::test_helpers::empty_config;
+
+    pub fn add_default_ntfy_endpoint(config: &mut Config) -> Result<(), HttpError> {
+        add_endpoint(
+            config,
+            NtfyConfig {
+                name: "ntfy-endpoint".into(),
+                server: "localhost".into(),
+                topic: "topic".into(),
+                comment: Some("comment".into()),
+                ..Default::default()
+            },
+            NtfyPrivateConfig {
+                name: "ntfy-endpoint".into(),
+                token: Some("supersecrettoken".into()),
+            },
+        )?;
+
+        assert!(get_endpoint(config, "ntfy-endpoint").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_not_existing_returns_error() -> Result<(), HttpError> {
+        let mut config = empty_config();
+
+        assert!(update_endpoint(
+            &mut config,
+            "test",
+            Default::default(),
+            Default::default(),
+            None,
+            None
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_update_invalid_digest_returns_error() -> Result<(), HttpError> {
+        let mut config = empty_config();
+        add_default_ntfy_endpoint(&mut config)?;
+
+        assert!(update_endpoint(
+            &mut config,
+            "ntfy-endpoint",
+            Default::default(),
+            Default::default(),
+            None,
+            Some(&[0; 32])
+        )
+        .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ntfy_update() -> Result<(), HttpError> {
+        let mut config = empty_config();
+        add_default_ntfy_endpoint(&mut config)?;
+
+        let digest = config.digest;
+
+        update_endpoint(
+            &mut config,
+            "ntfy-endpoint",
+            NtfyConfigUpdater {
+                server: Some("newhost".into()),
+                topic: Some("newtopic".into()),
+                comment: Some("newcomment".into()),
+                ..Default::default()
+            },
+            NtfyPrivateConfigUpdater {
+                token: Some("changedtoken".into()),
+            },
+            None,
+            Some(&digest),
+        )?;
+
+        let endpoint = get_endpoint(&config, "ntfy-endpoint")?;
+
+        assert_eq!(endpoint.server, "newhost".to_string());
+        assert_eq!(endpoint.topic, "newtopic".to_string());
+
+        let token = config
+            .private_config
+            .lookup::<NtfyPrivateConfig>(NTFY_TYPENAME, "ntfy-endpoint")
+            .unwrap()
+            .token;
+
+        assert_eq!(token, Some("changedtoken".to_string()));
+        assert_eq!(endpoint.comment, Some("newcomment".to_string()));
+
+        // Test property deletion
+        update_endpoint(
+            &mut config,
+            "ntfy-endpoint",
+            Default::default(),
+            Default::default(),
+            Some(&[
+                DeleteableNtfyProperty::Comment,
+                DeleteableNtfyProperty::Token,
+            ]),
+            None,
+        )?;
+
+        let endpoint = get_endpoint(&config, "ntfy-endpoint")?;
+        assert_eq!(endpoint.comment, None);
+
+        let token = config
+            .private_config
+            .lookup::<NtfyPrivateConfig>(NTFY_TYPENAME, "ntfy-endpoint")
+            .unwrap()
+            .token;
+        assert_eq!(token, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_ntfy_endpoint_delete() -> Result<(), HttpError> {
+        let mut config = empty_config();
+        add_default_ntfy_endpoint(&mut config)?;
+
+        delete_ntfy_endpoint(&mut config, "ntfy-endpoint")?;
+        assert!(delete_ntfy_endpoint(&mut config, "ntfy-endpoint").is_err());
+        assert_eq!(get_endpoints(&config)?.len(), 0);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use proxmox_http_error::HttpError;
+
+use crate::api::{http_bail, http_err};
+use crate::queue::{verify_entry_id, NotificationQueue, QueuedNotification};
+use crate::{Bus, Config};
+
+fn get_queue() -> Result<NotificationQueue, HttpError> {
+    match NotificationQueue::from_context() {
+        Some(queue) => Ok(queue),
+        None => http_bail!(BAD_REQUEST, "notification queue is not available"),
+    }
+}
+
+fn verify_id(id: &str, target: &str) -> Result<(), HttpError> {
+    verify_entry_id(id, target).map_err(|err| http_err!(BAD_REQUEST, "{err}"))
+}
+
+fn instantiate_bus(config: &Config) -> Result<Bus, HttpError> {
+    Bus::from_config(config).map_err(|err| {
+        http_err!(
+            INTERNAL_SERVER_ERROR,
+            "Could not instantiate notification bus: {err}"
+        )
+    })
+}
+
+/// Get a list of all queued notifications.
+///
+/// The caller is responsible for any needed permission checks.
+/// Returns a list of all queued notifications or a `HttpError` if the queue could
+/// not be read (`500 Internal server error`).
+pub fn get_queued_notifications() -> Result<Vec<QueuedNotification>, HttpError> {
+    get_queue()?
+        .list()
+        .map_err(|err| http_err!(INTERNAL_SERVER_ERROR, "Could not read queue: {err}"))
+}
+
+/// Immediately retry delivering the notification with the given `id` via `target`.
+///
+/// The caller is responsible for any needed permission checks.
+/// Returns a `HttpError` if:
+///   - the id or the target name is invalid (`400 Bad request`)
+///   - the notification is not queued for the target (`404 Not found`)
+///   - the target does not exist (`404 Not found`)
+///   - the notification could not be delivered (`500 Internal server error`)
+pub fn retry_queued_notification(config: &Config, id: &str, target: &str) -> Result<(), HttpError> {
+    get_queue()?;
+    verify_id(id, target)?;
+
+    instantiate_bus(config)?
+        .retry_queued(id, target)
+        .map_err(|err| match err {
+            crate::Error::NotQueued(..) => http_err!(NOT_FOUND, "{err}"),
+            crate::Error::TargetDoesNotExist(target) => {
+                http_err!(NOT_FOUND, "endpoint '{target}' does not exist")
+            }
+            _ => http_err!(
+                INTERNAL_SERVER_ERROR,
+                "Could not deliver queued notification: {err}"
+            ),
+        })
+}
+
+/// Drop the notification with the given `id` queued for `target`.
+///
+/// The caller is responsible for any needed permission checks.
+/// Returns a `HttpError` if:
+///   - the id or the target name is invalid (`400 Bad request`)
+///   - the notification is not queued for the target (`404 Not found`)
+///   - the queue entry could not be removed (`500 Internal server error`)
+pub fn delete_queued_notification(id: &str, target: &str) -> Result<(), HttpError> {
+    let queue = get_queue()?;
+    verify_id(id, target)?;
+
+    queue.remove(id, target).map_err(|err| match err {
+        crate::Error::NotQueued(..) => http_err!(NOT_FOUND, "{err}"),
+        _ => http_err!(
+            INTERNAL_SERVER_ERROR,
+            "Could not drop queued notification: {err}"
+        ),
+    })
+}
+
+/// Retry delivering all queued notifications which are due.
+///
+/// This is supposed to be called periodically by the product.
+/// Returns a `HttpError` if the queue could not be processed (`500 Internal server error`).
+pub fn process_queue(config: &Config) -> Result<(), HttpError> {
+    instantiate_bus(config)?
+        .process_queue()
+        .map_err(|err| http_err!(INTERNAL_SERVER_ERROR, "Could not process queue: {err}"))
+}
@@ -70,6 +70,39 @@ fn config_init() -> SectionConfig {
             WEBHOOK_SCHEMA,
         ));
     }
+    #[cfg(feature = "matrix")]
+    {
+        use crate::endpoints::matrix::{MatrixConfig, MATRIX_TYPENAME};
+
+        const MATRIX_SCHEMA: &ObjectSchema = MatrixConfig::API_SCHEMA.unwrap_object_schema();
+        config.register_plugin(SectionConfigPlugin::new(
+            MATRIX_TYPENAME.to_string(),
+            Some(String::from("name")),
+            MATRIX_SCHEMA,
+        ));
+    }
+    #[cfg(feature = "slack")]
+    {
+        use crate::endpoints::slack::{SlackConfig, SLACK_TYPENAME};
+
+        const SLACK_SCHEMA: &ObjectSchema = SlackConfig::API_SCHEMA.unwrap_object_schema();
+        config.register_plugin(SectionConfigPlugin::new(
+            SLACK_TYPENAME.to_string(),
+            Some(String::from("name")),
+            SLACK_SCHEMA,
+        ));
+    }
+    #[cfg(feature = "ntfy")]
+    {
+        use crate::endpoints::ntfy::{NtfyConfig, NTFY_TYPENAME};
+
+        const NTFY_SCHEMA: &ObjectSchema = NtfyConfig::API_SCHEMA.unwrap_object_schema();
+        config.register_plugin(SectionConfigPlugin::new(
+            NTFY_TYPENAME.to_string(),
+            Some(String::from("name")),
+            NTFY_SCHEMA,
+        ));
+    }
 
     const MATCHER_SCHEMA: &ObjectSchema = MatcherConfig::API_SCHEMA.unwrap_object_schema();
     config.register_plugin(SectionConfigPlugin::new(
@@ -135,6 +168,39 @@ fn private_config_init() -> SectionConfig {
             WEBHOOK_SCHEMA,
         ));
     }
+    #[cfg(feature = "matrix")]
+    {
+        use crate::endpoints::matrix::{MatrixPrivateConfig, MATRIX_TYPENAME};
+
+        const MATRIX_SCHEMA: &ObjectSchema = MatrixPrivateConfig::API_SCHEMA.unwrap_object_schema();
+        config.register_plugin(SectionConfigPlugin::new(
+            MATRIX_TYPENAME.to_string(),
+            Some(String::from("name")),
+            MATRIX_SCHEMA,
+        ));
+    }
+    #[cfg(feature = "slack")]
+    {
+        use crate::endpoints::slack::{SlackPrivateConfig, SLACK_TYPENAME};
+
+        const SLACK_SCHEMA: &ObjectSchema = SlackPrivateConfig::API_SCHEMA.unwrap_object_schema();
+        config.register_plugin(SectionConfigPlugin::new(
+            SLACK_TYPENAME.to_string(),
+            Some(String::from("name")),
+            SLACK_SCHEMA,
+        ));
+    }
+    #[cfg(feature = "ntfy")]
+    {
+        use crate::endpoints::ntfy::{NtfyPrivateConfig, NTFY_TYPENAME};
+
+        const NTFY_SCHEMA: &ObjectSchema = NtfyPrivateConfig::API_SCHEMA.unwrap_object_schema();
+        config.register_plugin(SectionConfigPlugin::new(
+            NTFY_TYPENAME.to_string(),
+            Some(String::from("name")),
+            NTFY_SCHEMA,
+        ));
+    }
     config
 }
 
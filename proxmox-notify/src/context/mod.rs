@@ -32,6 +32,13 @@ pub trait Context: Send + Sync + Debug {
         namespace: Option<&str>,
         source: TemplateSource,
     ) -> Result<Option<String>, Error>;
+    /// Directory for persistent notification state, e.g. the queue of notifications
+    /// that could not be delivered yet.
+    ///
+    /// Returns `None` if the product does not keep any persistent notification state.
+    fn state_directory(&self) -> Option<&'static str> {
+        None
+    }
 }
 
 #[cfg(not(test))]
@@ -125,6 +125,10 @@ impl Context for PBSContext {
             .map_err(|err| Error::Generic(format!("could not load template: {err}")))?;
         Ok(template_string)
     }
+
+    fn state_directory(&self) -> Option<&'static str> {
+        Some("/var/lib/proxmox-backup/notifications")
+    }
 }
 
 #[cfg(test)]
@@ -74,6 +74,10 @@ impl Context for PVEContext {
             .map_err(|err| Error::Generic(format!("could not load template: {err}")))?;
         Ok(template_string)
     }
+
+    fn state_directory(&self) -> Option<&'static str> {
+        Some("/var/lib/pve-manager/notifications")
+    }
 }
 
 pub static PVE_CONTEXT: PVEContext = PVEContext;
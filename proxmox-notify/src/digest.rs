@@ -0,0 +1,258 @@
+//! Batching of notifications into digests.
+//!
+//! Notifications matched by a matcher with a `digest-schedule` are not sent immediately, but
+//! collected per matcher. Once the schedule fires, the collected notifications are combined
+//! into a single digest notification, which is sent to all targets of the matcher.
+//!
+//! The collected notifications are kept in the product's state directory, so they survive
+//! process restarts.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use proxmox_uuid::Uuid;
+
+use crate::context::context;
+use crate::matcher::MatcherConfig;
+use crate::state::StateFile;
+use crate::{Content, Error, Metadata, Notification, Severity};
+
+/// Value of the `type` metadata field of digest notifications.
+pub const DIGEST_TYPE: &str = "digest";
+
+/// Maximum number of notifications collected in a single digest.
+const MAX_DIGEST_ENTRIES: usize = 100;
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct PendingDigest {
+    /// Time of the last digest, or when collecting started
+    since: i64,
+    /// Collected notifications
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    entries: Vec<Notification>,
+    /// Number of notifications which were not collected, because the digest was full
+    #[serde(default)]
+    omitted: usize,
+}
+
+impl PendingDigest {
+    fn add(&mut self, notification: &Notification) {
+        if self.entries.len() < MAX_DIGEST_ENTRIES {
+            self.entries.push(notification.clone());
+        } else {
+            self.omitted += 1;
+        }
+    }
+
+    /// Create a digest notification of everything collected so far and reset the state.
+    fn take(&mut self, matcher: &str, now: i64) -> Option<Notification> {
+        self.since = now;
+
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        let entries = std::mem::take(&mut self.entries);
+        let omitted = std::mem::take(&mut self.omitted);
+
+        let severity = entries
+            .iter()
+            .map(|entry| entry.metadata.severity)
+            .reduce(|a, b| if b > a { b } else { a })
+            .unwrap_or(Severity::Info);
+
+        let mut fields = HashMap::new();
+        fields.insert("type".into(), DIGEST_TYPE.into());
+        fields.insert("matcher".into(), matcher.into());
+
+        Some(Notification {
+            content: Content::Digest {
+                entries: entries.into_iter().map(|entry| entry.content).collect(),
+                omitted,
+            },
+            metadata: Metadata {
+                severity,
+                timestamp: now,
+                additional_fields: fields,
+            },
+            id: Uuid::generate(),
+        })
+    }
+}
+
+/// Notifications collected for digests, stored in a state directory.
+pub(crate) struct DigestBuffer {
+    state: StateFile,
+}
+
+impl DigestBuffer {
+    pub(crate) fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            state: StateFile::new(path, "digest"),
+        }
+    }
+
+    /// Create a handle for the product's state directory, if there is one.
+    pub(crate) fn from_context() -> Option<Self> {
+        context()
+            .state_directory()
+            .map(|dir| Self::new(Path::new(dir).join("digest")))
+    }
+
+    /// Collect `notification` for the digests of all `matchers`.
+    pub(crate) fn add(
+        &self,
+        matchers: &[&MatcherConfig],
+        notification: &Notification,
+    ) -> Result<(), Error> {
+        let now = notification.timestamp();
+
+        self.state
+            .with_state(|state: &mut HashMap<String, PendingDigest>| {
+                for matcher in matchers {
+                    state
+                        .entry(matcher.name.clone())
+                        .or_insert_with(|| PendingDigest {
+                            since: now,
+                            ..Default::default()
+                        })
+                        .add(notification);
+                }
+            })
+    }
+
+    /// Create digests for all matchers whose schedule has fired, with their targets.
+    ///
+    /// State belonging to matchers which do not exist anymore or which do not have a
+    /// `digest-schedule` anymore is dropped.
+    pub(crate) fn flush(
+        &self,
+        matchers: &[MatcherConfig],
+        now: i64,
+    ) -> Result<Vec<(String, Notification)>, Error> {
+        self.state
+            .with_state(|state: &mut HashMap<String, PendingDigest>| {
+                let mut digests = Vec::new();
+
+                state.retain(|name, _| {
+                    matchers
+                        .iter()
+                        .any(|matcher| &matcher.name == name && matcher.digest_schedule.is_some())
+                });
+
+                for matcher in matchers {
+                    let (Some(pending), Some(schedule)) =
+                        (state.get_mut(&matcher.name), matcher.digest_schedule())
+                    else {
+                        continue;
+                    };
+
+                    match schedule.compute_next_event(pending.since) {
+                        Ok(Some(next)) if next <= now => {}
+                        Ok(_) => continue,
+                        Err(err) => {
+                            warn!(
+                                "matcher '{name}': could not compute next digest: {err}",
+                                name = matcher.name
+                            );
+                            continue;
+                        }
+                    }
+
+                    if let Some(digest) = pending.take(&matcher.name, now) {
+                        for target in &matcher.target {
+                            digests.push((target.clone(), digest.clone()));
+                        }
+                    }
+                }
+
+                digests
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    fn test_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("proxmox-notify-digest-{}", Uuid::generate()))
+    }
+
+    fn notification(timestamp: i64, severity: Severity) -> Notification {
+        let mut notification =
+            Notification::from_template(severity, "test", Value::Null, HashMap::new());
+        notification.metadata.timestamp = timestamp;
+        notification
+    }
+
+    fn matcher(digest_schedule: Option<&str>) -> MatcherConfig {
+        MatcherConfig {
+            name: "matcher".into(),
+            target: vec!["a".into(), "b".into()],
+            digest_schedule: digest_schedule.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_digest() -> Result<(), Error> {
+        let dir = test_dir();
+        let buffer = DigestBuffer::new(&dir);
+
+        let matchers = [matcher(Some("hourly"))];
+        let matcher = &matchers[0];
+
+        buffer.add(&[matcher], &notification(60, Severity::Info))?;
+        buffer.add(&[matcher], &notification(120, Severity::Warning))?;
+
+        // schedule has not fired yet
+        assert!(buffer.flush(&matchers, 3599)?.is_empty());
+
+        // state must survive re-instantiation
+        let digests = DigestBuffer::new(&dir).flush(&matchers, 3600)?;
+        assert_eq!(digests.len(), 2);
+
+        let (target, digest) = &digests[0];
+        assert_eq!(target, "a");
+        assert_eq!(digest.metadata.severity, Severity::Warning);
+        assert_eq!(digest.metadata.additional_fields["type"], DIGEST_TYPE);
+        match &digest.content {
+            Content::Digest { entries, omitted } => {
+                assert_eq!(entries.len(), 2);
+                assert_eq!(*omitted, 0);
+            }
+            _ => panic!("unexpected digest content"),
+        }
+
+        // nothing collected since the last digest
+        assert!(buffer.flush(&matchers, 7200)?.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_digest_removed_schedule() -> Result<(), Error> {
+        let dir = test_dir();
+        let buffer = DigestBuffer::new(&dir);
+
+        buffer.add(
+            &[&matcher(Some("hourly"))],
+            &notification(60, Severity::Info),
+        )?;
+
+        // collected notifications are dropped with the schedule
+        assert!(buffer.flush(&[matcher(None)], 3600)?.is_empty());
+        assert!(buffer.flush(&[matcher(Some("hourly"))], 7200)?.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+}
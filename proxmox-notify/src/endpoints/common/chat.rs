@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use proxmox_http::client::sync::Client;
+use proxmox_http::{HttpOptions, ProxyConfig};
+
+use crate::context::context;
+use crate::renderer::{self, TemplateType};
+use crate::{Content, Error, Notification, Severity};
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Create an HTTP client for the endpoint `name`, using the product's proxy configuration.
+pub(crate) fn create_client(name: &str) -> Result<Client, Error> {
+    let proxy_config = context()
+        .http_proxy_config()
+        .map(|url| ProxyConfig::parse_proxy_url(&url))
+        .transpose()
+        .map_err(|err| Error::NotifyFailed(name.to_string(), err.into()))?;
+
+    let options = HttpOptions {
+        proxy_config,
+        ..Default::default()
+    };
+
+    Ok(Client::new_with_timeout(options, HTTP_TIMEOUT))
+}
+
+/// Render the title and the plaintext message of a notification.
+pub(crate) fn render_title_and_message(
+    notification: &Notification,
+) -> Result<(String, String), Error> {
+    match &notification.content {
+        content @ (Content::Template { .. } | Content::Digest { .. }) => {
+            let title = renderer::render_content(TemplateType::Subject, content)?;
+            let message = renderer::render_content(TemplateType::PlaintextBody, content)?;
+
+            Ok((title, message))
+        }
+        #[cfg(feature = "mail-forwarder")]
+        Content::ForwardedMail { title, body, .. } => Ok((title.clone(), body.clone())),
+    }
+}
+
+/// Color used to highlight notifications of the given severity.
+#[allow(dead_code)] // Unused in some feature flag permutations
+pub(crate) fn severity_to_color(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "#2e7d32",
+        Severity::Notice => "#1565c0",
+        Severity::Warning => "#f9a825",
+        Severity::Error => "#c62828",
+        Severity::Unknown => "#757575",
+    }
+}
@@ -1,2 +1,4 @@
+#[cfg(any(feature = "matrix", feature = "ntfy", feature = "slack"))]
+pub(crate) mod chat;
 #[cfg(any(feature = "sendmail", feature = "smtp"))]
 pub(crate) mod mail;
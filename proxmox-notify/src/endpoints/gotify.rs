@@ -98,14 +98,10 @@ pub enum DeleteableGotifyProperty {
 impl Endpoint for GotifyEndpoint {
     fn send(&self, notification: &Notification) -> Result<(), Error> {
         let (title, message) = match &notification.content {
-            Content::Template {
-                template_name,
-                data,
-            } => {
-                let rendered_title =
-                    renderer::render_template(TemplateType::Subject, template_name, data)?;
+            content @ (Content::Template { .. } | Content::Digest { .. }) => {
+                let rendered_title = renderer::render_content(TemplateType::Subject, content)?;
                 let rendered_message =
-                    renderer::render_template(TemplateType::PlaintextBody, template_name, data)?;
+                    renderer::render_content(TemplateType::PlaintextBody, content)?;
 
                 (rendered_title, rendered_message)
             }
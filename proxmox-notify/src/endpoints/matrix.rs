@@ -0,0 +1,212 @@
+use handlebars::html_escape;
+use http::Request;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use proxmox_http::HttpClient;
+use proxmox_schema::api_types::{COMMENT_SCHEMA, HTTP_URL_SCHEMA};
+use proxmox_schema::{api, const_regex, ApiStringFormat, Schema, StringSchema, Updater};
+
+use crate::endpoints::common::chat;
+use crate::schema::ENTITY_NAME_SCHEMA;
+use crate::{Endpoint, Error, Notification, Origin};
+
+pub(crate) const MATRIX_TYPENAME: &str = "matrix";
+
+const_regex! {
+    pub MATRIX_ROOM_ID_REGEX = r"^![^:\s]+:\S+$";
+}
+
+pub const MATRIX_ROOM_ID_FORMAT: ApiStringFormat = ApiStringFormat::Pattern(&MATRIX_ROOM_ID_REGEX);
+
+pub const MATRIX_ROOM_ID_SCHEMA: Schema =
+    StringSchema::new("Matrix room ID, for instance '!abcdef:example.org'.")
+        .format(&MATRIX_ROOM_ID_FORMAT)
+        .min_length(3)
+        .max_length(255)
+        .schema();
+
+#[api(
+    properties: {
+        name: {
+            schema: ENTITY_NAME_SCHEMA,
+        },
+        server: {
+            schema: HTTP_URL_SCHEMA,
+        },
+        room: {
+            schema: MATRIX_ROOM_ID_SCHEMA,
+        },
+        comment: {
+            optional: true,
+            schema: COMMENT_SCHEMA,
+        },
+    }
+)]
+#[derive(Serialize, Deserialize, Updater, Default)]
+#[serde(rename_all = "kebab-case")]
+/// Config for Matrix notification endpoints
+pub struct MatrixConfig {
+    /// Name of the endpoint.
+    #[updater(skip)]
+    pub name: String,
+    /// URL of the homeserver's client-server API.
+    pub server: String,
+    /// Room the notifications are posted to.
+    pub room: String,
+    /// Comment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Disable this target.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable: Option<bool>,
+    /// Origin of this config entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[updater(skip)]
+    pub origin: Option<Origin>,
+}
+
+#[api()]
+#[derive(Serialize, Deserialize, Clone, Updater)]
+#[serde(rename_all = "kebab-case")]
+/// Private configuration for Matrix notification endpoints.
+/// This config will be saved to a separate configuration file with stricter
+/// permissions (root:root 0600)
+pub struct MatrixPrivateConfig {
+    /// Name of the endpoint
+    #[updater(skip)]
+    pub name: String,
+    /// Access token of the user posting the notifications
+    pub token: String,
+}
+
+/// A Matrix notification endpoint.
+pub struct MatrixEndpoint {
+    pub config: MatrixConfig,
+    pub private_config: MatrixPrivateConfig,
+}
+
+#[api]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// The set of properties that can be deleted from a Matrix endpoint configuration.
+pub enum DeleteableMatrixProperty {
+    /// Delete `comment`
+    Comment,
+    /// Delete `disable`
+    Disable,
+}
+
+impl Endpoint for MatrixEndpoint {
+    fn send(&self, notification: &Notification) -> Result<(), Error> {
+        let request = self.build_request(notification)?;
+
+        chat::create_client(self.name())?
+            .request(request)
+            .map_err(|err| Error::NotifyFailed(self.name().to_string(), err.into()))?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Check if the endpoint is disabled
+    fn disabled(&self) -> bool {
+        self.config.disable.unwrap_or_default()
+    }
+}
+
+impl MatrixEndpoint {
+    fn build_request(&self, notification: &Notification) -> Result<Request<String>, Error> {
+        let (title, message) = chat::render_title_and_message(notification)?;
+
+        let color = chat::severity_to_color(notification.metadata.severity);
+
+        let body = json!({
+            "msgtype": "m.text",
+            "body": format!("{title}\n\n{message}"),
+            "format": "org.matrix.custom.html",
+            "formatted_body": format!(
+                "<font data-mx-color=\"{color}\"><b>{title}</b></font>\n<pre><code>{message}</code></pre>",
+                title = html_escape(&title),
+                message = html_escape(&message),
+            ),
+        })
+        .to_string();
+
+        // The notification's ID serves as transaction ID, so that the homeserver drops
+        // duplicates if a notification is retried.
+        let uri = format!(
+            "{server}/_matrix/client/v3/rooms/{room}/send/m.room.message/{txn}",
+            server = self.config.server.trim_end_matches('/'),
+            room = utf8_percent_encode(&self.config.room, NON_ALPHANUMERIC),
+            txn = notification.id(),
+        );
+
+        Request::builder()
+            .method("PUT")
+            .uri(uri)
+            .header(
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", self.private_config.token),
+            )
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header(http::header::CONTENT_LENGTH, body.len())
+            .body(body)
+            .map_err(|err| Error::Generic(format!("failed to build http request: {err}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::Severity;
+
+    #[test]
+    fn test_build_request() -> Result<(), Error> {
+        let endpoint = MatrixEndpoint {
+            config: MatrixConfig {
+                name: "matrix".into(),
+                server: "https://matrix.example.org/".into(),
+                room: "!room:example.org".into(),
+                ..Default::default()
+            },
+            private_config: MatrixPrivateConfig {
+                name: "matrix".into(),
+                token: "secret".into(),
+            },
+        };
+
+        let notification =
+            Notification::from_template(Severity::Error, "foo", json!({}), Default::default());
+
+        let request = endpoint.build_request(&notification)?;
+
+        assert_eq!(request.method(), "PUT");
+        assert_eq!(
+            request.uri().to_string(),
+            format!(
+                "https://matrix.example.org/_matrix/client/v3/rooms/%21room%3Aexample%2Eorg/send/m.room.message/{}",
+                notification.id()
+            )
+        );
+        assert_eq!(
+            request.headers().get(http::header::AUTHORIZATION).unwrap(),
+            "Bearer secret"
+        );
+
+        let body: Value = serde_json::from_str(request.body()).unwrap();
+        assert_eq!(body["msgtype"], "m.text");
+        assert!(body["formatted_body"]
+            .as_str()
+            .unwrap()
+            .starts_with("<font data-mx-color=\"#c62828\">"));
+
+        Ok(())
+    }
+}
@@ -1,7 +1,13 @@
 #[cfg(feature = "gotify")]
 pub mod gotify;
+#[cfg(feature = "matrix")]
+pub mod matrix;
+#[cfg(feature = "ntfy")]
+pub mod ntfy;
 #[cfg(feature = "sendmail")]
 pub mod sendmail;
+#[cfg(feature = "slack")]
+pub mod slack;
 #[cfg(feature = "smtp")]
 pub mod smtp;
 #[cfg(feature = "webhook")]
@@ -0,0 +1,211 @@
+use http::Request;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use proxmox_http::HttpClient;
+use proxmox_schema::api_types::{COMMENT_SCHEMA, HTTP_URL_SCHEMA};
+use proxmox_schema::{api, const_regex, ApiStringFormat, Schema, StringSchema, Updater};
+
+use crate::endpoints::common::chat;
+use crate::schema::ENTITY_NAME_SCHEMA;
+use crate::{Endpoint, Error, Notification, Origin, Severity};
+
+pub(crate) const NTFY_TYPENAME: &str = "ntfy";
+
+fn severity_to_priority(level: Severity) -> u32 {
+    match level {
+        Severity::Info => 2,
+        Severity::Notice => 3,
+        Severity::Warning => 4,
+        Severity::Error => 5,
+        Severity::Unknown => 3,
+    }
+}
+
+fn severity_to_tag(level: Severity) -> &'static str {
+    match level {
+        Severity::Info => "information_source",
+        Severity::Notice => "information_source",
+        Severity::Warning => "warning",
+        Severity::Error => "rotating_light",
+        Severity::Unknown => "grey_question",
+    }
+}
+
+const_regex! {
+    pub NTFY_TOPIC_REGEX = r"^[-_A-Za-z0-9]+$";
+}
+
+pub const NTFY_TOPIC_FORMAT: ApiStringFormat = ApiStringFormat::Pattern(&NTFY_TOPIC_REGEX);
+
+pub const NTFY_TOPIC_SCHEMA: Schema = StringSchema::new("ntfy topic.")
+    .format(&NTFY_TOPIC_FORMAT)
+    .min_length(1)
+    .max_length(64)
+    .schema();
+
+#[api(
+    properties: {
+        name: {
+            schema: ENTITY_NAME_SCHEMA,
+        },
+        server: {
+            schema: HTTP_URL_SCHEMA,
+        },
+        topic: {
+            schema: NTFY_TOPIC_SCHEMA,
+        },
+        comment: {
+            optional: true,
+            schema: COMMENT_SCHEMA,
+        },
+    }
+)]
+#[derive(Serialize, Deserialize, Updater, Default)]
+#[serde(rename_all = "kebab-case")]
+/// Config for ntfy notification endpoints
+pub struct NtfyConfig {
+    /// Name of the endpoint.
+    #[updater(skip)]
+    pub name: String,
+    /// ntfy server URL.
+    pub server: String,
+    /// Topic the notifications are published to.
+    pub topic: String,
+    /// Comment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Disable this target.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable: Option<bool>,
+    /// Origin of this config entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[updater(skip)]
+    pub origin: Option<Origin>,
+}
+
+#[api()]
+#[derive(Serialize, Deserialize, Clone, Updater, Default)]
+#[serde(rename_all = "kebab-case")]
+/// Private configuration for ntfy notification endpoints.
+/// This config will be saved to a separate configuration file with stricter
+/// permissions (root:root 0600)
+pub struct NtfyPrivateConfig {
+    /// Name of the endpoint
+    #[updater(skip)]
+    pub name: String,
+    /// Access token, needed if the topic is access-controlled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+/// A ntfy notification endpoint.
+pub struct NtfyEndpoint {
+    pub config: NtfyConfig,
+    pub private_config: NtfyPrivateConfig,
+}
+
+#[api]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// The set of properties that can be deleted from a ntfy endpoint configuration.
+pub enum DeleteableNtfyProperty {
+    /// Delete `comment`
+    Comment,
+    /// Delete `disable`
+    Disable,
+    /// Delete `token`
+    Token,
+}
+
+impl Endpoint for NtfyEndpoint {
+    fn send(&self, notification: &Notification) -> Result<(), Error> {
+        let request = self.build_request(notification)?;
+
+        chat::create_client(self.name())?
+            .request(request)
+            .map_err(|err| Error::NotifyFailed(self.name().to_string(), err.into()))?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Check if the endpoint is disabled
+    fn disabled(&self) -> bool {
+        self.config.disable.unwrap_or_default()
+    }
+}
+
+impl NtfyEndpoint {
+    fn build_request(&self, notification: &Notification) -> Result<Request<String>, Error> {
+        let (title, message) = chat::render_title_and_message(notification)?;
+
+        let severity = notification.metadata.severity;
+
+        // Publish as JSON, HTTP headers cannot carry non-ASCII titles
+        let body = json!({
+            "topic": &self.config.topic,
+            "title": &title,
+            "message": &message,
+            "priority": severity_to_priority(severity),
+            "tags": [severity_to_tag(severity)],
+        })
+        .to_string();
+
+        let mut builder = Request::builder()
+            .method("POST")
+            .uri(self.config.server.trim_end_matches('/'))
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header(http::header::CONTENT_LENGTH, body.len());
+
+        if let Some(token) = &self.private_config.token {
+            builder = builder.header(http::header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+
+        builder
+            .body(body)
+            .map_err(|err| Error::Generic(format!("failed to build http request: {err}")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    #[test]
+    fn test_build_request() -> Result<(), Error> {
+        let endpoint = NtfyEndpoint {
+            config: NtfyConfig {
+                name: "ntfy".into(),
+                server: "https://ntfy.example.org/".into(),
+                topic: "backups".into(),
+                ..Default::default()
+            },
+            private_config: NtfyPrivateConfig {
+                name: "ntfy".into(),
+                token: None,
+            },
+        };
+
+        let notification =
+            Notification::from_template(Severity::Error, "foo", json!({}), Default::default());
+
+        let request = endpoint.build_request(&notification)?;
+
+        assert_eq!(request.method(), "POST");
+        assert_eq!(request.uri(), "https://ntfy.example.org/");
+        assert!(request.headers().get(http::header::AUTHORIZATION).is_none());
+
+        let body: Value = serde_json::from_str(request.body()).unwrap();
+        assert_eq!(body["topic"], "backups");
+        assert_eq!(body["priority"], 5);
+        assert_eq!(body["tags"][0], "rotating_light");
+
+        Ok(())
+    }
+}
@@ -118,16 +118,10 @@ impl Endpoint for SendmailEndpoint {
             .unwrap_or_else(|| context().default_sendmail_from());
 
         match &notification.content {
-            Content::Template {
-                template_name,
-                data,
-            } => {
-                let subject =
-                    renderer::render_template(TemplateType::Subject, template_name, data)?;
-                let html_part =
-                    renderer::render_template(TemplateType::HtmlBody, template_name, data)?;
-                let text_part =
-                    renderer::render_template(TemplateType::PlaintextBody, template_name, data)?;
+            content @ (Content::Template { .. } | Content::Digest { .. }) => {
+                let subject = renderer::render_content(TemplateType::Subject, content)?;
+                let html_part = renderer::render_content(TemplateType::HtmlBody, content)?;
+                let text_part = renderer::render_content(TemplateType::PlaintextBody, content)?;
 
                 let author = self
                     .config
@@ -0,0 +1,225 @@
+use http::Request;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use proxmox_http::HttpClient;
+use proxmox_schema::api_types::{COMMENT_SCHEMA, HTTP_URL_SCHEMA, SINGLE_LINE_COMMENT_FORMAT};
+use proxmox_schema::{api, Schema, StringSchema, Updater};
+
+use crate::endpoints::common::chat;
+use crate::schema::ENTITY_NAME_SCHEMA;
+use crate::{Endpoint, Error, Notification, Origin};
+
+pub(crate) const SLACK_TYPENAME: &str = "slack";
+
+pub const SLACK_CHANNEL_SCHEMA: Schema = StringSchema::new("Channel, for instance '#alerts'.")
+    .format(&SINGLE_LINE_COMMENT_FORMAT)
+    .min_length(1)
+    .max_length(128)
+    .schema();
+
+pub const SLACK_USERNAME_SCHEMA: Schema = StringSchema::new("Name of the posting user.")
+    .format(&SINGLE_LINE_COMMENT_FORMAT)
+    .min_length(1)
+    .max_length(128)
+    .schema();
+
+#[api(
+    properties: {
+        name: {
+            schema: ENTITY_NAME_SCHEMA,
+        },
+        channel: {
+            optional: true,
+            schema: SLACK_CHANNEL_SCHEMA,
+        },
+        username: {
+            optional: true,
+            schema: SLACK_USERNAME_SCHEMA,
+        },
+        comment: {
+            optional: true,
+            schema: COMMENT_SCHEMA,
+        },
+    }
+)]
+#[derive(Serialize, Deserialize, Updater, Default)]
+#[serde(rename_all = "kebab-case")]
+/// Config for Slack-compatible incoming webhook endpoints
+pub struct SlackConfig {
+    /// Name of the endpoint.
+    #[updater(skip)]
+    pub name: String,
+    /// Override the channel configured for the webhook.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    /// Override the username configured for the webhook.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// Comment.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    /// Disable this target.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disable: Option<bool>,
+    /// Origin of this config entry.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[updater(skip)]
+    pub origin: Option<Origin>,
+}
+
+#[api(
+    properties: {
+        url: {
+            schema: HTTP_URL_SCHEMA,
+        },
+    }
+)]
+#[derive(Serialize, Deserialize, Clone, Updater)]
+#[serde(rename_all = "kebab-case")]
+/// Private configuration for Slack-compatible incoming webhook endpoints.
+/// This config will be saved to a separate configuration file with stricter
+/// permissions (root:root 0600)
+pub struct SlackPrivateConfig {
+    /// Name of the endpoint
+    #[updater(skip)]
+    pub name: String,
+    /// Incoming webhook URL. Contains the secret used for authentication.
+    pub url: String,
+}
+
+/// A Slack-compatible incoming webhook endpoint.
+pub struct SlackEndpoint {
+    pub config: SlackConfig,
+    pub private_config: SlackPrivateConfig,
+}
+
+#[api]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// The set of properties that can be deleted from a Slack endpoint configuration.
+pub enum DeleteableSlackProperty {
+    /// Delete `channel`
+    Channel,
+    /// Delete `comment`
+    Comment,
+    /// Delete `disable`
+    Disable,
+    /// Delete `username`
+    Username,
+}
+
+impl Endpoint for SlackEndpoint {
+    fn send(&self, notification: &Notification) -> Result<(), Error> {
+        let request = self.build_request(notification)?;
+
+        chat::create_client(self.name())?
+            .request(request)
+            .map_err(|err| {
+                // The webhook URL is secret, but might be part of the error message
+                let err = err
+                    .to_string()
+                    .replace(&self.private_config.url, "<masked>");
+                Error::NotifyFailed(self.name().to_string(), err.into())
+            })?;
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    /// Check if the endpoint is disabled
+    fn disabled(&self) -> bool {
+        self.config.disable.unwrap_or_default()
+    }
+}
+
+impl SlackEndpoint {
+    fn build_request(&self, notification: &Notification) -> Result<Request<String>, Error> {
+        let (title, message) = chat::render_title_and_message(notification)?;
+
+        let title = escape(&title);
+
+        let mut body = json!({
+            "text": &title,
+            "attachments": [{
+                "fallback": &title,
+                "color": chat::severity_to_color(notification.metadata.severity),
+                "title": &title,
+                // Preformatted, so that tables etc. are displayed properly
+                "text": format!("```\n{}\n```", escape(&message)),
+                "mrkdwn_in": ["text"],
+                "ts": notification.metadata.timestamp,
+            }],
+        });
+
+        if let Some(channel) = &self.config.channel {
+            body["channel"] = channel.as_str().into();
+        }
+        if let Some(username) = &self.config.username {
+            body["username"] = username.as_str().into();
+        }
+
+        let body = body.to_string();
+
+        Request::builder()
+            .method("POST")
+            .uri(&self.private_config.url)
+            .header(http::header::CONTENT_TYPE, "application/json")
+            .header(http::header::CONTENT_LENGTH, body.len())
+            .body(body)
+            .map_err(|_| Error::Generic("failed to build http request: invalid webhook url".into()))
+    }
+}
+
+/// Escape the control characters of Slack's message formatting.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::Severity;
+
+    #[test]
+    fn test_build_request() -> Result<(), Error> {
+        let endpoint = SlackEndpoint {
+            config: SlackConfig {
+                name: "slack".into(),
+                channel: Some("#alerts".into()),
+                ..Default::default()
+            },
+            private_config: SlackPrivateConfig {
+                name: "slack".into(),
+                url: "https://hooks.example.org/services/secret".into(),
+            },
+        };
+
+        let notification =
+            Notification::from_template(Severity::Warning, "foo", json!({}), Default::default());
+
+        let request = endpoint.build_request(&notification)?;
+
+        assert_eq!(request.method(), "POST");
+        assert_eq!(request.uri(), "https://hooks.example.org/services/secret");
+
+        let body: Value = serde_json::from_str(request.body()).unwrap();
+        assert_eq!(body["channel"], "#alerts");
+        assert!(body.get("username").is_none());
+        assert_eq!(body["attachments"][0]["color"], "#f9a825");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("<a> & <b>"), "&lt;a&gt; &amp; &lt;b&gt;");
+    }
+}
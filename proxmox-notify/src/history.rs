@@ -0,0 +1,194 @@
+//! History of notification delivery attempts.
+//!
+//! Every attempt to deliver a notification via a target is recorded, together with the
+//! matchers which selected the target and the outcome. The history is stored in the product's
+//! state directory as JSON lines and rotated once it exceeds a fixed size, so it stays bounded.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use proxmox_schema::api;
+use proxmox_sys::fs::CreateOptions;
+
+use crate::context::context;
+use crate::{DeliveryKind, Error};
+
+#[api(
+    properties: {
+        matcher: {
+            type: Array,
+            items: {
+                description: "Name of a matcher.",
+                type: String,
+            },
+            optional: true,
+        },
+    }
+)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+/// A single attempt to deliver a notification via a target.
+pub struct DeliveryRecord {
+    /// Unique ID of the notification.
+    pub id: String,
+    /// Time of the attempt (UNIX epoch).
+    pub timestamp: i64,
+    /// Target the notification was sent to.
+    pub target: String,
+    /// Matchers which selected the target.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub matcher: Vec<String>,
+    /// Reason for the attempt.
+    pub kind: DeliveryKind,
+    /// Whether the notification was delivered successfully.
+    pub success: bool,
+    /// Error of a failed attempt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+const HISTORY_FILE: &str = "history.jsonl";
+const ROTATED_HISTORY_FILE: &str = "history.jsonl.1";
+const LOCK_FILE: &str = ".lock";
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Size at which the history file is rotated. At most one rotated file is kept.
+const MAX_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Delivery history stored in a state directory.
+pub struct NotificationHistory {
+    path: PathBuf,
+    max_file_size: u64,
+}
+
+impl NotificationHistory {
+    /// Create a history handle for the directory at `path`.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            max_file_size: MAX_FILE_SIZE,
+        }
+    }
+
+    /// Create a history handle in the product's state directory, if there is one.
+    pub(crate) fn from_context() -> Option<Self> {
+        context()
+            .state_directory()
+            .map(|dir| Self::new(Path::new(dir).join("history")))
+    }
+
+    fn lock(&self) -> Result<File, Error> {
+        proxmox_sys::fs::create_path(&self.path, None, None)
+            .map_err(|err| Error::Generic(format!("could not create history directory: {err}")))?;
+
+        proxmox_sys::fs::open_file_locked(
+            self.path.join(LOCK_FILE),
+            LOCK_TIMEOUT,
+            true,
+            CreateOptions::new(),
+        )
+        .map_err(|err| Error::Generic(format!("could not lock history: {err}")))
+    }
+
+    /// Append a record, rotating the history file if it grew too large.
+    pub(crate) fn record(&self, record: &DeliveryRecord) -> Result<(), Error> {
+        let _lock = self.lock()?;
+
+        let file = self.path.join(HISTORY_FILE);
+
+        if std::fs::metadata(&file).is_ok_and(|meta| meta.len() >= self.max_file_size) {
+            std::fs::rename(&file, self.path.join(ROTATED_HISTORY_FILE))
+                .map_err(|err| Error::Generic(format!("could not rotate history: {err}")))?;
+        }
+
+        let mut line = serde_json::to_string(record)
+            .map_err(|err| Error::Generic(format!("could not serialize record: {err}")))?;
+        line.push('\n');
+
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&file)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|err| Error::Generic(format!("could not write history: {err}")))
+    }
+
+    /// Get all records matching `filter`, newest first.
+    pub fn query(
+        &self,
+        filter: impl Fn(&DeliveryRecord) -> bool,
+    ) -> Result<Vec<DeliveryRecord>, Error> {
+        let _lock = self.lock()?;
+
+        let mut records = Vec::new();
+
+        for name in [ROTATED_HISTORY_FILE, HISTORY_FILE] {
+            let content = proxmox_sys::fs::file_read_optional_string(self.path.join(name))
+                .map_err(|err| Error::Generic(format!("could not read history: {err}")))?;
+
+            for line in content.iter().flat_map(|content| content.lines()) {
+                match serde_json::from_str::<DeliveryRecord>(line) {
+                    Ok(record) if filter(&record) => records.push(record),
+                    Ok(_) => {}
+                    Err(err) => warn!("skipping invalid history record: {err}"),
+                }
+            }
+        }
+
+        records.reverse();
+
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proxmox_uuid::Uuid;
+
+    use super::*;
+
+    fn record(target: &str, success: bool) -> DeliveryRecord {
+        DeliveryRecord {
+            id: Uuid::generate().to_string(),
+            timestamp: proxmox_time::epoch_i64(),
+            target: target.into(),
+            matcher: vec!["matcher".into()],
+            kind: DeliveryKind::Notification,
+            success,
+            error: (!success).then(|| "failed".into()),
+        }
+    }
+
+    #[test]
+    fn test_record_and_rotate() -> Result<(), Error> {
+        let dir = std::env::temp_dir().join(format!("proxmox-notify-history-{}", Uuid::generate()));
+
+        let mut history = NotificationHistory::new(&dir);
+        history.max_file_size = 512;
+
+        let mut recorded = Vec::new();
+        for i in 0..20 {
+            let record = record(if i % 2 == 0 { "a" } else { "b" }, i % 3 != 0);
+            history.record(&record)?;
+            recorded.push(record);
+        }
+
+        let all = history.query(|_| true)?;
+        assert!(!all.is_empty() && all.len() < recorded.len());
+        assert_eq!(all[0], recorded[19]);
+
+        let a = history.query(|record| record.target == "a")?;
+        assert!(a.iter().all(|record| record.target == "a"));
+
+        let id = recorded[19].id.clone();
+        assert_eq!(history.query(|record| record.id == id)?.len(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+}
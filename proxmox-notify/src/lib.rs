@@ -1,6 +1,6 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::error::Error as StdError;
 use std::fmt::Display;
 use std::str::FromStr;
@@ -21,11 +21,21 @@ use matcher::{MatcherConfig, MATCHER_TYPENAME};
 pub mod api;
 pub mod config;
 pub mod context;
+#[cfg(feature = "digest")]
+pub mod digest;
 pub mod endpoints;
 pub mod filter;
 pub mod group;
+#[cfg(feature = "history")]
+pub mod history;
+#[cfg(feature = "queue")]
+pub mod queue;
 pub mod renderer;
 pub mod schema;
+#[cfg(any(feature = "throttle", feature = "digest"))]
+mod state;
+#[cfg(feature = "throttle")]
+pub mod throttle;
 
 #[derive(Debug)]
 pub enum Error {
@@ -43,6 +53,8 @@ pub enum Error {
     FilterFailed(String),
     /// The notification's template string could not be rendered
     RenderError(Box<dyn StdError + Send + Sync>),
+    /// A notification is not queued for a target
+    NotQueued(String, String),
     /// Generic error for anything else
     Generic(String),
 }
@@ -73,6 +85,9 @@ impl Display for Error {
                 write!(f, "could not apply filter: {message}")
             }
             Error::RenderError(err) => write!(f, "could not render notification template: {err}"),
+            Error::NotQueued(id, target) => {
+                write!(f, "notification '{id}' is not queued for target '{target}'")
+            }
             Error::Generic(message) => f.write_str(message),
         }
     }
@@ -88,6 +103,7 @@ impl StdError for Error {
             Error::TargetTestFailed(errs) => Some(&*errs[0]),
             Error::FilterFailed(_) => None,
             Error::RenderError(err) => Some(&**err),
+            Error::NotQueued(..) => None,
             Error::Generic(_) => None,
         }
     }
@@ -136,6 +152,21 @@ impl FromStr for Severity {
     }
 }
 
+#[api()]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+/// Reason for a delivery attempt.
+pub enum DeliveryKind {
+    /// A notification matched by matchers.
+    Notification,
+    /// A digest of collected or suppressed notifications.
+    Digest,
+    /// Redelivery of a queued notification.
+    Retry,
+    /// A test notification.
+    Test,
+}
+
 #[api()]
 #[derive(Clone, Debug, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd)]
 #[serde(rename_all = "kebab-case")]
@@ -172,6 +203,14 @@ pub enum Content {
         /// Data that can be used for template rendering.
         data: Value,
     },
+    /// Several notifications combined into a single one
+    #[serde(rename_all = "kebab-case")]
+    Digest {
+        /// Content of the combined notifications
+        entries: Vec<Content>,
+        /// Number of notifications which were left out
+        omitted: usize,
+    },
     #[cfg(feature = "mail-forwarder")]
     #[serde(rename_all = "kebab-case")]
     ForwardedMail {
@@ -377,6 +416,14 @@ impl Config {
 pub struct Bus {
     endpoints: HashMap<String, Box<dyn Endpoint>>,
     matchers: Vec<MatcherConfig>,
+    #[cfg(feature = "queue")]
+    queue: Option<queue::NotificationQueue>,
+    #[cfg(feature = "throttle")]
+    throttle: Option<throttle::Throttle>,
+    #[cfg(feature = "digest")]
+    digest: Option<digest::DigestBuffer>,
+    #[cfg(feature = "history")]
+    history: Option<history::NotificationHistory>,
 }
 
 #[allow(unused_macros)]
@@ -514,14 +561,82 @@ impl Bus {
             );
         }
 
-        let matchers = config
+        #[cfg(feature = "matrix")]
+        {
+            use endpoints::matrix::MATRIX_TYPENAME;
+            use endpoints::matrix::{MatrixConfig, MatrixEndpoint, MatrixPrivateConfig};
+            endpoints.extend(
+                parse_endpoints_with_private_config!(
+                    config,
+                    MatrixConfig,
+                    MatrixPrivateConfig,
+                    MatrixEndpoint,
+                    MATRIX_TYPENAME
+                )?
+                .into_iter()
+                .map(|e| (e.name().into(), e)),
+            );
+        }
+
+        #[cfg(feature = "slack")]
+        {
+            use endpoints::slack::SLACK_TYPENAME;
+            use endpoints::slack::{SlackConfig, SlackEndpoint, SlackPrivateConfig};
+            endpoints.extend(
+                parse_endpoints_with_private_config!(
+                    config,
+                    SlackConfig,
+                    SlackPrivateConfig,
+                    SlackEndpoint,
+                    SLACK_TYPENAME
+                )?
+                .into_iter()
+                .map(|e| (e.name().into(), e)),
+            );
+        }
+
+        #[cfg(feature = "ntfy")]
+        {
+            use endpoints::ntfy::NTFY_TYPENAME;
+            use endpoints::ntfy::{NtfyConfig, NtfyEndpoint, NtfyPrivateConfig};
+            endpoints.extend(
+                parse_endpoints_with_private_config!(
+                    config,
+                    NtfyConfig,
+                    NtfyPrivateConfig,
+                    NtfyEndpoint,
+                    NTFY_TYPENAME
+                )?
+                .into_iter()
+                .map(|e| (e.name().into(), e)),
+            );
+        }
+
+        let mut matchers: Vec<MatcherConfig> = config
             .config
             .convert_to_typed_array(MATCHER_TYPENAME)
             .map_err(|err| Error::ConfigDeserialization(err.into()))?;
 
+        let invalid: HashSet<String> = matcher::invalid_matcher_references(&matchers)
+            .into_iter()
+            .map(|(name, err)| {
+                error!("ignoring matcher '{name}': {err}");
+                name.to_string()
+            })
+            .collect();
+        matchers.retain(|matcher| !invalid.contains(&matcher.name));
+
         Ok(Bus {
             endpoints,
             matchers,
+            #[cfg(feature = "queue")]
+            queue: queue::NotificationQueue::from_context(),
+            #[cfg(feature = "throttle")]
+            throttle: throttle::Throttle::from_context(),
+            #[cfg(feature = "digest")]
+            digest: digest::DigestBuffer::from_context(),
+            #[cfg(feature = "history")]
+            history: history::NotificationHistory::from_context(),
         })
     }
 
@@ -535,36 +650,164 @@ impl Bus {
         self.matchers.push(filter)
     }
 
+    #[cfg(all(test, feature = "queue"))]
+    pub fn set_queue(&mut self, queue: queue::NotificationQueue) {
+        self.queue = Some(queue);
+    }
+
+    #[cfg(all(test, feature = "digest"))]
+    pub(crate) fn set_digest_buffer(&mut self, digest: digest::DigestBuffer) {
+        self.digest = Some(digest);
+    }
+
+    #[cfg(all(test, feature = "history"))]
+    pub fn set_history(&mut self, history: history::NotificationHistory) {
+        self.history = Some(history);
+    }
+
     /// Send a notification. Notification matchers will determine which targets will receive
-    /// the notification.
+    /// the notification, possibly suppressing duplicates or limiting the rate of notifications.
+    /// Matchers with a digest schedule collect the notification for their next digest
+    /// instead, see [`Bus::send_digests`].
     ///
-    /// Any errors will not be returned but only logged.
+    /// Any errors will not be returned but only logged. If delivery via a target fails,
+    /// the notification is queued for a later retry, see [`Bus::process_queue`].
     pub fn send(&self, notification: &Notification) {
-        let targets = matcher::check_matches(self.matchers.as_slice(), notification);
+        #[allow(unused_mut)]
+        let mut matched = matcher::matching_matchers(self.matchers.as_slice(), notification);
 
-        for target in targets {
-            if let Some(endpoint) = self.endpoints.get(target) {
-                let name = endpoint.name();
+        #[cfg(feature = "digest")]
+        if let Some(buffer) = &self.digest {
+            let (digested, immediate): (Vec<_>, Vec<_>) = matched
+                .into_iter()
+                .partition(|matcher| matcher.digest_schedule.is_some());
+
+            matched = immediate;
 
-                if endpoint.disabled() {
-                    // Skip this target if it is disabled
-                    info!("skipping disabled target '{name}'");
-                    continue;
+            if !digested.is_empty() {
+                if let Err(err) = buffer.add(&digested, notification) {
+                    // Rather send immediately than lose notifications
+                    error!("could not collect notification for digest: {err}");
+                    matched.extend(digested);
                 }
+            }
+        }
 
-                match endpoint.send(notification) {
-                    Ok(_) => {
-                        info!("notified via target `{name}`");
+        #[cfg(feature = "throttle")]
+        if let Some(throttle) = &self.throttle {
+            match throttle.apply(&matched, notification) {
+                Ok(throttled) => {
+                    for (target, digest) in &throttled.digests {
+                        self.send_digest_via_target(target, digest);
                     }
-                    Err(e) => {
-                        // Only log on errors, do not propagate fail to the caller.
-                        error!("could not notify via target `{name}`: {e}");
+                    for target in &throttled.targets {
+                        let matchers = matchers_for_target(&matched, target);
+                        self.send_via_target(
+                            target,
+                            notification,
+                            &matchers,
+                            DeliveryKind::Notification,
+                        );
                     }
+                    return;
                 }
-            } else {
-                error!("could not notify via target '{target}', it does not exist");
+                Err(err) => {
+                    // Rather send too much than lose notifications
+                    error!("could not apply notification throttling: {err}");
+                }
+            }
+        }
+
+        let targets: HashSet<&str> = matched
+            .iter()
+            .flat_map(|matcher| matcher.target.iter().map(|s| s.as_str()))
+            .collect();
+
+        for target in targets {
+            let matchers = matchers_for_target(&matched, target);
+            self.send_via_target(target, notification, &matchers, DeliveryKind::Notification);
+        }
+    }
+
+    /// Send a digest created for the matcher named in its `matcher` metadata field.
+    #[allow(dead_code)] // Unused in some feature flag permutations
+    fn send_digest_via_target(&self, target: &str, digest: &Notification) {
+        let matchers: Vec<&str> = digest
+            .metadata
+            .additional_fields
+            .get("matcher")
+            .map(|matcher| matcher.as_str())
+            .into_iter()
+            .collect();
+
+        self.send_via_target(target, digest, &matchers, DeliveryKind::Digest);
+    }
+
+    fn send_via_target(
+        &self,
+        target: &str,
+        notification: &Notification,
+        matchers: &[&str],
+        kind: DeliveryKind,
+    ) {
+        if let Some(endpoint) = self.endpoints.get(target) {
+            let name = endpoint.name();
+
+            if endpoint.disabled() {
+                // Skip this target if it is disabled
+                info!("skipping disabled target '{name}'");
+                return;
+            }
+
+            match self.deliver(endpoint.as_ref(), notification, matchers, kind) {
+                Ok(_) => {
+                    info!("notified via target `{name}`");
+                }
+                Err(e) => {
+                    // Only log on errors, do not propagate fail to the caller.
+                    error!("could not notify via target `{name}`: {e}");
+
+                    #[cfg(feature = "queue")]
+                    if let Some(queue) = &self.queue {
+                        match queue.enqueue(name, notification, &e) {
+                            Ok(()) => info!("queued notification for retry via target `{name}`"),
+                            Err(err) => error!("could not queue notification: {err}"),
+                        }
+                    }
+                }
+            }
+        } else {
+            error!("could not notify via target '{target}', it does not exist");
+        }
+    }
+
+    /// Send digests of notifications suppressed by matchers whose deduplication window or
+    /// rate limiting interval has passed.
+    ///
+    /// This is supposed to be called periodically by the product.
+    #[cfg(feature = "throttle")]
+    pub fn send_suppression_digests(&self) -> Result<(), Error> {
+        if let Some(throttle) = &self.throttle {
+            for (target, digest) in throttle.flush(&self.matchers)? {
+                self.send_digest_via_target(&target, &digest);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Send digests for all matchers whose digest schedule has fired.
+    ///
+    /// This is supposed to be called periodically by the product.
+    #[cfg(feature = "digest")]
+    pub fn send_digests(&self) -> Result<(), Error> {
+        if let Some(buffer) = &self.digest {
+            for (target, digest) in buffer.flush(&self.matchers, proxmox_time::epoch_i64())? {
+                self.send_digest_via_target(&target, &digest);
             }
         }
+
+        Ok(())
     }
 
     /// Send a test notification to a target (endpoint or group).
@@ -587,13 +830,83 @@ impl Bus {
         };
 
         if let Some(endpoint) = self.endpoints.get(target) {
-            endpoint.send(&notification)?;
+            self.deliver(endpoint.as_ref(), &notification, &[], DeliveryKind::Test)?;
         } else {
             return Err(Error::TargetDoesNotExist(target.to_string()));
         }
 
         Ok(())
     }
+
+    /// Retry delivering all queued notifications which are due.
+    ///
+    /// This is supposed to be called periodically by the product.
+    #[cfg(feature = "queue")]
+    pub fn process_queue(&self) -> Result<(), Error> {
+        match &self.queue {
+            Some(queue) => queue.process(&self.endpoints, &|endpoint, notification| {
+                self.deliver(endpoint, notification, &[], DeliveryKind::Retry)
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Immediately retry delivering a queued notification via `target`.
+    ///
+    /// In contrast to `process_queue`, any error is returned to the caller.
+    #[cfg(feature = "queue")]
+    pub fn retry_queued(&self, id: &str, target: &str) -> Result<(), Error> {
+        match &self.queue {
+            Some(queue) => queue.retry(
+                &self.endpoints,
+                &|endpoint, notification| {
+                    self.deliver(endpoint, notification, &[], DeliveryKind::Retry)
+                },
+                id,
+                target,
+            ),
+            None => Err(Error::Generic("notification queue is not available".into())),
+        }
+    }
+
+    /// Send a notification via `endpoint` and record the attempt in the delivery history.
+    fn deliver(
+        &self,
+        endpoint: &dyn Endpoint,
+        notification: &Notification,
+        #[allow(unused)] matchers: &[&str],
+        #[allow(unused)] kind: DeliveryKind,
+    ) -> Result<(), Error> {
+        let result = endpoint.send(notification);
+
+        #[cfg(feature = "history")]
+        if let Some(history) = &self.history {
+            let record = history::DeliveryRecord {
+                id: notification.id().to_string(),
+                timestamp: proxmox_time::epoch_i64(),
+                target: endpoint.name().to_string(),
+                matcher: matchers.iter().map(|matcher| matcher.to_string()).collect(),
+                kind,
+                success: result.is_ok(),
+                error: result.as_ref().err().map(|err| err.to_string()),
+            };
+
+            if let Err(err) = history.record(&record) {
+                error!("could not record notification delivery: {err}");
+            }
+        }
+
+        result
+    }
+}
+
+/// Names of the `matched` matchers which selected `target`.
+fn matchers_for_target<'a>(matched: &[&'a MatcherConfig], target: &str) -> Vec<&'a str> {
+    matched
+        .iter()
+        .filter(|matcher| matcher.target.iter().any(|t| t == target))
+        .map(|matcher| matcher.name.as_str())
+        .collect()
 }
 
 #[cfg(test)]
@@ -715,4 +1028,99 @@ mod tests {
 
         Ok(())
     }
+
+    #[cfg(feature = "history")]
+    #[test]
+    fn test_delivery_history() -> Result<(), Error> {
+        let endpoint = MockEndpoint::new("mock");
+
+        let dir = std::env::temp_dir().join(format!("proxmox-notify-bus-{}", Uuid::generate()));
+
+        let mut bus = Bus::default();
+        bus.set_history(history::NotificationHistory::new(&dir));
+        bus.add_endpoint(Box::new(endpoint.clone()));
+
+        bus.add_matcher(MatcherConfig {
+            name: "matcher".into(),
+            target: vec!["mock".into()],
+            ..Default::default()
+        });
+
+        let notification = Notification::from_template(
+            Severity::Info,
+            "test",
+            Default::default(),
+            Default::default(),
+        );
+        bus.send(&notification);
+        bus.test_target("mock")?;
+
+        let records = history::NotificationHistory::new(&dir).query(|_| true)?;
+        assert_eq!(records.len(), 2);
+
+        assert_eq!(records[0].kind, DeliveryKind::Test);
+        assert_eq!(records[1].kind, DeliveryKind::Notification);
+        assert_eq!(records[1].id, notification.id().to_string());
+        assert_eq!(records[1].matcher, vec!["matcher".to_string()]);
+        assert_eq!(records[1].target, "mock");
+        assert!(records[1].success);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+
+    #[cfg(feature = "digest")]
+    #[test]
+    fn test_digest_matcher() -> Result<(), Error> {
+        let endpoint1 = MockEndpoint::new("mock1");
+        let endpoint2 = MockEndpoint::new("mock2");
+
+        let dir = std::env::temp_dir().join(format!("proxmox-notify-bus-{}", Uuid::generate()));
+
+        let mut bus = Bus::default();
+        bus.set_digest_buffer(digest::DigestBuffer::new(&dir));
+
+        bus.add_endpoint(Box::new(endpoint1.clone()));
+        bus.add_endpoint(Box::new(endpoint2.clone()));
+
+        bus.add_matcher(MatcherConfig {
+            name: "matcher1".into(),
+            target: vec!["mock1".into()],
+            ..Default::default()
+        });
+
+        bus.add_matcher(MatcherConfig {
+            name: "matcher2".into(),
+            target: vec!["mock2".into()],
+            digest_schedule: Some("hourly".into()),
+            ..Default::default()
+        });
+
+        for _ in 0..3 {
+            let mut notification = Notification::from_template(
+                Severity::Info,
+                "test",
+                Default::default(),
+                Default::default(),
+            );
+            notification.metadata.timestamp -= 7200;
+
+            bus.send(&notification);
+        }
+
+        assert_eq!(endpoint1.messages().len(), 3);
+        assert_eq!(endpoint2.messages().len(), 0);
+
+        bus.send_digests()?;
+        assert_eq!(endpoint1.messages().len(), 3);
+        assert_eq!(endpoint2.messages().len(), 1);
+
+        match &endpoint2.messages()[0].content {
+            Content::Digest { entries, .. } => assert_eq!(entries.len(), 3),
+            _ => panic!("unexpected digest content"),
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
 }
@@ -1,4 +1,4 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::fmt::Debug;
 use std::str::FromStr;
@@ -8,15 +8,23 @@ use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tracing::{error, info};
 
+use proxmox_human_byte::HumanByte;
 use proxmox_schema::api_types::{COMMENT_SCHEMA, SAFE_ID_REGEX_STR};
-use proxmox_schema::{api, const_regex, ApiStringFormat, Schema, StringSchema, Updater};
-use proxmox_time::{parse_daily_duration, DailyDuration};
+use proxmox_schema::{
+    api, const_regex, ApiStringFormat, Schema, StringSchema, Updater, UpdaterType,
+};
+use proxmox_time::{
+    parse_daily_duration, verify_calendar_event, CalendarEvent, DailyDuration, TimeSpan,
+};
 
 use crate::schema::ENTITY_NAME_SCHEMA;
 use crate::{Error, Notification, Origin, Severity};
 
 pub const MATCHER_TYPENAME: &str = "matcher";
 
+/// Maximum depth of nested matchers referenced via `match-matcher`.
+const MAX_NESTING_DEPTH: usize = 16;
+
 #[api]
 #[derive(Debug, Serialize, Deserialize, Default, Clone, Copy)]
 #[serde(rename_all = "kebab-case")]
@@ -48,7 +56,7 @@ impl MatchModeOperator {
 }
 
 const_regex! {
-    pub MATCH_FIELD_ENTRY_REGEX = concatcp!(r"^(?:(exact|regex):)?(", SAFE_ID_REGEX_STR, r")=(.*)$");
+    pub MATCH_FIELD_ENTRY_REGEX = concatcp!(r"^(?:(exact|regex|glob|in|gt|lt|ge|le):)?(", SAFE_ID_REGEX_STR, r")=(.*)$");
 }
 
 pub const MATCH_FIELD_ENTRY_FORMAT: ApiStringFormat =
@@ -59,11 +67,51 @@ fn verify_field_matcher(s: &str) -> Result<(), anyhow::Error> {
     Ok(())
 }
 
-pub const MATCH_FIELD_ENTRY_SCHEMA: Schema = StringSchema::new("Match metadata field.")
-    .format(&MATCH_FIELD_ENTRY_FORMAT)
-    .min_length(1)
-    .max_length(1024)
-    .schema();
+pub const MATCH_FIELD_ENTRY_SCHEMA: Schema = StringSchema::new(
+    "Match metadata field. Supported operators are 'exact', 'regex', 'glob', 'in' \
+    and the numeric comparisons 'gt', 'lt', 'ge' and 'le'.",
+)
+.format(&MATCH_FIELD_ENTRY_FORMAT)
+.min_length(1)
+.max_length(1024)
+.schema();
+
+pub const DEDUP_WINDOW_FORMAT: ApiStringFormat = ApiStringFormat::VerifyFn(verify_dedup_window);
+
+fn verify_dedup_window(s: &str) -> Result<(), anyhow::Error> {
+    let _: DedupWindow = s.parse()?;
+    Ok(())
+}
+
+pub const DEDUP_WINDOW_SCHEMA: Schema = StringSchema::new(
+    "Suppress notifications with identical metadata matched again within this time span.",
+)
+.format(&DEDUP_WINDOW_FORMAT)
+.max_length(64)
+.schema();
+
+pub const DIGEST_SCHEDULE_FORMAT: ApiStringFormat =
+    ApiStringFormat::VerifyFn(verify_calendar_event);
+
+pub const DIGEST_SCHEDULE_SCHEMA: Schema = StringSchema::new(
+    "Collect matched notifications and send them as a single digest on this schedule.",
+)
+.format(&DIGEST_SCHEDULE_FORMAT)
+.max_length(256)
+.schema();
+
+pub const RATE_LIMIT_FORMAT: ApiStringFormat = ApiStringFormat::VerifyFn(verify_rate_limit);
+
+fn verify_rate_limit(s: &str) -> Result<(), anyhow::Error> {
+    let _: RateLimit = s.parse()?;
+    Ok(())
+}
+
+pub const RATE_LIMIT_SCHEMA: Schema =
+    StringSchema::new("Maximum number of notifications per target and interval, e.g. '10/1h'.")
+        .format(&RATE_LIMIT_FORMAT)
+        .max_length(64)
+        .schema();
 
 #[api(
     properties: {
@@ -98,6 +146,13 @@ pub const MATCH_FIELD_ENTRY_SCHEMA: Schema = StringSchema::new("Match metadata f
             },
             optional: true,
         },
+        "match-matcher": {
+            type: Array,
+            items: {
+                schema: ENTITY_NAME_SCHEMA,
+            },
+            optional: true,
+        },
         "target": {
             type: Array,
             items: {
@@ -105,6 +160,26 @@ pub const MATCH_FIELD_ENTRY_SCHEMA: Schema = StringSchema::new("Match metadata f
             },
             optional: true,
         },
+        "dedup-window": {
+            schema: DEDUP_WINDOW_SCHEMA,
+            optional: true,
+        },
+        "dedup-field": {
+            type: Array,
+            items: {
+                description: "Name of a metadata field",
+                type: String,
+            },
+            optional: true,
+        },
+        "rate-limit": {
+            schema: RATE_LIMIT_SCHEMA,
+            optional: true,
+        },
+        "digest-schedule": {
+            schema: DIGEST_SCHEDULE_SCHEMA,
+            optional: true,
+        },
     })]
 #[derive(Debug, Serialize, Deserialize, Updater, Default)]
 #[serde(rename_all = "kebab-case")]
@@ -128,6 +203,14 @@ pub struct MatcherConfig {
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     #[updater(serde(skip_serializing_if = "Option::is_none"))]
     pub match_calendar: Vec<CalendarMatcher>,
+
+    /// List of other matchers whose results are combined like any other match statement.
+    /// Together with `mode` and `invert-match` of the referenced matchers, this allows
+    /// building nested expressions.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[updater(serde(skip_serializing_if = "Option::is_none"))]
+    pub match_matcher: Vec<String>,
+
     /// Decide if 'all' or 'any' match statements must match.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mode: Option<MatchModeOperator>,
@@ -141,6 +224,25 @@ pub struct MatcherConfig {
     #[updater(serde(skip_serializing_if = "Option::is_none"))]
     pub target: Vec<String>,
 
+    /// Suppress notifications with identical metadata matched again within this time span.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dedup_window: Option<DedupWindow>,
+
+    /// Metadata fields compared to detect identical notifications. If empty, the
+    /// severity and all metadata fields are compared.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[updater(serde(skip_serializing_if = "Option::is_none"))]
+    pub dedup_field: Vec<String>,
+
+    /// Maximum number of notifications sent to each target within an interval.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimit>,
+
+    /// Collect matched notifications and send them as a single digest on this schedule.
+    /// Deduplication and rate limiting do not apply to notifications collected in a digest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest_schedule: Option<String>,
+
     /// Comment.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub comment: Option<String>,
@@ -159,6 +261,41 @@ trait MatchDirective {
     fn matches(&self, notification: &Notification) -> Result<bool, Error>;
 }
 
+/// Numeric comparison of a metadata field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompareOperator {
+    /// Field value is greater than the given value
+    Gt,
+    /// Field value is less than the given value
+    Lt,
+    /// Field value is greater than or equal to the given value
+    Ge,
+    /// Field value is less than or equal to the given value
+    Le,
+}
+
+impl CompareOperator {
+    fn apply(&self, lhs: f64, rhs: f64) -> bool {
+        match self {
+            CompareOperator::Gt => lhs > rhs,
+            CompareOperator::Lt => lhs < rhs,
+            CompareOperator::Ge => lhs >= rhs,
+            CompareOperator::Le => lhs <= rhs,
+        }
+    }
+}
+
+impl fmt::Display for CompareOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            CompareOperator::Gt => "gt",
+            CompareOperator::Lt => "lt",
+            CompareOperator::Ge => "ge",
+            CompareOperator::Le => "le",
+        })
+    }
+}
+
 /// Check if the notification metadata fields match
 #[derive(Clone, Debug)]
 pub enum FieldMatcher {
@@ -170,6 +307,21 @@ pub enum FieldMatcher {
         field: String,
         matched_regex: Regex,
     },
+    Glob {
+        field: String,
+        pattern: String,
+        matched_regex: Regex,
+    },
+    In {
+        field: String,
+        matched_values: Vec<String>,
+    },
+    Compare {
+        field: String,
+        operator: CompareOperator,
+        value: f64,
+        original: String,
+    },
 }
 
 proxmox_serde::forward_deserialize_to_from_str!(FieldMatcher);
@@ -177,33 +329,34 @@ proxmox_serde::forward_serialize_to_display!(FieldMatcher);
 
 impl MatchDirective for FieldMatcher {
     fn matches(&self, notification: &Notification) -> Result<bool, Error> {
+        let field = match self {
+            FieldMatcher::Exact { field, .. }
+            | FieldMatcher::Regex { field, .. }
+            | FieldMatcher::Glob { field, .. }
+            | FieldMatcher::In { field, .. }
+            | FieldMatcher::Compare { field, .. } => field,
+        };
+
+        let value = match notification.metadata.additional_fields.get(field) {
+            Some(value) => value,
+            // Metadata field does not exist, so we do not match
+            None => return Ok(false),
+        };
+
         Ok(match self {
-            FieldMatcher::Exact {
-                field,
-                matched_values,
-            } => {
-                let value = notification.metadata.additional_fields.get(field);
-
-                if let Some(value) = value {
-                    matched_values.contains(value)
-                } else {
-                    // Metadata field does not exist, so we do not match
-                    false
-                }
-            }
-            FieldMatcher::Regex {
-                field,
-                matched_regex,
-            } => {
-                let value = notification.metadata.additional_fields.get(field);
-
-                if let Some(value) = value {
-                    matched_regex.is_match(value)
-                } else {
-                    // Metadata field does not exist, so we do not match
-                    false
-                }
-            }
+            FieldMatcher::Exact { matched_values, .. }
+            | FieldMatcher::In { matched_values, .. } => matched_values.contains(value),
+            FieldMatcher::Regex { matched_regex, .. }
+            | FieldMatcher::Glob { matched_regex, .. } => matched_regex.is_match(value),
+            FieldMatcher::Compare {
+                operator,
+                value: expected,
+                ..
+            } => match parse_number(value) {
+                Some(value) => operator.apply(value, *expected),
+                // Not a number, so we do not match
+                None => false,
+            },
         })
     }
 }
@@ -228,6 +381,20 @@ impl fmt::Display for FieldMatcher {
                 let re = matched_regex.as_str();
                 write!(f, "regex:{field}={re}")
             }
+            FieldMatcher::Glob { field, pattern, .. } => write!(f, "glob:{field}={pattern}"),
+            FieldMatcher::In {
+                field,
+                matched_values,
+            } => {
+                let values = matched_values.join(",");
+                write!(f, "in:{field}={values}")
+            }
+            FieldMatcher::Compare {
+                field,
+                operator,
+                original,
+                ..
+            } => write!(f, "{operator}:{field}={original}"),
         }
     }
 }
@@ -235,54 +402,138 @@ impl fmt::Display for FieldMatcher {
 impl FromStr for FieldMatcher {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self, Error> {
-        if !MATCH_FIELD_ENTRY_REGEX.is_match(s) {
-            return Err(Error::FilterFailed(format!(
-                "invalid match-field statement: {s}"
-            )));
+        let invalid = || Error::FilterFailed(format!("invalid match-field statement: {s}"));
+
+        let captures = MATCH_FIELD_ENTRY_REGEX.captures(s).ok_or_else(invalid)?;
+
+        let field = captures[2].to_string();
+        let value = &captures[3];
+
+        let split_values = || {
+            value
+                .split(',')
+                .map(str::trim)
+                .map(String::from)
+                .collect::<Vec<String>>()
+        };
+
+        let compare = |operator| {
+            let expected = parse_number(value).ok_or_else(|| {
+                Error::FilterFailed(format!("invalid number in match-field statement: {s}"))
+            })?;
+
+            Ok(Self::Compare {
+                field: field.clone(),
+                operator,
+                value: expected,
+                original: value.to_string(),
+            })
+        };
+
+        match captures.get(1).map(|operator| operator.as_str()) {
+            Some("regex") => {
+                let regex = Regex::new(value)
+                    .map_err(|err| Error::FilterFailed(format!("invalid regex: {err}")))?;
+
+                Ok(Self::Regex {
+                    field,
+                    matched_regex: regex,
+                })
+            }
+            Some("exact") => Ok(Self::Exact {
+                field,
+                matched_values: split_values(),
+            }),
+            Some("glob") => Ok(Self::Glob {
+                field,
+                pattern: value.to_string(),
+                matched_regex: glob_to_regex(value)?,
+            }),
+            Some("in") => Ok(Self::In {
+                field,
+                matched_values: split_values(),
+            }),
+            Some("gt") => compare(CompareOperator::Gt),
+            Some("lt") => compare(CompareOperator::Lt),
+            Some("ge") => compare(CompareOperator::Ge),
+            Some("le") => compare(CompareOperator::Le),
+            _ => Err(invalid()),
         }
+    }
+}
 
-        if let Some(remaining) = s.strip_prefix("regex:") {
-            match remaining.split_once('=') {
-                None => Err(Error::FilterFailed(format!(
-                    "invalid match-field statement: {s}"
-                ))),
-                Some((field, expected_value_regex)) => {
-                    let regex = Regex::new(expected_value_regex)
-                        .map_err(|err| Error::FilterFailed(format!("invalid regex: {err}")))?;
-
-                    Ok(Self::Regex {
-                        field: field.into(),
-                        matched_regex: regex,
-                    })
-                }
-            }
-        } else if let Some(remaining) = s.strip_prefix("exact:") {
-            match remaining.split_once('=') {
-                None => Err(Error::FilterFailed(format!(
-                    "invalid match-field statement: {s}"
-                ))),
-                Some((field, expected_values)) => {
-                    let values: Vec<String> = expected_values
-                        .split(',')
-                        .map(str::trim)
-                        .map(String::from)
-                        .collect();
-                    Ok(Self::Exact {
-                        field: field.into(),
-                        matched_values: values,
-                    })
-                }
-            }
-        } else {
-            Err(Error::FilterFailed(format!(
-                "invalid match-field statement: {s}"
-            )))
+/// Parse a number, either a plain one or a byte size with unit (e.g. `1.5 GiB`).
+fn parse_number(s: &str) -> Option<f64> {
+    let s = s.trim();
+
+    s.parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .or_else(|| s.parse::<HumanByte>().ok().map(|bytes| bytes.as_f64()))
+}
+
+/// Convert a glob pattern, where `*` matches any number and `?` a single character, into
+/// an anchored regular expression.
+fn glob_to_regex(pattern: &str) -> Result<Regex, Error> {
+    let mut regex = String::from("^");
+
+    for c in pattern.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
         }
     }
+
+    regex.push('$');
+
+    Regex::new(&regex).map_err(|err| Error::FilterFailed(format!("invalid glob: {err}")))
+}
+
+/// Reference to another matcher via `match-matcher`.
+struct NestedMatcher<'a> {
+    matcher: &'a MatcherConfig,
+    matchers: &'a [MatcherConfig],
+    depth: usize,
+}
+
+impl MatchDirective for NestedMatcher<'_> {
+    fn matches(&self, notification: &Notification) -> Result<bool, Error> {
+        self.matcher
+            .is_match(notification, self.matchers, self.depth)
+    }
 }
 
 impl MatcherConfig {
+    /// Check if the matcher matches a notification and return its targets if it does.
+    ///
+    /// Matchers referenced via `match-matcher` cannot be resolved, use [`Self::matches_with`]
+    /// for matchers which reference others.
     pub fn matches(&self, notification: &Notification) -> Result<Option<&[String]>, Error> {
+        self.matches_with(notification, &[])
+    }
+
+    /// Check if the matcher matches a notification and return its targets if it does.
+    ///
+    /// Matchers referenced via `match-matcher` are looked up in `matchers`.
+    pub fn matches_with(
+        &self,
+        notification: &Notification,
+        matchers: &[MatcherConfig],
+    ) -> Result<Option<&[String]>, Error> {
+        Ok(if self.is_match(notification, matchers, 0)? {
+            Some(&self.target)
+        } else {
+            None
+        })
+    }
+
+    fn is_match(
+        &self,
+        notification: &Notification,
+        matchers: &[MatcherConfig],
+        depth: usize,
+    ) -> Result<bool, Error> {
         let mode = self.mode.unwrap_or_default();
 
         let mut is_match = mode.neutral_element();
@@ -310,14 +561,70 @@ impl MatcherConfig {
                 self.check_matches(notification, &self.match_calendar)?,
             );
         }
+        if !self.match_matcher.is_empty() {
+            no_matchers = false;
+
+            if depth >= MAX_NESTING_DEPTH {
+                return Err(Error::FilterFailed(format!(
+                    "matcher '{name}': nesting too deep",
+                    name = self.name
+                )));
+            }
+
+            let nested = self
+                .match_matcher
+                .iter()
+                .map(|name| {
+                    let matcher = matchers
+                        .iter()
+                        .find(|matcher| &matcher.name == name)
+                        .ok_or_else(|| {
+                            Error::FilterFailed(format!("matcher '{name}' does not exist"))
+                        })?;
+
+                    Ok(NestedMatcher {
+                        matcher,
+                        matchers,
+                        depth: depth + 1,
+                    })
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            is_match = mode.apply(is_match, self.check_matches(notification, &nested)?);
+        }
 
         let invert_match = self.invert_match.unwrap_or_default();
 
-        Ok(if is_match != invert_match || no_matchers {
-            Some(&self.target)
-        } else {
-            None
-        })
+        Ok(is_match != invert_match || no_matchers)
+    }
+
+    /// Length of the `dedup-window` in seconds.
+    #[allow(dead_code)] // Unused in some feature flag permutations
+    pub(crate) fn dedup_window(&self) -> Option<i64> {
+        self.dedup_window.as_ref().map(|window| window.seconds)
+    }
+
+    /// Parsed `digest-schedule`.
+    #[allow(dead_code)] // Unused in some feature flag permutations
+    pub(crate) fn digest_schedule(&self) -> Option<CalendarEvent> {
+        let schedule = self.digest_schedule.as_deref()?;
+
+        match schedule.parse() {
+            Ok(event) => Some(event),
+            Err(err) => {
+                error!(
+                    "matcher '{name}': invalid digest-schedule: {err}",
+                    name = self.name
+                );
+                None
+            }
+        }
+    }
+
+    /// Check if the matcher suppresses or limits any notifications.
+    #[allow(dead_code)] // Unused in some feature flag permutations
+    pub(crate) fn is_throttled(&self) -> bool {
+        self.dedup_window.is_some() || self.rate_limit.is_some()
     }
 
     /// Check if given `MatchDirectives` match a notification.
@@ -414,6 +721,100 @@ impl FromStr for CalendarMatcher {
     }
 }
 
+/// Maximum number of notifications per target within an interval.
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    /// Maximum number of notifications within the interval.
+    pub count: u64,
+    /// Length of the interval in seconds.
+    pub interval: i64,
+    original: String,
+}
+
+proxmox_serde::forward_deserialize_to_from_str!(RateLimit);
+proxmox_serde::forward_serialize_to_display!(RateLimit);
+
+impl UpdaterType for RateLimit {
+    type Updater = Option<Self>;
+}
+
+impl fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.original)
+    }
+}
+
+impl FromStr for RateLimit {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let (count, interval) = s
+            .split_once('/')
+            .ok_or_else(|| Error::Generic(format!("invalid rate limit: {s}")))?;
+
+        let count = count
+            .trim()
+            .parse()
+            .map_err(|err| Error::Generic(format!("invalid rate limit count: {err}")))?;
+
+        let interval: TimeSpan = interval
+            .trim()
+            .parse()
+            .map_err(|err| Error::Generic(format!("invalid rate limit interval: {err}")))?;
+        let interval = f64::from(interval) as i64;
+
+        if interval <= 0 {
+            return Err(Error::Generic(format!("invalid rate limit interval: {s}")));
+        }
+
+        Ok(Self {
+            count,
+            interval,
+            original: s.to_string(),
+        })
+    }
+}
+
+/// Time span within which identical notifications are suppressed.
+#[derive(Clone, Debug)]
+pub struct DedupWindow {
+    /// Length of the window in seconds.
+    pub seconds: i64,
+    original: String,
+}
+
+proxmox_serde::forward_deserialize_to_from_str!(DedupWindow);
+proxmox_serde::forward_serialize_to_display!(DedupWindow);
+
+impl UpdaterType for DedupWindow {
+    type Updater = Option<Self>;
+}
+
+impl fmt::Display for DedupWindow {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.original)
+    }
+}
+
+impl FromStr for DedupWindow {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let span: TimeSpan = s
+            .trim()
+            .parse()
+            .map_err(|err| Error::Generic(format!("invalid dedup window: {err}")))?;
+        let seconds = f64::from(span) as i64;
+
+        if seconds <= 0 {
+            return Err(Error::Generic(format!("invalid dedup window: {s}")));
+        }
+
+        Ok(Self {
+            seconds,
+            original: s.to_string(),
+        })
+    }
+}
+
 #[api]
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -421,6 +822,12 @@ impl FromStr for CalendarMatcher {
 pub enum DeleteableMatcherProperty {
     /// Delete `comment`
     Comment,
+    /// Delete `dedup-field`
+    DedupField,
+    /// Delete `dedup-window`
+    DedupWindow,
+    /// Delete `digest-schedule`
+    DigestSchedule,
     /// Delete `disable`
     Disable,
     /// Delete `invert-match`
@@ -429,10 +836,14 @@ pub enum DeleteableMatcherProperty {
     MatchCalendar,
     /// Delete `match-field`
     MatchField,
+    /// Delete `match-matcher`
+    MatchMatcher,
     /// Delete `match-severity`
     MatchSeverity,
     /// Delete `mode`
     Mode,
+    /// Delete `rate-limit`
+    RateLimit,
     /// Delete `target`
     Target,
 }
@@ -441,7 +852,18 @@ pub fn check_matches<'a>(
     matchers: &'a [MatcherConfig],
     notification: &Notification,
 ) -> HashSet<&'a str> {
-    let mut targets = HashSet::new();
+    matching_matchers(matchers, notification)
+        .into_iter()
+        .flat_map(|matcher| matcher.target.iter().map(|s| s.as_str()))
+        .collect()
+}
+
+/// Return all enabled matchers which match the notification.
+pub(crate) fn matching_matchers<'a>(
+    matchers: &'a [MatcherConfig],
+    notification: &Notification,
+) -> Vec<&'a MatcherConfig> {
+    let mut matching = Vec::new();
 
     for matcher in matchers {
         if matcher.disable.unwrap_or_default() {
@@ -450,16 +872,88 @@ pub fn check_matches<'a>(
             continue;
         }
 
-        match matcher.matches(notification) {
-            Ok(t) => {
-                let t = t.unwrap_or_default();
-                targets.extend(t.iter().map(|s| s.as_str()));
-            }
+        match matcher.matches_with(notification, matchers) {
+            Ok(Some(_)) => matching.push(matcher),
+            Ok(None) => {}
             Err(err) => error!("matcher '{matcher}' failed: {err}", matcher = matcher.name),
         }
     }
 
-    targets
+    matching
+}
+
+/// Check that all matchers referenced via `match-matcher` exist and that there are no cycles.
+pub(crate) fn verify_matcher_references(matchers: &[MatcherConfig]) -> Result<(), Error> {
+    match invalid_matcher_references(matchers).into_iter().next() {
+        Some((_name, err)) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Find all matchers which reference unknown matchers, are part of a cycle or nested too
+/// deeply, directly or via the matchers they reference.
+///
+/// Returns the names of the affected matchers together with the problem.
+pub(crate) fn invalid_matcher_references(matchers: &[MatcherConfig]) -> Vec<(&str, Error)> {
+    let by_name: HashMap<&str, &MatcherConfig> = matchers
+        .iter()
+        .map(|matcher| (matcher.name.as_str(), matcher))
+        .collect();
+
+    fn visit<'a>(
+        matcher: &'a MatcherConfig,
+        by_name: &HashMap<&str, &'a MatcherConfig>,
+        path: &mut Vec<&'a str>,
+        verified: &mut HashSet<&'a str>,
+    ) -> Result<(), Error> {
+        if verified.contains(matcher.name.as_str()) {
+            return Ok(());
+        }
+
+        if path.contains(&matcher.name.as_str()) {
+            path.push(&matcher.name);
+            return Err(Error::FilterFailed(format!(
+                "matchers reference each other: {}",
+                path.join(" -> ")
+            )));
+        }
+
+        if path.len() >= MAX_NESTING_DEPTH {
+            return Err(Error::FilterFailed(format!(
+                "matcher '{name}': nesting too deep",
+                name = matcher.name
+            )));
+        }
+
+        path.push(&matcher.name);
+
+        for name in &matcher.match_matcher {
+            let nested = by_name.get(name.as_str()).ok_or_else(|| {
+                Error::FilterFailed(format!(
+                    "matcher '{parent}' references unknown matcher '{name}'",
+                    parent = matcher.name
+                ))
+            })?;
+
+            visit(nested, by_name, path, verified)?;
+        }
+
+        path.pop();
+        verified.insert(&matcher.name);
+
+        Ok(())
+    }
+
+    let mut verified = HashSet::new();
+    let mut invalid = Vec::new();
+
+    for matcher in matchers {
+        if let Err(err) = visit(matcher, &by_name, &mut Vec::new(), &mut verified) {
+            invalid.push((matcher.name.as_str(), err));
+        }
+    }
+
+    invalid
 }
 
 #[cfg(test)]
@@ -505,6 +999,51 @@ mod tests {
         assert!("regex:'3=b.*".parse::<FieldMatcher>().is_err());
         assert!("invalid:'bar=b.*".parse::<FieldMatcher>().is_err());
     }
+
+    #[test]
+    fn test_field_operators() {
+        let fields = HashMap::from([
+            ("job".to_string(), "backup-vm100".to_string()),
+            ("duration".to_string(), "3600".to_string()),
+            ("size".to_string(), "2 GiB".to_string()),
+        ]);
+
+        let notification =
+            Notification::from_template(Severity::Notice, "test", Value::Null, fields);
+
+        let matches = |statement: &str| {
+            let matcher: FieldMatcher = statement.parse().unwrap();
+            // must survive a round trip through the config
+            assert_eq!(matcher.to_string(), statement);
+            matcher.matches(&notification).unwrap()
+        };
+
+        assert!(matches("glob:job=backup-*"));
+        assert!(matches("glob:job=backup-vm1??"));
+        assert!(!matches("glob:job=backup"));
+        assert!(!matches("glob:job=*.vm100"));
+
+        assert!(matches("in:job=backup-vm100,backup-vm101"));
+        assert!(!matches("in:job=backup-vm101"));
+
+        assert!(matches("gt:duration=60"));
+        assert!(matches("ge:duration=3600"));
+        assert!(matches("le:duration=3600"));
+        assert!(!matches("lt:duration=3600"));
+        assert!(matches("gt:size=1 GiB"));
+        assert!(matches("lt:size=2147483649"));
+
+        // non-numeric values never match numeric comparisons
+        assert!(!matches("gt:job=0"));
+        assert!(!matches("gt:notthere=0"));
+
+        assert!("gt:duration=foo".parse::<FieldMatcher>().is_err());
+        assert!("lt:duration=".parse::<FieldMatcher>().is_err());
+        assert!("eq:duration=1".parse::<FieldMatcher>().is_err());
+        assert!(verify_field_matcher("ge:duration=1.5").is_ok());
+        assert!(verify_field_matcher("ge:duration=1,5").is_err());
+    }
+
     #[test]
     fn test_severities() {
         let notification =
@@ -514,6 +1053,113 @@ mod tests {
         assert!(matcher.matches(&notification).unwrap());
     }
 
+    #[test]
+    fn test_rate_limit() {
+        let limit: RateLimit = "10/1h".parse().unwrap();
+        assert_eq!(limit.count, 10);
+        assert_eq!(limit.interval, 3600);
+        assert_eq!(limit.to_string(), "10/1h");
+
+        let limit: RateLimit = "3 / 1h 30min".parse().unwrap();
+        assert_eq!(limit.count, 3);
+        assert_eq!(limit.interval, 5400);
+
+        assert!("10".parse::<RateLimit>().is_err());
+        assert!("-1/1h".parse::<RateLimit>().is_err());
+        assert!("10/0s".parse::<RateLimit>().is_err());
+        assert!("10/foo".parse::<RateLimit>().is_err());
+    }
+
+    #[test]
+    fn test_dedup_window() {
+        let window: DedupWindow = "1h 30min".parse().unwrap();
+        assert_eq!(window.seconds, 5400);
+        assert_eq!(window.to_string(), "1h 30min");
+
+        assert!("0s".parse::<DedupWindow>().is_err());
+        assert!("foo".parse::<DedupWindow>().is_err());
+    }
+
+    #[test]
+    fn test_nested_matchers() {
+        // (severity=error OR type=gc) AND NOT datastore=scratch
+        let matchers = vec![
+            MatcherConfig {
+                name: "top".into(),
+                match_matcher: vec!["error-or-gc".into(), "scratch".into()],
+                target: vec!["foo".into()],
+                ..Default::default()
+            },
+            MatcherConfig {
+                name: "error-or-gc".into(),
+                mode: Some(MatchModeOperator::Any),
+                match_severity: vec!["error".parse().unwrap()],
+                match_field: vec!["exact:type=gc".parse().unwrap()],
+                ..Default::default()
+            },
+            MatcherConfig {
+                name: "scratch".into(),
+                match_field: vec!["exact:datastore=scratch".parse().unwrap()],
+                invert_match: Some(true),
+                ..Default::default()
+            },
+        ];
+
+        verify_matcher_references(&matchers).unwrap();
+
+        let notification = |severity, ty: &str, datastore: &str| {
+            let fields = HashMap::from([
+                ("type".to_string(), ty.to_string()),
+                ("datastore".to_string(), datastore.to_string()),
+            ]);
+            Notification::from_template(severity, "test", Value::Null, fields)
+        };
+
+        let top = &matchers[0];
+        let matches = |n: &Notification| top.matches_with(n, &matchers).unwrap().is_some();
+
+        assert!(matches(&notification(Severity::Error, "backup", "store")));
+        assert!(matches(&notification(Severity::Info, "gc", "store")));
+        assert!(!matches(&notification(Severity::Info, "backup", "store")));
+        assert!(!matches(&notification(Severity::Error, "gc", "scratch")));
+
+        // references cannot be resolved without the other matchers
+        assert!(top
+            .matches(&notification(Severity::Error, "gc", "store"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_matcher_reference_cycle() {
+        let matcher = |name: &str, nested: &str| MatcherConfig {
+            name: name.into(),
+            match_matcher: vec![nested.into()],
+            ..Default::default()
+        };
+
+        assert!(verify_matcher_references(&[matcher("a", "b"), matcher("b", "a")]).is_err());
+        assert!(verify_matcher_references(&[matcher("a", "a")]).is_err());
+        assert!(verify_matcher_references(&[matcher("a", "c")]).is_err());
+
+        let matchers = [
+            matcher("a", "b"),
+            matcher("b", "a"),
+            matcher("c", "a"),
+            matcher("d", "e"),
+            MatcherConfig {
+                name: "e".into(),
+                ..Default::default()
+            },
+        ];
+        let invalid = invalid_matcher_references(&matchers);
+        let names: Vec<&str> = invalid.iter().map(|(name, _)| *name).collect();
+        assert_eq!(names, ["a", "b", "c"]);
+
+        // invalid references must not prevent loading the config
+        let config = "matcher: m1\n\tmatch-matcher m2\n\nmatcher: m2\n\tmatch-matcher m1\n";
+        crate::Config::new(config, "").unwrap();
+    }
+
     #[test]
     fn test_empty_matcher_matches_always() {
         let notification =
@@ -0,0 +1,528 @@
+//! Persistent queue for notifications which could not be delivered.
+//!
+//! If sending a notification via a target fails, the notification is spooled to disk and
+//! delivery is retried later. Every target backs off exponentially on its own, and entries
+//! which are older than the maximum age of the [`RetryPolicy`] are dropped.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+use proxmox_schema::api;
+use proxmox_sys::fs::CreateOptions;
+use proxmox_uuid::Uuid;
+
+use crate::context::context;
+use crate::schema::ENTITY_NAME_SCHEMA;
+use crate::{Endpoint, Error, Notification};
+
+const LOCK_FILE: &str = ".lock";
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Retry policy for queued notifications. All values are in seconds.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Delay before the first retry.
+    pub initial_delay: i64,
+    /// Upper bound for the delay between two retries.
+    pub max_delay: i64,
+    /// Queued notifications older than this are dropped.
+    pub max_age: i64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: 60,
+            max_delay: 3600,
+            max_age: 86400,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before the next attempt after `attempts` failed attempts.
+    fn delay(&self, attempts: u32) -> i64 {
+        let factor = 1i64 << attempts.saturating_sub(1).min(30);
+        self.initial_delay
+            .saturating_mul(factor)
+            .min(self.max_delay)
+    }
+}
+
+#[api]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+/// A notification waiting for redelivery via a single target.
+pub struct QueuedNotification {
+    /// Unique ID of the notification.
+    pub id: String,
+    /// Target the notification could not be delivered to.
+    pub target: String,
+    /// Time the notification was queued (UNIX epoch).
+    pub queued: i64,
+    /// Number of failed delivery attempts.
+    pub attempts: u32,
+    /// Time of the next delivery attempt (UNIX epoch).
+    pub next_attempt: i64,
+    /// Error of the last failed delivery attempt.
+    pub last_error: String,
+}
+
+/// On-disk representation of a queue entry.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct QueueEntry {
+    #[serde(flatten)]
+    info: QueuedNotification,
+    notification: Notification,
+}
+
+/// Notification queue stored in a spool directory.
+///
+/// Every entry is stored in its own file at `<path>/<target>/<id>.json`.
+pub struct NotificationQueue {
+    path: PathBuf,
+    policy: RetryPolicy,
+}
+
+impl NotificationQueue {
+    /// Create a queue handle for the spool directory at `path`.
+    pub fn new<P: Into<PathBuf>>(path: P, policy: RetryPolicy) -> Self {
+        Self {
+            path: path.into(),
+            policy,
+        }
+    }
+
+    /// Create a queue handle in the product's state directory, if there is one.
+    pub(crate) fn from_context() -> Option<Self> {
+        context()
+            .state_directory()
+            .map(|dir| Self::new(Path::new(dir).join("queue"), RetryPolicy::default()))
+    }
+
+    fn entry_path(&self, target: &str, id: &str) -> PathBuf {
+        self.path.join(target).join(format!("{id}.json"))
+    }
+
+    /// Lock the queue, serializing the processing of queued entries.
+    fn lock(&self) -> Result<File, Error> {
+        proxmox_sys::fs::create_path(&self.path, None, None)
+            .map_err(|err| Error::Generic(format!("could not create queue directory: {err}")))?;
+
+        proxmox_sys::fs::open_file_locked(
+            self.path.join(LOCK_FILE),
+            LOCK_TIMEOUT,
+            true,
+            CreateOptions::new(),
+        )
+        .map_err(|err| Error::Generic(format!("could not lock notification queue: {err}")))
+    }
+
+    fn write_entry(&self, entry: &QueueEntry) -> Result<(), Error> {
+        let path = self.entry_path(&entry.info.target, &entry.info.id);
+
+        if let Some(parent) = path.parent() {
+            proxmox_sys::fs::create_path(parent, None, None).map_err(|err| {
+                Error::Generic(format!("could not create queue directory: {err}"))
+            })?;
+        }
+
+        let data = serde_json::to_vec(entry)
+            .map_err(|err| Error::Generic(format!("could not serialize queue entry: {err}")))?;
+
+        proxmox_sys::fs::replace_file(path, &data, CreateOptions::new(), true)
+            .map_err(|err| Error::Generic(format!("could not write queue entry: {err}")))
+    }
+
+    fn remove_entry(&self, target: &str, id: &str) -> Result<(), Error> {
+        match std::fs::remove_file(self.entry_path(target, id)) {
+            Ok(()) => Ok(()),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(Error::Generic(format!(
+                "could not remove queue entry: {err}"
+            ))),
+        }
+    }
+
+    fn read_entry(path: &Path) -> Result<QueueEntry, Error> {
+        let data = std::fs::read(path)
+            .map_err(|err| Error::Generic(format!("could not read {path:?}: {err}")))?;
+
+        serde_json::from_slice(&data)
+            .map_err(|err| Error::Generic(format!("could not parse {path:?}: {err}")))
+    }
+
+    /// Load all entries, oldest first.
+    fn load_entries(&self) -> Result<Vec<QueueEntry>, Error> {
+        let mut entries = Vec::new();
+
+        let target_dirs = match std::fs::read_dir(&self.path) {
+            Ok(dirs) => dirs,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(entries),
+            Err(err) => {
+                return Err(Error::Generic(format!(
+                    "could not read notification queue: {err}"
+                )))
+            }
+        };
+
+        for target_dir in target_dirs {
+            let target_dir = target_dir
+                .map_err(|err| Error::Generic(format!("could not read notification queue: {err}")))?
+                .path();
+
+            if !target_dir.is_dir() {
+                continue;
+            }
+
+            let files = std::fs::read_dir(&target_dir)
+                .map_err(|err| Error::Generic(format!("could not read {target_dir:?}: {err}")))?;
+
+            for file in files {
+                let path = match file {
+                    Ok(file) => file.path(),
+                    Err(err) => {
+                        warn!("could not read queue entry in {target_dir:?}: {err}");
+                        continue;
+                    }
+                };
+
+                if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+                    continue;
+                }
+
+                match Self::read_entry(&path) {
+                    Ok(entry) => entries.push(entry),
+                    Err(err) => warn!("skipping invalid queue entry: {err}"),
+                }
+            }
+        }
+
+        entries.sort_by_key(|entry| entry.info.queued);
+
+        Ok(entries)
+    }
+
+    /// Queue a notification after delivery via `target` failed with `err`.
+    pub fn enqueue(
+        &self,
+        target: &str,
+        notification: &Notification,
+        err: &Error,
+    ) -> Result<(), Error> {
+        let now = proxmox_time::epoch_i64();
+
+        let entry = QueueEntry {
+            info: QueuedNotification {
+                id: notification.id().to_string(),
+                target: target.to_string(),
+                queued: now,
+                attempts: 1,
+                next_attempt: now + self.policy.delay(1),
+                last_error: err.to_string(),
+            },
+            notification: notification.clone(),
+        };
+
+        self.write_entry(&entry)
+    }
+
+    /// List all queued notifications, oldest first.
+    pub fn list(&self) -> Result<Vec<QueuedNotification>, Error> {
+        Ok(self
+            .load_entries()?
+            .into_iter()
+            .map(|entry| entry.info)
+            .collect())
+    }
+
+    /// Drop a queued notification without delivering it.
+    pub fn remove(&self, id: &str, target: &str) -> Result<(), Error> {
+        verify_entry_id(id, target)?;
+        let _lock = self.lock()?;
+
+        if !self.entry_path(target, id).exists() {
+            return Err(Error::NotQueued(id.to_string(), target.to_string()));
+        }
+
+        self.remove_entry(target, id)
+    }
+
+    /// Immediately retry delivering a single queued notification, using `send`.
+    ///
+    /// On failure, the entry is rescheduled and the error is returned.
+    pub(crate) fn retry(
+        &self,
+        endpoints: &HashMap<String, Box<dyn Endpoint>>,
+        send: &dyn Fn(&dyn Endpoint, &Notification) -> Result<(), Error>,
+        id: &str,
+        target: &str,
+    ) -> Result<(), Error> {
+        verify_entry_id(id, target)?;
+        let _lock = self.lock()?;
+
+        let path = self.entry_path(target, id);
+        if !path.exists() {
+            return Err(Error::NotQueued(id.to_string(), target.to_string()));
+        }
+
+        let mut entry = Self::read_entry(&path)?;
+
+        let endpoint = endpoints
+            .get(target)
+            .ok_or_else(|| Error::TargetDoesNotExist(target.to_string()))?;
+
+        match send(endpoint.as_ref(), &entry.notification) {
+            Ok(()) => self.remove_entry(target, id),
+            Err(err) => {
+                self.reschedule(&mut entry, &err)?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Retry delivering all queued notifications which are due, using `send`.
+    ///
+    /// Entries exceeding the maximum age or referring to targets which do not exist anymore
+    /// are dropped. Once delivery via a target fails, the remaining entries for this target
+    /// are left alone until the next run.
+    pub(crate) fn process(
+        &self,
+        endpoints: &HashMap<String, Box<dyn Endpoint>>,
+        send: &dyn Fn(&dyn Endpoint, &Notification) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let _lock = self.lock()?;
+
+        let now = proxmox_time::epoch_i64();
+        let mut failed_targets = HashSet::new();
+
+        for mut entry in self.load_entries()? {
+            let id = entry.info.id.clone();
+            let target = entry.info.target.clone();
+
+            if now - entry.info.queued > self.policy.max_age {
+                warn!(
+                    "dropping notification {id} for target `{target}` after {attempts} failed attempts",
+                    attempts = entry.info.attempts
+                );
+                self.remove_entry(&target, &id)?;
+                continue;
+            }
+
+            if entry.info.next_attempt > now || failed_targets.contains(&target) {
+                continue;
+            }
+
+            let endpoint = match endpoints.get(&target) {
+                Some(endpoint) => endpoint,
+                None => {
+                    warn!("dropping queued notification {id}: target `{target}` does not exist");
+                    self.remove_entry(&target, &id)?;
+                    continue;
+                }
+            };
+
+            if endpoint.disabled() {
+                continue;
+            }
+
+            match send(endpoint.as_ref(), &entry.notification) {
+                Ok(()) => {
+                    info!("delivered queued notification {id} via target `{target}`");
+                    self.remove_entry(&target, &id)?;
+                }
+                Err(err) => {
+                    error!(
+                        "could not deliver queued notification {id} via target `{target}`: {err}"
+                    );
+                    self.reschedule(&mut entry, &err)?;
+                    failed_targets.insert(target);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn reschedule(&self, entry: &mut QueueEntry, err: &Error) -> Result<(), Error> {
+        entry.info.attempts += 1;
+        entry.info.next_attempt =
+            proxmox_time::epoch_i64() + self.policy.delay(entry.info.attempts);
+        entry.info.last_error = err.to_string();
+
+        self.write_entry(entry)
+    }
+}
+
+/// Make sure that `id` and `target` cannot be used to escape the queue directory.
+pub(crate) fn verify_entry_id(id: &str, target: &str) -> Result<(), Error> {
+    Uuid::parse_str(id).map_err(|err| Error::Generic(format!("invalid notification id: {err}")))?;
+
+    ENTITY_NAME_SCHEMA
+        .unwrap_string_schema()
+        .check_constraints(target)
+        .map_err(|err| Error::Generic(format!("invalid target name: {err}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::*;
+    use crate::matcher::MatcherConfig;
+    use crate::{Bus, Severity};
+
+    struct FlakyEndpoint {
+        failures: Rc<Cell<u32>>,
+        delivered: Rc<Cell<u32>>,
+    }
+
+    impl Endpoint for FlakyEndpoint {
+        fn send(&self, _notification: &Notification) -> Result<(), Error> {
+            if self.failures.get() > 0 {
+                self.failures.set(self.failures.get() - 1);
+                return Err(Error::Generic("target unreachable".into()));
+            }
+
+            self.delivered.set(self.delivered.get() + 1);
+            Ok(())
+        }
+
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn disabled(&self) -> bool {
+            false
+        }
+    }
+
+    fn test_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("proxmox-notify-queue-{}", Uuid::generate()))
+    }
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy {
+            initial_delay: 10,
+            max_delay: 100,
+            max_age: 1000,
+        };
+
+        assert_eq!(policy.delay(1), 10);
+        assert_eq!(policy.delay(2), 20);
+        assert_eq!(policy.delay(4), 80);
+        assert_eq!(policy.delay(5), 100);
+        assert_eq!(policy.delay(u32::MAX), 100);
+    }
+
+    #[test]
+    fn test_failed_delivery_is_queued_and_retried() -> Result<(), Error> {
+        let dir = test_dir();
+        let failures = Rc::new(Cell::new(2));
+        let delivered = Rc::new(Cell::new(0));
+
+        let policy = RetryPolicy {
+            initial_delay: 0,
+            max_delay: 0,
+            max_age: 3600,
+        };
+
+        let mut bus = Bus::default();
+        bus.add_endpoint(Box::new(FlakyEndpoint {
+            failures: failures.clone(),
+            delivered: delivered.clone(),
+        }));
+        bus.add_matcher(MatcherConfig {
+            target: vec!["flaky".into()],
+            ..Default::default()
+        });
+        bus.set_queue(NotificationQueue::new(&dir, policy));
+
+        let notification = Notification::from_template(
+            Severity::Error,
+            "test",
+            Default::default(),
+            Default::default(),
+        );
+        bus.send(&notification);
+
+        let queue = NotificationQueue::new(&dir, policy);
+        let queued = queue.list()?;
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].id, notification.id().to_string());
+        assert_eq!(queued[0].attempts, 1);
+
+        bus.process_queue()?;
+        let queued = queue.list()?;
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].attempts, 2);
+        assert_eq!(delivered.get(), 0);
+
+        bus.process_queue()?;
+        assert!(queue.list()?.is_empty());
+        assert_eq!(delivered.get(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_remove_and_expire() -> Result<(), Error> {
+        let dir = test_dir();
+        let queue = NotificationQueue::new(
+            &dir,
+            RetryPolicy {
+                initial_delay: 0,
+                max_delay: 0,
+                max_age: -1,
+            },
+        );
+
+        let notification = Notification::from_template(
+            Severity::Error,
+            "test",
+            Default::default(),
+            Default::default(),
+        );
+        let id = notification.id().to_string();
+        let err = Error::Generic("target unreachable".into());
+
+        queue.enqueue("flaky", &notification, &err)?;
+        assert!(matches!(
+            queue.remove(&id, "other"),
+            Err(Error::NotQueued(..))
+        ));
+        assert!(queue.remove("../../etc", "flaky").is_err());
+        queue.remove(&id, "flaky")?;
+        assert!(queue.list()?.is_empty());
+
+        // entries exceeding the maximum age are dropped without being sent
+        let delivered = Rc::new(Cell::new(0));
+        let mut endpoints: HashMap<String, Box<dyn Endpoint>> = HashMap::new();
+        endpoints.insert(
+            "flaky".into(),
+            Box::new(FlakyEndpoint {
+                failures: Rc::new(Cell::new(0)),
+                delivered: delivered.clone(),
+            }),
+        );
+
+        queue.enqueue("flaky", &notification, &err)?;
+        queue.process(&endpoints, &|endpoint, notification| {
+            endpoint.send(notification)
+        })?;
+        assert!(queue.list()?.is_empty());
+        assert_eq!(delivered.get(), 0);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+//! Rendering of digests, which combine the content of several notifications into one.
+
+use handlebars::html_escape;
+
+use super::{render_template, TemplateType};
+use crate::{Content, Error};
+
+/// Render the subject and the plaintext or HTML body of a single notification's content.
+pub(crate) fn render_content(ty: TemplateType, content: &Content) -> Result<String, Error> {
+    match content {
+        Content::Template {
+            template_name,
+            data,
+        } => render_template(ty, template_name, data),
+        Content::Digest { entries, omitted } => render_digest(ty, entries, *omitted),
+        #[cfg(feature = "mail-forwarder")]
+        Content::ForwardedMail { title, body, .. } => Ok(match ty {
+            TemplateType::Subject => title.clone(),
+            TemplateType::PlaintextBody => body.clone(),
+            TemplateType::HtmlBody | TemplateType::HtmlBodyFromPlaintext => {
+                TemplateType::HtmlBodyFromPlaintext.postprocess(body.clone())
+            }
+        }),
+    }
+}
+
+/// Render several notifications as a single one.
+///
+/// The subject is the one of the first notification, followed by the number of further
+/// notifications. The body contains the subject and body of every notification, `omitted`
+/// is the number of notifications which did not make it into the digest.
+pub fn render_digest(
+    ty: TemplateType,
+    entries: &[Content],
+    omitted: usize,
+) -> Result<String, Error> {
+    let total = entries.len() + omitted;
+
+    match ty {
+        TemplateType::Subject => {
+            let first = match entries.first() {
+                Some(first) => render_content(TemplateType::Subject, first)?,
+                None => return Ok(format!("{total} notifications")),
+            };
+
+            if total > 1 {
+                Ok(format!("{first} (+{more} more)", more = total - 1))
+            } else {
+                Ok(first)
+            }
+        }
+        TemplateType::PlaintextBody => {
+            let mut rendered = String::new();
+
+            for entry in entries {
+                let subject = render_content(TemplateType::Subject, entry)?;
+                let body = render_content(TemplateType::PlaintextBody, entry)?;
+
+                rendered += &format!("{subject}\n{}\n\n{body}\n\n", "=".repeat(subject.len()));
+            }
+
+            if omitted > 0 {
+                rendered += &format!("{omitted} more notifications were omitted.\n");
+            }
+
+            Ok(rendered)
+        }
+        TemplateType::HtmlBodyFromPlaintext => {
+            let rendered = render_digest(TemplateType::PlaintextBody, entries, omitted)?;
+            Ok(ty.postprocess(rendered))
+        }
+        TemplateType::HtmlBody => {
+            let mut rendered = String::from("<html><body>\n");
+
+            for entry in entries {
+                let subject = render_content(TemplateType::Subject, entry)?;
+                let body = render_content(TemplateType::HtmlBody, entry)?;
+
+                rendered += &format!(
+                    "<h2>{subject}</h2>\n{body}\n<hr>\n",
+                    subject = html_escape(&subject),
+                    body = html_body_content(&body),
+                );
+            }
+
+            if omitted > 0 {
+                rendered += &format!("<p>{omitted} more notifications were omitted.</p>\n");
+            }
+
+            rendered += "</body></html>";
+
+            Ok(rendered)
+        }
+    }
+}
+
+/// Strip the surrounding `<html>` and `<body>` elements from a rendered HTML body, so that
+/// several of them can be embedded into one document.
+fn html_body_content(html: &str) -> &str {
+    let start = html
+        .find("<body")
+        .and_then(|start| html[start..].find('>').map(|end| start + end + 1));
+
+    match (start, html.rfind("</body>")) {
+        (Some(start), Some(end)) if start <= end => &html[start..end],
+        _ => html,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_body_content() {
+        assert_eq!(
+            html_body_content("<html><body class=\"x\"><p>foo</p></body></html>"),
+            "<p>foo</p>"
+        );
+        assert_eq!(html_body_content("<p>foo</p>"), "<p>foo</p>");
+    }
+}
@@ -15,10 +15,23 @@ use proxmox_time::TimeSpan;
 
 use crate::{context, Error};
 
+mod digest;
 mod html;
 mod plaintext;
 mod table;
 
+#[cfg(any(
+    feature = "gotify",
+    feature = "matrix",
+    feature = "ntfy",
+    feature = "sendmail",
+    feature = "slack",
+    feature = "smtp",
+    feature = "webhook"
+))]
+pub(crate) use digest::render_content;
+pub use digest::render_digest;
+
 /// Convert a serde_json::Value to a String.
 ///
 /// The main difference between this and simply calling Value::to_string is that
@@ -0,0 +1,85 @@
+//! Persistent state kept as a single locked JSON file in a directory.
+
+use std::fs::File;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tracing::warn;
+
+use proxmox_sys::fs::CreateOptions;
+
+use crate::Error;
+
+const STATE_FILE: &str = "state.json";
+const LOCK_FILE: &str = ".lock";
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// JSON state stored in a directory, protected by a lock file.
+pub(crate) struct StateFile {
+    path: PathBuf,
+    /// Name of the state used in error messages
+    name: &'static str,
+}
+
+impl StateFile {
+    pub(crate) fn new<P: Into<PathBuf>>(path: P, name: &'static str) -> Self {
+        Self {
+            path: path.into(),
+            name,
+        }
+    }
+
+    /// Run `func` on the locked state and persist the result.
+    ///
+    /// A missing state file yields the default state, an invalid one is discarded.
+    pub(crate) fn with_state<T, R>(&self, func: impl FnOnce(&mut T) -> R) -> Result<R, Error>
+    where
+        T: Default + Serialize + DeserializeOwned,
+    {
+        let name = self.name;
+
+        proxmox_sys::fs::create_path(&self.path, None, None)
+            .map_err(|err| Error::Generic(format!("could not create {name} directory: {err}")))?;
+
+        let _lock: File = proxmox_sys::fs::open_file_locked(
+            self.path.join(LOCK_FILE),
+            LOCK_TIMEOUT,
+            true,
+            CreateOptions::new(),
+        )
+        .map_err(|err| Error::Generic(format!("could not lock {name} state: {err}")))?;
+
+        let mut state = self.read()?;
+
+        let result = func(&mut state);
+
+        let data = serde_json::to_vec(&state)
+            .map_err(|err| Error::Generic(format!("could not serialize {name} state: {err}")))?;
+        proxmox_sys::fs::replace_file(
+            self.path.join(STATE_FILE),
+            &data,
+            CreateOptions::new(),
+            false,
+        )
+        .map_err(|err| Error::Generic(format!("could not write {name} state: {err}")))?;
+
+        Ok(result)
+    }
+
+    fn read<T: Default + DeserializeOwned>(&self) -> Result<T, Error> {
+        let name = self.name;
+
+        let content = proxmox_sys::fs::file_read_optional_string(self.path.join(STATE_FILE))
+            .map_err(|err| Error::Generic(format!("could not read {name} state: {err}")))?;
+
+        match content {
+            Some(content) => Ok(serde_json::from_str(&content).unwrap_or_else(|err| {
+                warn!("discarding invalid {name} state: {err}");
+                Default::default()
+            })),
+            None => Ok(Default::default()),
+        }
+    }
+}
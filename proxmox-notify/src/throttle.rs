@@ -0,0 +1,406 @@
+//! Deduplication and rate limiting of notifications.
+//!
+//! Matchers can suppress notifications which are identical to one they matched recently
+//! (`dedup-window`) and cap the number of notifications sent to each of their targets within
+//! an interval (`rate-limit`). Suppressed notifications are summarized in a digest, which is
+//! sent to the affected targets once the window or interval has passed.
+//!
+//! The state is kept in the product's state directory, so it survives process restarts.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use proxmox_uuid::Uuid;
+
+use crate::context::context;
+use crate::matcher::MatcherConfig;
+use crate::state::StateFile;
+use crate::{Content, Error, Metadata, Notification, Severity};
+
+/// Value of the `type` metadata field of digests of suppressed notifications.
+pub const SUPPRESSED_TYPE: &str = "suppressed";
+
+/// Maximum number of suppressed notifications listed in a single digest.
+const MAX_DIGEST_ENTRIES: usize = 50;
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Suppressed {
+    count: usize,
+    entries: Vec<Notification>,
+}
+
+impl Suppressed {
+    fn add(&mut self, notification: &Notification) {
+        self.count += 1;
+
+        if self.entries.len() < MAX_DIGEST_ENTRIES {
+            self.entries.push(notification.clone());
+        }
+    }
+
+    /// Create a digest notification, if anything was suppressed.
+    fn digest(&self, matcher: &str, now: i64) -> Option<Notification> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let severity = self
+            .entries
+            .iter()
+            .map(|entry| entry.metadata.severity)
+            .reduce(|a, b| if b > a { b } else { a })
+            .unwrap_or(Severity::Info);
+
+        let mut fields = HashMap::new();
+        fields.insert("type".into(), SUPPRESSED_TYPE.into());
+        fields.insert("matcher".into(), matcher.into());
+
+        Some(Notification {
+            content: Content::Digest {
+                entries: self
+                    .entries
+                    .iter()
+                    .map(|entry| entry.content.clone())
+                    .collect(),
+                omitted: self.count - self.entries.len(),
+            },
+            metadata: Metadata {
+                severity,
+                timestamp: now,
+                additional_fields: fields,
+            },
+            id: Uuid::generate(),
+        })
+    }
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct DedupEntry {
+    last_sent: i64,
+    suppressed: Suppressed,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RateEntry {
+    start: i64,
+    sent: u64,
+    suppressed: Suppressed,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct MatcherState {
+    /// Deduplication state, keyed by the hash of the compared metadata
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    dedup: HashMap<String, DedupEntry>,
+    /// Rate limiting state, keyed by target
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    rate: HashMap<String, RateEntry>,
+}
+
+impl MatcherState {
+    fn is_empty(&self) -> bool {
+        self.dedup.is_empty() && self.rate.is_empty()
+    }
+}
+
+/// Result of throttling a notification.
+#[derive(Default)]
+pub(crate) struct Throttled {
+    /// Targets the notification should be sent to
+    pub(crate) targets: HashSet<String>,
+    /// Digests of previously suppressed notifications, with their target
+    pub(crate) digests: Vec<(String, Notification)>,
+}
+
+/// Throttling state stored in a state directory.
+pub(crate) struct Throttle {
+    state: StateFile,
+}
+
+impl Throttle {
+    pub(crate) fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            state: StateFile::new(path, "throttle"),
+        }
+    }
+
+    /// Create a handle for the product's state directory, if there is one.
+    pub(crate) fn from_context() -> Option<Self> {
+        context()
+            .state_directory()
+            .map(|dir| Self::new(Path::new(dir).join("throttle")))
+    }
+
+    /// Decide which targets of the `matched` matchers receive the notification.
+    ///
+    /// Also returns digests for all windows and intervals which have passed in the meantime.
+    pub(crate) fn apply(
+        &self,
+        matched: &[&MatcherConfig],
+        notification: &Notification,
+    ) -> Result<Throttled, Error> {
+        let mut result = Throttled::default();
+
+        if !matched.iter().any(|matcher| matcher.is_throttled()) {
+            result.targets = matched
+                .iter()
+                .flat_map(|matcher| matcher.target.iter().cloned())
+                .collect();
+
+            return Ok(result);
+        }
+
+        let now = notification.timestamp();
+
+        self.state
+            .with_state(|state: &mut HashMap<String, MatcherState>| {
+                for matcher in matched {
+                    let matcher_state = state.entry(matcher.name.clone()).or_default();
+                    expire(matcher, matcher_state, now, &mut result.digests);
+
+                    if let Some(window) = matcher.dedup_window() {
+                        let key = dedup_key(matcher, notification);
+
+                        if let Some(entry) = matcher_state.dedup.get_mut(&key) {
+                            if now < entry.last_sent + window {
+                                entry.suppressed.add(notification);
+                                continue;
+                            }
+                        }
+
+                        matcher_state.dedup.insert(
+                            key,
+                            DedupEntry {
+                                last_sent: now,
+                                ..Default::default()
+                            },
+                        );
+                    }
+
+                    for target in &matcher.target {
+                        let limit = match &matcher.rate_limit {
+                            Some(limit) => limit,
+                            None => {
+                                result.targets.insert(target.clone());
+                                continue;
+                            }
+                        };
+
+                        let entry =
+                            matcher_state
+                                .rate
+                                .entry(target.clone())
+                                .or_insert_with(|| RateEntry {
+                                    start: now,
+                                    ..Default::default()
+                                });
+
+                        if entry.sent < limit.count {
+                            entry.sent += 1;
+                            result.targets.insert(target.clone());
+                        } else {
+                            entry.suppressed.add(notification);
+                        }
+                    }
+                }
+
+                result
+            })
+    }
+
+    /// Collect digests for all windows and intervals which have passed.
+    ///
+    /// State belonging to matchers which do not exist anymore is dropped.
+    pub(crate) fn flush(
+        &self,
+        matchers: &[MatcherConfig],
+    ) -> Result<Vec<(String, Notification)>, Error> {
+        let now = proxmox_time::epoch_i64();
+
+        self.state
+            .with_state(|state: &mut HashMap<String, MatcherState>| {
+                let mut digests = Vec::new();
+
+                state.retain(|name, _| matchers.iter().any(|matcher| &matcher.name == name));
+
+                for matcher in matchers {
+                    if let Some(matcher_state) = state.get_mut(&matcher.name) {
+                        expire(matcher, matcher_state, now, &mut digests);
+                    }
+                }
+
+                state.retain(|_, matcher_state| !matcher_state.is_empty());
+
+                digests
+            })
+    }
+}
+
+/// Remove all entries of `state` whose window or interval has passed at `now`, and
+/// create digests for them if they suppressed any notifications.
+fn expire(
+    matcher: &MatcherConfig,
+    state: &mut MatcherState,
+    now: i64,
+    digests: &mut Vec<(String, Notification)>,
+) {
+    match matcher.dedup_window() {
+        Some(window) => state.dedup.retain(|_, entry| {
+            if now < entry.last_sent + window {
+                return true;
+            }
+
+            if let Some(digest) = entry.suppressed.digest(&matcher.name, now) {
+                for target in &matcher.target {
+                    digests.push((target.clone(), digest.clone()));
+                }
+            }
+
+            false
+        }),
+        None => state.dedup.clear(),
+    }
+
+    match &matcher.rate_limit {
+        Some(limit) => state.rate.retain(|target, entry| {
+            if now < entry.start + limit.interval {
+                return true;
+            }
+
+            if let Some(digest) = entry.suppressed.digest(&matcher.name, now) {
+                digests.push((target.clone(), digest));
+            }
+
+            false
+        }),
+        None => state.rate.clear(),
+    }
+}
+
+/// Hash of the metadata compared for deduplication.
+fn dedup_key(matcher: &MatcherConfig, notification: &Notification) -> String {
+    let fields = &notification.metadata.additional_fields;
+
+    let compared: BTreeMap<&str, Option<&String>> = if matcher.dedup_field.is_empty() {
+        fields.iter().map(|(k, v)| (k.as_str(), Some(v))).collect()
+    } else {
+        matcher
+            .dedup_field
+            .iter()
+            .map(|field| (field.as_str(), fields.get(field)))
+            .collect()
+    };
+
+    let data = if matcher.dedup_field.is_empty() {
+        json!({ "severity": notification.metadata.severity, "fields": compared })
+    } else {
+        json!({ "fields": compared })
+    };
+
+    openssl::sha::sha256(data.to_string().as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+
+    fn test_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("proxmox-notify-throttle-{}", Uuid::generate()))
+    }
+
+    fn notification(timestamp: i64, job: &str) -> Notification {
+        let mut fields = HashMap::new();
+        fields.insert("job".to_string(), job.to_string());
+
+        let mut notification =
+            Notification::from_template(Severity::Error, "test", Value::Null, fields);
+        notification.metadata.timestamp = timestamp;
+        notification
+    }
+
+    #[test]
+    fn test_dedup() -> Result<(), Error> {
+        let dir = test_dir();
+        let throttle = Throttle::new(&dir);
+
+        let matcher = MatcherConfig {
+            name: "matcher".into(),
+            target: vec!["a".into(), "b".into()],
+            dedup_window: Some("1h".parse()?),
+            ..Default::default()
+        };
+
+        let result = throttle.apply(&[&matcher], &notification(1000, "job1"))?;
+        assert_eq!(result.targets.len(), 2);
+
+        // identical notification within the window is suppressed
+        let result = throttle.apply(&[&matcher], &notification(2000, "job1"))?;
+        assert!(result.targets.is_empty());
+
+        // other metadata is not
+        let result = throttle.apply(&[&matcher], &notification(2000, "job2"))?;
+        assert_eq!(result.targets.len(), 2);
+
+        // after the window, the notification passes again and a digest is created
+        let result = throttle.apply(&[&matcher], &notification(5000, "job1"))?;
+        assert_eq!(result.targets.len(), 2);
+        assert_eq!(result.digests.len(), 2);
+        assert_eq!(
+            result.digests[0].1.metadata.additional_fields["matcher"],
+            "matcher"
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_rate_limit() -> Result<(), Error> {
+        let dir = test_dir();
+
+        let matcher = MatcherConfig {
+            name: "matcher".into(),
+            target: vec!["a".into()],
+            rate_limit: Some("2/1h".parse()?),
+            ..Default::default()
+        };
+
+        for (timestamp, expected) in [(1000, 1), (1001, 1), (1002, 0), (1003, 0)] {
+            // state must survive re-instantiation
+            let throttle = Throttle::new(&dir);
+            let result = throttle.apply(&[&matcher], &notification(timestamp, "job"))?;
+            assert_eq!(result.targets.len(), expected);
+            assert!(result.digests.is_empty());
+        }
+
+        let result = Throttle::new(&dir).apply(&[&matcher], &notification(5000, "job"))?;
+        assert_eq!(result.targets.len(), 1);
+        assert_eq!(result.digests.len(), 1);
+
+        let (target, digest) = &result.digests[0];
+        assert_eq!(target, "a");
+        assert_eq!(digest.metadata.additional_fields["type"], SUPPRESSED_TYPE);
+        match &digest.content {
+            Content::Digest { entries, omitted } => {
+                assert_eq!(entries.len(), 2);
+                assert_eq!(*omitted, 0);
+            }
+            _ => panic!("unexpected digest content"),
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+}
@@ -1,10 +1,12 @@
 use std::collections::HashMap;
 use std::future::Future;
 use std::io;
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::{Arc, Mutex};
 use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 
 use anyhow::{format_err, Error};
 use http::{HeaderMap, Method, Uri};
@@ -17,8 +19,8 @@ use proxmox_log::{FileLogOptions, FileLogger};
 use proxmox_router::{Router, RpcEnvironmentType, UserInformation};
 use proxmox_sys::fs::{create_path, CreateOptions};
 
-use crate::rest::Handler;
-use crate::RestEnvironment;
+use crate::rest::{Handler, UNIX_SOCKET_PEER};
+use crate::{RequestLimiter, RestEnvironment};
 
 /// REST server configuration
 pub struct ApiConfig {
@@ -31,6 +33,8 @@ pub struct ApiConfig {
     auth_handler: Option<AuthHandler>,
     index_handler: Option<IndexHandler>,
     pub(crate) privileged_addr: Option<PrivilegedAddr>,
+    request_limiter: Option<RequestLimiter>,
+    trusted_proxies: Vec<IpAddr>,
 
     #[cfg(feature = "templates")]
     templates: templates::Templates,
@@ -60,6 +64,8 @@ impl ApiConfig {
             auth_handler: None,
             index_handler: None,
             privileged_addr: None,
+            request_limiter: None,
+            trusted_proxies: Vec::new(),
 
             #[cfg(feature = "templates")]
             templates: templates::Templates::with_escape_fn(),
@@ -86,6 +92,56 @@ impl ApiConfig {
         self
     }
 
+    /// Set the per-client request limits.
+    pub fn request_limiter(mut self, request_limiter: RequestLimiter) -> Self {
+        self.request_limiter = Some(request_limiter);
+        self
+    }
+
+    /// Accept the `Forwarded` header from these proxies
+    ///
+    /// The client address from that header is used for logging and for the
+    /// [RequestLimiter]. Without a [RequestLimiter], and for connections on a
+    /// unix socket, the header is always trusted. Otherwise it is ignored
+    /// unless the TCP peer is listed here.
+    pub fn trusted_proxies<I>(mut self, proxies: I) -> Self
+    where
+        I: IntoIterator<Item = IpAddr>,
+    {
+        self.trusted_proxies = proxies.into_iter().map(|ip| ip.to_canonical()).collect();
+        self
+    }
+
+    pub(crate) fn is_trusted_proxy(&self, peer: SocketAddr) -> bool {
+        self.request_limiter.is_none()
+            || peer == UNIX_SOCKET_PEER
+            || self.trusted_proxies.contains(&peer.ip().to_canonical())
+    }
+
+    /// Returns the time after which the client may retry if the request
+    /// has to be rejected.
+    pub(crate) fn check_peer_limits(&self, peer: IpAddr) -> Result<(), Duration> {
+        match &self.request_limiter {
+            Some(limiter) => limiter.check_peer(peer.to_canonical(), Instant::now()),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the time after which the client may retry if the request
+    /// has to be rejected.
+    pub(crate) fn check_auth_id_limits(&self, auth_id: &str) -> Result<(), Duration> {
+        match &self.request_limiter {
+            Some(limiter) => limiter.check_auth_id(auth_id, Instant::now()),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn record_failed_auth(&self, peer: IpAddr) {
+        if let Some(limiter) = &self.request_limiter {
+            limiter.record_failed_auth(peer.to_canonical(), Instant::now());
+        }
+    }
+
     /// Set the index handler.
     pub fn index_handler(mut self, index_handler: IndexHandler) -> Self {
         self.index_handler = Some(index_handler);
@@ -2,38 +2,150 @@ use anyhow::{bail, Error};
 use hyper::header;
 
 /// Possible Compression Methods, order determines preference (later is preferred)
-#[derive(Eq, Ord, PartialEq, PartialOrd, Debug)]
+#[derive(Eq, Ord, PartialEq, PartialOrd, Debug, Copy, Clone)]
 pub enum CompressionMethod {
     Deflate,
-    //    Gzip,
-    //    Brotli,
+    Gzip,
+    Brotli,
 }
 
 impl CompressionMethod {
     pub fn content_encoding(&self) -> header::HeaderValue {
-        header::HeaderValue::from_static(self.extension())
+        header::HeaderValue::from_static(match *self {
+            CompressionMethod::Brotli => "br",
+            CompressionMethod::Gzip => "gzip",
+            CompressionMethod::Deflate => "deflate",
+        })
     }
 
+    /// File extension of precompressed files
     pub fn extension(&self) -> &'static str {
         match *self {
-            //            CompressionMethod::Brotli => "br",
-            //            CompressionMethod::Gzip => "gzip",
+            CompressionMethod::Brotli => "br",
+            CompressionMethod::Gzip => "gz",
             CompressionMethod::Deflate => "deflate",
         }
     }
+
+    /// Parse an `Accept-Encoding` header value
+    ///
+    /// Returns all acceptable methods, ordered by their weight (`q`
+    /// value) and then by our own preference. Methods with a weight of
+    /// zero are not acceptable, `*` matches all methods not listed
+    /// explicitly.
+    pub fn from_accept_encoding(value: &str) -> Vec<CompressionMethod> {
+        let mut weights: Vec<(CompressionMethod, u16)> = Vec::new();
+        let mut wildcard = None;
+
+        for item in value.split(',') {
+            let mut parts = item.split(';').map(str::trim);
+            let coding = parts.next().unwrap_or_default();
+            if coding.is_empty() {
+                continue;
+            }
+
+            let mut weight = 1000;
+            for param in parts {
+                if let Some((name, value)) = param.split_once('=') {
+                    if name.trim().eq_ignore_ascii_case("q") {
+                        weight = parse_qvalue(value.trim()).unwrap_or(0);
+                    }
+                }
+            }
+
+            if coding == "*" {
+                wildcard = Some(weight);
+            } else if let Ok(method) = coding.parse() {
+                match weights.iter_mut().find(|(m, _)| *m == method) {
+                    Some((_, w)) => *w = weight,
+                    None => weights.push((method, weight)),
+                }
+            }
+        }
+
+        if let Some(weight) = wildcard {
+            for method in [
+                CompressionMethod::Deflate,
+                CompressionMethod::Gzip,
+                CompressionMethod::Brotli,
+            ] {
+                if !weights.iter().any(|(m, _)| *m == method) {
+                    weights.push((method, weight));
+                }
+            }
+        }
+
+        weights.retain(|(_, weight)| *weight > 0);
+        weights.sort_by(|(m1, w1), (m2, w2)| w2.cmp(w1).then(m2.cmp(m1)));
+
+        weights.into_iter().map(|(method, _)| method).collect()
+    }
+}
+
+/// Parse a `q` value into thousandths (0 to 1000)
+fn parse_qvalue(value: &str) -> Option<u16> {
+    let (int, frac) = value.split_once('.').unwrap_or((value, ""));
+    if frac.len() > 3 || !frac.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let int = match int {
+        "0" => 0,
+        "1" => 1000,
+        _ => return None,
+    };
+    let frac = format!("{frac:0<3}").parse::<u16>().ok()?;
+
+    if int + frac > 1000 {
+        return None;
+    }
+    Some(int + frac)
 }
 
 impl std::str::FromStr for CompressionMethod {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            //            "br" => Ok(CompressionMethod::Brotli),
-            //            "gzip" => Ok(CompressionMethod::Gzip),
+        // http accept-encoding allows to give weights with ';q='
+        let coding = s.split(';').next().unwrap_or_default().trim();
+        match coding.to_ascii_lowercase().as_str() {
+            "br" => Ok(CompressionMethod::Brotli),
+            "gzip" | "x-gzip" => Ok(CompressionMethod::Gzip),
             "deflate" => Ok(CompressionMethod::Deflate),
-            // http accept-encoding allows to give weights with ';q='
-            other if other.starts_with("deflate;q=") => Ok(CompressionMethod::Deflate),
             _ => bail!("unknown compression format"),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use CompressionMethod::*;
+
+    #[test]
+    fn accept_encoding() {
+        assert_eq!(
+            CompressionMethod::from_accept_encoding("gzip, deflate, br"),
+            [Brotli, Gzip, Deflate]
+        );
+        assert_eq!(
+            CompressionMethod::from_accept_encoding("deflate;q=0.5, gzip;q=0.8, br;q=0.1"),
+            [Gzip, Deflate, Brotli]
+        );
+        assert_eq!(
+            CompressionMethod::from_accept_encoding("br;q=0, *;q=0.5, identity"),
+            [Gzip, Deflate]
+        );
+        assert_eq!(
+            CompressionMethod::from_accept_encoding("GZIP; Q=1.0, zstd"),
+            [Gzip]
+        );
+        assert_eq!(CompressionMethod::from_accept_encoding("gzip;q=2"), []);
+        assert_eq!(CompressionMethod::from_accept_encoding(""), []);
+
+        assert_eq!(
+            "deflate;q=0.5".parse::<CompressionMethod>().unwrap(),
+            Deflate
+        );
+    }
+}
@@ -64,6 +64,9 @@ impl RestEnvironment {
         if let Some(auth_logger) = self.api.get_auth_log() {
             auth_logger.lock().unwrap().log(&msg);
         }
+        if let Some(peer) = self.client_ip {
+            self.api.record_failed_auth(peer.ip());
+        }
     }
 }
 
@@ -0,0 +1,192 @@
+//! HTTP caching helpers (`ETag`, `Last-Modified` and conditional requests)
+
+use hyper::header::{self, HeaderMap, HeaderValue};
+use hyper::{Body, Response, StatusCode};
+
+/// Weak entity tag for a static file, derived from its size and mtime.
+pub(crate) fn file_etag(metadata: &std::fs::Metadata) -> String {
+    use std::os::unix::fs::MetadataExt;
+
+    format!(
+        "W/\"{:x}-{:x}.{:x}\"",
+        metadata.len(),
+        metadata.mtime(),
+        metadata.mtime_nsec()
+    )
+}
+
+/// Weak entity tag for an API result, derived from the `digest` result
+/// attribute, the authenticated user and the request URI.
+///
+/// Including the user makes sure a cached result is never revalidated
+/// for a different user with different privileges.
+pub(crate) fn api_etag(digest: &str, auth_id: Option<&str>, uri: &hyper::Uri) -> String {
+    let mut hasher = openssl::sha::Sha256::new();
+    hasher.update(digest.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(auth_id.unwrap_or_default().as_bytes());
+    hasher.update(b"\0");
+    if let Some(path_and_query) = uri.path_and_query() {
+        hasher.update(path_and_query.as_str().as_bytes());
+    }
+
+    format!("W/\"{}\"", hex::encode(hasher.finish()))
+}
+
+/// Compare two entity tags using the weak comparison function.
+fn weak_match(a: &str, b: &str) -> bool {
+    fn opaque_tag(tag: &str) -> &str {
+        let tag = tag.trim();
+        tag.strip_prefix("W/").unwrap_or(tag)
+    }
+    opaque_tag(a) == opaque_tag(b)
+}
+
+/// Returns `Some(true)` if any `If-None-Match` header matches `etag`,
+/// and `None` if there is no such header.
+fn if_none_match(headers: &HeaderMap, etag: &str) -> Option<bool> {
+    let mut found = false;
+    for value in headers.get_all(header::IF_NONE_MATCH) {
+        found = true;
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        if value
+            .split(',')
+            .any(|tag| tag.trim() == "*" || weak_match(tag, etag))
+        {
+            return Some(true);
+        }
+    }
+    found.then_some(false)
+}
+
+/// Evaluate the conditional request headers of a `GET` request.
+///
+/// Returns `true` if the client already has the current representation,
+/// i.e. we can answer with `304 Not Modified`. `If-Modified-Since` is
+/// only used if there is no `If-None-Match` header (RFC9110 13.1.3).
+pub(crate) fn is_not_modified(headers: &HeaderMap, etag: &str, mtime: Option<i64>) -> bool {
+    if let Some(matched) = if_none_match(headers, etag) {
+        return matched;
+    }
+
+    let (Some(mtime), Some(since)) = (mtime, headers.get(header::IF_MODIFIED_SINCE)) else {
+        return false;
+    };
+
+    match since
+        .to_str()
+        .ok()
+        .and_then(|since| proxmox_time::parse_http_date(since).ok())
+    {
+        Some(since) => mtime <= since,
+        None => false,
+    }
+}
+
+/// Build a `304 Not Modified` response, keeping the headers relevant for caches.
+pub(crate) fn not_modified_response(headers: &HeaderMap) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NOT_MODIFIED;
+
+    for name in [
+        header::ETAG,
+        header::LAST_MODIFIED,
+        header::CACHE_CONTROL,
+        header::VARY,
+    ] {
+        for value in headers.get_all(&name) {
+            response.headers_mut().append(&name, value.clone());
+        }
+    }
+
+    response
+}
+
+/// Insert the `ETag` header, and `Last-Modified` if `mtime` is known.
+pub(crate) fn insert_validators(headers: &mut HeaderMap, etag: &str, mtime: Option<i64>) {
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        headers.insert(header::ETAG, value);
+    }
+
+    if let Some(date) = mtime.and_then(|mtime| proxmox_time::epoch_to_http_date(mtime).ok()) {
+        if let Ok(value) = HeaderValue::from_str(&date) {
+            headers.insert(header::LAST_MODIFIED, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn headers(list: &[(header::HeaderName, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in list {
+            headers.append(name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn conditional_requests() {
+        let etag = "W/\"10-20.0\"";
+        let mtime = Some(784111777);
+
+        assert!(!is_not_modified(&headers(&[]), etag, mtime));
+
+        let if_none_match = |value| headers(&[(header::IF_NONE_MATCH, value)]);
+        assert!(is_not_modified(
+            &if_none_match("W/\"10-20.0\""),
+            etag,
+            mtime
+        ));
+        assert!(is_not_modified(
+            &if_none_match("\"a\", \"10-20.0\""),
+            etag,
+            mtime
+        ));
+        assert!(is_not_modified(&if_none_match("*"), etag, mtime));
+        assert!(!is_not_modified(
+            &if_none_match("W/\"10-21.0\""),
+            etag,
+            mtime
+        ));
+
+        let since = |value| headers(&[(header::IF_MODIFIED_SINCE, value)]);
+        assert!(is_not_modified(
+            &since("Sun, 06 Nov 1994 08:49:37 GMT"),
+            etag,
+            mtime
+        ));
+        assert!(!is_not_modified(
+            &since("Sun, 06 Nov 1994 08:49:36 GMT"),
+            etag,
+            mtime
+        ));
+        assert!(!is_not_modified(&since("invalid"), etag, mtime));
+        assert!(!is_not_modified(
+            &since("Sun, 06 Nov 1994 08:49:37 GMT"),
+            etag,
+            None
+        ));
+
+        // If-None-Match takes precedence
+        let both = headers(&[
+            (header::IF_NONE_MATCH, "W/\"other\""),
+            (header::IF_MODIFIED_SINCE, "Sun, 06 Nov 1994 08:49:37 GMT"),
+        ]);
+        assert!(!is_not_modified(&both, etag, mtime));
+    }
+
+    #[test]
+    fn api_etag_depends_on_user() {
+        let uri: hyper::Uri = "/api2/json/config?type=a".parse().unwrap();
+        let a = api_etag("abc", Some("root@pam"), &uri);
+        assert_eq!(a, api_etag("abc", Some("root@pam"), &uri));
+        assert_ne!(a, api_etag("abc", Some("user@pam"), &uri));
+        assert_ne!(a, api_etag("abd", Some("root@pam"), &uri));
+        assert!(a.starts_with("W/\""));
+    }
+}
@@ -13,6 +13,7 @@
 //!   - logfile rotation
 //!   - worker task management
 //! * generic interface to authenticate user
+//! * per-client request rate limiting and failed login lockout
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
@@ -28,6 +29,8 @@ use proxmox_sys::linux::procfs::PidStat;
 mod compression;
 pub use compression::*;
 
+mod http_cache;
+
 pub mod formatter;
 
 mod environment;
@@ -39,6 +42,9 @@ pub use api_config::{ApiConfig, AuthError, AuthHandler, IndexHandler, UnixAccept
 mod rest;
 pub use rest::{Redirector, RestServer};
 
+mod request_limiter;
+pub use request_limiter::RequestLimiter;
+
 pub mod connection;
 
 mod worker_task;
@@ -0,0 +1,322 @@
+//! Per-client request rate limiting and failed authentication lockout
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Number of tracked clients above which idle entries get removed.
+const PRUNE_THRESHOLD: usize = 4096;
+
+/// Limits the request rate of clients and locks out clients with too
+/// many failed authentication attempts
+///
+/// Requests are limited using token buckets, keyed by the peer IP
+/// address and by the authenticated user. Clients exceeding a limit,
+/// or locked out clients, get a `429 Too Many Requests` response with
+/// a `Retry-After` header.
+///
+/// ```
+/// use std::time::Duration;
+/// use proxmox_rest_server::RequestLimiter;
+///
+/// let limiter = RequestLimiter::new()
+///     .peer_rate(50.0, 200)
+///     .auth_id_rate(20.0, 100)
+///     .failed_auth_lockout(10, Duration::from_secs(600), Duration::from_secs(300));
+/// ```
+#[derive(Default)]
+pub struct RequestLimiter {
+    peer_rate: Option<RateLimit>,
+    auth_id_rate: Option<RateLimit>,
+    lockout: Option<Lockout>,
+    state: Mutex<LimiterState>,
+}
+
+#[derive(Clone, Copy)]
+struct RateLimit {
+    rate: f64,
+    burst: f64,
+}
+
+#[derive(Clone, Copy)]
+struct Lockout {
+    max_failures: u32,
+    interval: Duration,
+    duration: Duration,
+}
+
+#[derive(Default)]
+struct LimiterState {
+    peers: HashMap<IpAddr, Bucket>,
+    auth_ids: HashMap<String, Bucket>,
+    failed_auth: HashMap<IpAddr, FailedAuth>,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_update: Instant,
+}
+
+impl Bucket {
+    fn new(limit: RateLimit, now: Instant) -> Self {
+        Self {
+            tokens: limit.burst,
+            last_update: now,
+        }
+    }
+
+    fn refill(&mut self, limit: RateLimit, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_update);
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * limit.rate).min(limit.burst);
+        self.last_update = now;
+    }
+
+    /// Take a token, or return the time until the next one is available.
+    fn take(&mut self, limit: RateLimit, now: Instant) -> Result<(), Duration> {
+        self.refill(limit, now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - self.tokens) / limit.rate))
+        }
+    }
+
+    fn is_full(&mut self, limit: RateLimit, now: Instant) -> bool {
+        self.refill(limit, now);
+        self.tokens >= limit.burst
+    }
+}
+
+struct FailedAuth {
+    count: u32,
+    since: Instant,
+    locked_until: Option<Instant>,
+}
+
+impl FailedAuth {
+    fn is_expired(&self, lockout: Lockout, now: Instant) -> bool {
+        match self.locked_until {
+            Some(until) => until <= now,
+            None => now.saturating_duration_since(self.since) >= lockout.interval,
+        }
+    }
+}
+
+fn check_bucket<K>(
+    map: &mut HashMap<K, Bucket>,
+    key: K,
+    limit: RateLimit,
+    now: Instant,
+) -> Result<(), Duration>
+where
+    K: std::hash::Hash + Eq,
+{
+    if map.len() >= PRUNE_THRESHOLD {
+        map.retain(|_, bucket| !bucket.is_full(limit, now));
+    }
+
+    map.entry(key)
+        .or_insert_with(|| Bucket::new(limit, now))
+        .take(limit, now)
+}
+
+impl RequestLimiter {
+    /// Creates a new instance without any limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the requests per peer IP address
+    ///
+    /// `rate` is the sustained number of requests per second, `burst`
+    /// the number of requests allowed at once.
+    pub fn peer_rate(mut self, rate: f64, burst: u32) -> Self {
+        self.peer_rate = RateLimit::new(rate, burst);
+        self
+    }
+
+    /// Limit the requests per authenticated user (auth id)
+    ///
+    /// See [peer_rate](Self::peer_rate) for the parameters.
+    pub fn auth_id_rate(mut self, rate: f64, burst: u32) -> Self {
+        self.auth_id_rate = RateLimit::new(rate, burst);
+        self
+    }
+
+    /// Lock out peers with too many failed authentication attempts
+    ///
+    /// After `max_failures` failed attempts within `interval`, all
+    /// requests from the peer IP address are rejected for `duration`.
+    /// Failed attempts are recorded by
+    /// [RestEnvironment::log_failed_auth](crate::RestEnvironment::log_failed_auth).
+    pub fn failed_auth_lockout(
+        mut self,
+        max_failures: u32,
+        interval: Duration,
+        duration: Duration,
+    ) -> Self {
+        self.lockout = (max_failures > 0).then_some(Lockout {
+            max_failures,
+            interval,
+            duration,
+        });
+        self
+    }
+
+    /// Check if a request from `peer` is allowed, otherwise returns the
+    /// time after which the client may retry.
+    pub(crate) fn check_peer(&self, peer: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(lockout) = self.lockout {
+            if let Some(failed) = state.failed_auth.get(&peer) {
+                match failed.locked_until {
+                    Some(until) if until > now => return Err(until - now),
+                    _ if failed.is_expired(lockout, now) => {
+                        state.failed_auth.remove(&peer);
+                    }
+                    _ => (),
+                }
+            }
+        }
+
+        match self.peer_rate {
+            Some(limit) => check_bucket(&mut state.peers, peer, limit, now),
+            None => Ok(()),
+        }
+    }
+
+    /// Check if a request from an authenticated user is allowed,
+    /// otherwise returns the time after which the client may retry.
+    pub(crate) fn check_auth_id(&self, auth_id: &str, now: Instant) -> Result<(), Duration> {
+        let Some(limit) = self.auth_id_rate else {
+            return Ok(());
+        };
+
+        let mut state = self.state.lock().unwrap();
+        match state.auth_ids.get_mut(auth_id) {
+            Some(bucket) => bucket.take(limit, now),
+            None => check_bucket(&mut state.auth_ids, auth_id.to_string(), limit, now),
+        }
+    }
+
+    /// Record a failed authentication attempt from `peer`.
+    pub(crate) fn record_failed_auth(&self, peer: IpAddr, now: Instant) {
+        let Some(lockout) = self.lockout else {
+            return;
+        };
+
+        let mut state = self.state.lock().unwrap();
+
+        if state.failed_auth.len() >= PRUNE_THRESHOLD {
+            state
+                .failed_auth
+                .retain(|_, failed| !failed.is_expired(lockout, now));
+        }
+
+        let failed = state.failed_auth.entry(peer).or_insert(FailedAuth {
+            count: 0,
+            since: now,
+            locked_until: None,
+        });
+
+        if failed.is_expired(lockout, now) {
+            *failed = FailedAuth {
+                count: 0,
+                since: now,
+                locked_until: None,
+            };
+        } else if failed.locked_until.is_some() {
+            return;
+        }
+
+        failed.count += 1;
+        if failed.count >= lockout.max_failures {
+            failed.locked_until = Some(now + lockout.duration);
+            log::warn!(
+                "locking out client {peer} for {}s after {} failed authentication attempts",
+                lockout.duration.as_secs(),
+                failed.count,
+            );
+        }
+    }
+}
+
+impl RateLimit {
+    fn new(rate: f64, burst: u32) -> Option<Self> {
+        (rate > 0.0).then_some(Self {
+            rate,
+            burst: burst.max(1) as f64,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const PEER: IpAddr = IpAddr::V4(std::net::Ipv4Addr::new(192, 0, 2, 1));
+
+    #[test]
+    fn rate_limit() {
+        let limiter = RequestLimiter::new().peer_rate(2.0, 3).auth_id_rate(1.0, 1);
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(limiter.check_peer(PEER, now), Ok(()));
+        }
+        assert_eq!(
+            limiter.check_peer(PEER, now),
+            Err(Duration::from_millis(500))
+        );
+        assert_eq!(
+            limiter.check_peer(PEER, now + Duration::from_millis(500)),
+            Ok(())
+        );
+
+        // other peers are not affected
+        assert_eq!(limiter.check_peer([192, 0, 2, 2].into(), now), Ok(()));
+
+        assert_eq!(limiter.check_auth_id("root@pam", now), Ok(()));
+        assert_eq!(
+            limiter.check_auth_id("root@pam", now),
+            Err(Duration::from_secs(1))
+        );
+        assert_eq!(limiter.check_auth_id("user@pbs", now), Ok(()));
+    }
+
+    #[test]
+    fn failed_auth_lockout() {
+        let limiter = RequestLimiter::new().failed_auth_lockout(
+            3,
+            Duration::from_secs(60),
+            Duration::from_secs(300),
+        );
+        let now = Instant::now();
+
+        // failures outside the interval do not count
+        limiter.record_failed_auth(PEER, now);
+        limiter.record_failed_auth(PEER, now + Duration::from_secs(30));
+        limiter.record_failed_auth(PEER, now + Duration::from_secs(61));
+        assert_eq!(
+            limiter.check_peer(PEER, now + Duration::from_secs(62)),
+            Ok(())
+        );
+
+        let now = now + Duration::from_secs(200);
+        for _ in 0..3 {
+            assert_eq!(limiter.check_peer(PEER, now), Ok(()));
+            limiter.record_failed_auth(PEER, now);
+        }
+        assert_eq!(
+            limiter.check_peer(PEER, now + Duration::from_secs(100)),
+            Err(Duration::from_secs(200))
+        );
+        assert_eq!(
+            limiter.check_peer(PEER, now + Duration::from_secs(300)),
+            Ok(())
+        );
+    }
+}
@@ -23,17 +23,18 @@ use url::form_urlencoded;
 
 use proxmox_router::{
     check_api_permission, ApiHandler, ApiMethod, HttpError, Permission, RpcEnvironment,
-    RpcEnvironmentType, UserInformation,
+    RpcEnvironmentType, SerializableReturn, UserInformation,
 };
 use proxmox_router::{http_bail, http_err};
 use proxmox_schema::{ObjectSchemaType, ParameterSchema};
 
 use proxmox_async::stream::AsyncReaderStream;
-use proxmox_compression::DeflateEncoder;
+use proxmox_compression::{BrotliEncoder, DeflateEncoder, GzipEncoder};
 use proxmox_log::FileLogger;
 
 use crate::{
-    formatter::*, normalize_path, ApiConfig, AuthError, CompressionMethod, RestEnvironment,
+    formatter::*, http_cache, normalize_path, ApiConfig, AuthError, CompressionMethod,
+    RestEnvironment,
 };
 
 unsafe extern "C" {
@@ -202,11 +203,15 @@ impl PeerAddress for hyper::server::conn::AddrStream {
     }
 }
 
+// TODO: Find a way to actually represent the vsock peer in the ApiService struct - for now
+// it doesn't really matter, so just use a fake IP address
+pub(crate) const UNIX_SOCKET_PEER: std::net::SocketAddr = std::net::SocketAddr::V4(
+    std::net::SocketAddrV4::new(std::net::Ipv4Addr::UNSPECIFIED, 807),
+);
+
 impl PeerAddress for tokio::net::UnixStream {
     fn peer_addr(&self) -> Result<std::net::SocketAddr, Error> {
-        // TODO: Find a way to actually represent the vsock peer in the ApiService struct - for now
-        // it doesn't really matter, so just use a fake IP address
-        Ok(([0, 0, 0, 0], 807).into())
+        Ok(UNIX_SOCKET_PEER)
     }
 }
 
@@ -289,6 +294,19 @@ fn log_response(
     }
 }
 
+/// Returns the client address, taken from the `Forwarded` header only if
+/// `peer` is trusted to set it.
+fn client_peer(
+    config: &ApiConfig,
+    peer: std::net::SocketAddr,
+    headers: &HeaderMap,
+) -> std::net::SocketAddr {
+    if !config.is_trusted_proxy(peer) {
+        return peer;
+    }
+    get_proxied_peer(headers).unwrap_or(peer)
+}
+
 fn get_proxied_peer(headers: &HeaderMap) -> Option<std::net::SocketAddr> {
     static RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"for="([^"]+)""#).unwrap());
     let forwarded = headers.get(header::FORWARDED)?.to_str().ok()?;
@@ -325,11 +343,18 @@ impl Service<Request<Body>> for ApiService {
         let user_agent = get_user_agent(req.headers());
 
         let config = Arc::clone(&self.api_config);
-        let peer = match get_proxied_peer(req.headers()) {
-            Some(proxied_peer) => proxied_peer,
-            None => self.peer,
-        };
+        let peer = client_peer(&config, self.peer, req.headers());
         async move {
+            if let Err(retry_after) = config.check_peer_limits(peer.ip()) {
+                let response = too_many_requests_response(
+                    crate::formatter::error_to_response(too_many_requests_error()),
+                    retry_after,
+                );
+                let logger = config.get_access_log();
+                log_response(logger, &peer, method, &path, &response, user_agent);
+                return Ok(response);
+            }
+
             let response = match Arc::clone(&config).handle_request(req, &peer).await {
                 Ok(response) => response,
                 Err(err) => {
@@ -483,6 +508,23 @@ async fn proxy_protected_request(
     Ok(resp)
 }
 
+fn too_many_requests_error() -> Error {
+    http_err!(TOO_MANY_REQUESTS, "too many requests")
+}
+
+/// Add a `Retry-After` header to a `429 Too Many Requests` error response.
+fn too_many_requests_response(
+    mut response: Response<Body>,
+    retry_after: std::time::Duration,
+) -> Response<Body> {
+    // round up, so that clients do not retry too early
+    let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, seconds.max(1).into());
+    response
+}
+
 fn delay_unauth_time() -> std::time::Instant {
     std::time::Instant::now() + std::time::Duration::from_millis(3000)
 }
@@ -532,6 +574,8 @@ pub(crate) async fn handle_api_request<Env: RpcEnvironment, S: 'static + BuildHa
     uri_param: HashMap<String, String, S>,
 ) -> Result<Response<Body>, Error> {
     let formatter = formatter.unwrap_or(crate::formatter::DIRECT_JSON_FORMATTER);
+    // remember the digest while formatting, some handlers consume `rpcenv`
+    let formatter = &DigestFormatter::new(formatter);
 
     let compression = extract_compression_method(&parts.headers);
 
@@ -542,6 +586,15 @@ pub(crate) async fn handle_api_request<Env: RpcEnvironment, S: 'static + BuildHa
             .any(|e| e == b"application/json-seq" || e.starts_with(b"application/json-seq;"))
     });
 
+    // keep what we need to compute the cache validator, `parts` is consumed by some handlers
+    let cache_request = (info.cacheable && parts.method == hyper::Method::GET).then(|| {
+        (
+            parts.headers.clone(),
+            parts.uri.clone(),
+            rpcenv.get_auth_id(),
+        )
+    });
+
     let result = match info.handler {
         ApiHandler::AsyncHttp(handler) => {
             let params = parse_query_parameters(info.parameters, "", &parts, &uri_param)?;
@@ -605,6 +658,8 @@ pub(crate) async fn handle_api_request<Env: RpcEnvironment, S: 'static + BuildHa
         }
     };
 
+    let digest = formatter.digest.lock().unwrap().take();
+
     let mut resp = match result {
         Ok(resp) => resp,
         Err(err) => {
@@ -623,26 +678,46 @@ pub(crate) async fn handle_api_request<Env: RpcEnvironment, S: 'static + BuildHa
             .get(http::header::CONTENT_TYPE)
             .is_some_and(|h| h.as_ref().starts_with(b"application/json-seq"));
 
-    let resp = match compression {
-        Some(CompressionMethod::Deflate) => {
+    let mut not_modified = false;
+    if let (Some((headers, uri, auth_id)), Some(digest)) = (cache_request, digest) {
+        if resp.status().is_success() && !is_streaming {
+            let etag = http_cache::api_etag(&digest, auth_id.as_deref(), &uri);
+            http_cache::insert_validators(resp.headers_mut(), &etag, None);
             resp.headers_mut().insert(
-                header::CONTENT_ENCODING,
-                CompressionMethod::Deflate.content_encoding(),
+                header::CACHE_CONTROL,
+                header::HeaderValue::from_static("private, no-cache"),
+            );
+            not_modified = http_cache::is_not_modified(&headers, &etag, None);
+        }
+    }
+
+    let resp = match compression {
+        Some(method) => {
+            resp.headers_mut()
+                .insert(header::CONTENT_ENCODING, method.content_encoding());
+            resp.headers_mut().append(
+                header::VARY,
+                header::HeaderValue::from_static("accept-encoding"),
             );
             resp.map(|body| {
-                Body::wrap_stream(
-                    DeflateEncoder::builder(TryStreamExt::map_err(body, |err| {
+                compressed_body(
+                    TryStreamExt::map_err(body, |err| {
                         proxmox_lang::io_format_err!("error during compression: {}", err)
-                    }))
-                    .zlib(true)
-                    .flush_window(is_streaming.then_some(64 * 1024))
-                    .build(),
+                    }),
+                    method,
+                    is_streaming.then_some(64 * 1024),
                 )
             })
         }
         None => resp,
     };
 
+    let resp = if not_modified {
+        http_cache::not_modified_response(resp.headers())
+    } else {
+        resp
+    };
+
     if info.reload_timezone {
         unsafe {
             tzset();
@@ -652,6 +727,73 @@ pub(crate) async fn handle_api_request<Env: RpcEnvironment, S: 'static + BuildHa
     Ok(resp)
 }
 
+/// Output formatter remembering the `digest` result attribute of the formatted data.
+struct DigestFormatter {
+    formatter: &'static dyn OutputFormatter,
+    digest: Mutex<Option<String>>,
+}
+
+impl DigestFormatter {
+    fn new(formatter: &'static dyn OutputFormatter) -> Self {
+        Self {
+            formatter,
+            digest: Mutex::new(None),
+        }
+    }
+
+    fn capture(&self, rpcenv: &dyn RpcEnvironment) {
+        if let Some(digest) = rpcenv.result_attrib()["digest"].as_str() {
+            *self.digest.lock().unwrap() = Some(digest.to_string());
+        }
+    }
+}
+
+impl OutputFormatter for DigestFormatter {
+    fn format_data(&self, data: Value, rpcenv: &dyn RpcEnvironment) -> Response<Body> {
+        self.capture(rpcenv);
+        self.formatter.format_data(data, rpcenv)
+    }
+
+    fn format_data_streaming(
+        &self,
+        data: Box<dyn SerializableReturn + Send>,
+        rpcenv: &dyn RpcEnvironment,
+    ) -> Result<Response<Body>, Error> {
+        self.capture(rpcenv);
+        self.formatter.format_data_streaming(data, rpcenv)
+    }
+
+    fn format_error(&self, err: Error) -> Response<Body> {
+        self.formatter.format_error(err)
+    }
+}
+
+/// Compress a response body stream with the given method
+fn compressed_body<S, O>(stream: S, method: CompressionMethod, flush_window: Option<usize>) -> Body
+where
+    S: futures::Stream<Item = Result<O, io::Error>> + Send + Unpin + 'static,
+    O: Into<hyper::body::Bytes> + 'static,
+{
+    match method {
+        CompressionMethod::Deflate => Body::wrap_stream(
+            DeflateEncoder::builder(stream)
+                .zlib(true)
+                .flush_window(flush_window)
+                .build(),
+        ),
+        CompressionMethod::Gzip => Body::wrap_stream(
+            GzipEncoder::builder(stream)
+                .flush_window(flush_window)
+                .build(),
+        ),
+        CompressionMethod::Brotli => Body::wrap_stream(
+            BrotliEncoder::builder(stream)
+                .flush_window(flush_window)
+                .build(),
+        ),
+    }
+}
+
 fn extension_to_content_type(filename: &Path) -> (&'static str, bool) {
     if let Some(ext) = filename.extension().and_then(|osstr| osstr.to_str()) {
         return match ext {
@@ -690,24 +832,21 @@ async fn simple_static_file_download(
 
     let mut data: Vec<u8> = Vec::new();
 
+    file.read_to_end(&mut data)
+        .await
+        .map_err(|err| http_err!(BAD_REQUEST, "File read failed: {}", err))?;
+
     let mut response = match compression {
-        Some(CompressionMethod::Deflate) => {
-            let mut enc = DeflateEncoder::builder(data).zlib(true).build();
-            enc.compress_vec(&mut file, CHUNK_SIZE_LIMIT as usize)
-                .await?;
-            let mut response = Response::new(enc.into_inner().into());
-            response.headers_mut().insert(
-                header::CONTENT_ENCODING,
-                CompressionMethod::Deflate.content_encoding(),
-            );
+        Some(method) => {
+            let stream = futures::stream::iter([Ok::<_, io::Error>(data)]);
+            let data = hyper::body::to_bytes(compressed_body(stream, method, None)).await?;
+            let mut response = Response::new(data.into());
+            response
+                .headers_mut()
+                .insert(header::CONTENT_ENCODING, method.content_encoding());
             response
         }
-        None => {
-            file.read_to_end(&mut data)
-                .await
-                .map_err(|err| http_err!(BAD_REQUEST, "File read failed: {}", err))?;
-            Response::new(data.into())
-        }
+        None => Response::new(data.into()),
     };
 
     response.headers_mut().insert(
@@ -728,16 +867,9 @@ async fn chunked_static_file_download(
         .header(header::CONTENT_TYPE, content_type);
 
     let body = match compression {
-        Some(CompressionMethod::Deflate) => {
-            resp = resp.header(
-                header::CONTENT_ENCODING,
-                CompressionMethod::Deflate.content_encoding(),
-            );
-            Body::wrap_stream(
-                DeflateEncoder::builder(AsyncReaderStream::new(file))
-                    .zlib(true)
-                    .build(),
-            )
+        Some(method) => {
+            resp = resp.header(header::CONTENT_ENCODING, method.content_encoding());
+            compressed_body(AsyncReaderStream::new(file), method, None)
         }
         None => Body::wrap_stream(AsyncReaderStream::new(file)),
     };
@@ -748,7 +880,8 @@ async fn chunked_static_file_download(
 async fn handle_static_file_download(
     components: &[&str],
     filename: PathBuf,
-    compression: Option<CompressionMethod>,
+    request_headers: &HeaderMap,
+    compression: Vec<CompressionMethod>,
 ) -> Result<Response<Body>, Error> {
     let metadata = match tokio::fs::metadata(filename.clone()).await {
         Ok(metadata) => metadata,
@@ -764,36 +897,104 @@ async fn handle_static_file_download(
     };
 
     let (content_type, nocomp) = extension_to_content_type(&filename);
-    let compression = if nocomp { None } else { compression };
+    let compression = if nocomp { Vec::new() } else { compression };
+
+    // validators are based on the original file, precompressed files are
+    // expected to be updated together with it
+    let etag = http_cache::file_etag(&metadata);
+    let mtime = {
+        use std::os::unix::fs::MetadataExt;
+        metadata.mtime()
+    };
 
-    let file = File::open(filename).await.map_err(|err| {
-        http_err!(
-            BAD_REQUEST,
-            "File open failed for '{}': {}",
-            components.join("/"),
-            err.kind()
-        )
-    })?;
+    let mut headers = HeaderMap::new();
+    http_cache::insert_validators(&mut headers, &etag, Some(mtime));
+    if !nocomp {
+        headers.insert(
+            header::VARY,
+            header::HeaderValue::from_static("accept-encoding"),
+        );
+    }
 
-    if metadata.len() < CHUNK_SIZE_LIMIT {
-        simple_static_file_download(file, content_type, compression).await
-    } else {
-        chunked_static_file_download(file, content_type, compression).await
+    if http_cache::is_not_modified(request_headers, &etag, Some(mtime)) {
+        return Ok(http_cache::not_modified_response(&headers));
     }
-}
 
-// FIXME: support handling multiple compression methods
-fn extract_compression_method(headers: &http::HeaderMap) -> Option<CompressionMethod> {
-    if let Some(Ok(encodings)) = headers.get(header::ACCEPT_ENCODING).map(|v| v.to_str()) {
-        for encoding in encodings.split(&[',', ' '][..]) {
-            if let Ok(method) = encoding.parse() {
-                return Some(method);
+    let mut response = match open_precompressed_file(&filename, &compression).await {
+        Some((file, len, method)) => {
+            let mut response = if len < CHUNK_SIZE_LIMIT {
+                simple_static_file_download(file, content_type, None).await?
+            } else {
+                chunked_static_file_download(file, content_type, None).await?
+            };
+            response
+                .headers_mut()
+                .insert(header::CONTENT_ENCODING, method.content_encoding());
+            response
+        }
+        None => {
+            let compression = compression.first().copied();
+
+            let file = File::open(filename).await.map_err(|err| {
+                http_err!(
+                    BAD_REQUEST,
+                    "File open failed for '{}': {}",
+                    components.join("/"),
+                    err.kind()
+                )
+            })?;
+
+            if metadata.len() < CHUNK_SIZE_LIMIT {
+                simple_static_file_download(file, content_type, compression).await?
+            } else {
+                chunked_static_file_download(file, content_type, compression).await?
             }
         }
+    };
+
+    response.headers_mut().extend(headers);
+
+    Ok(response)
+}
+
+/// Open a precompressed version of `filename` (e.g. `file.js.gz`), using
+/// the first of the `compression` methods which has such a file.
+async fn open_precompressed_file(
+    filename: &Path,
+    compression: &[CompressionMethod],
+) -> Option<(File, u64, CompressionMethod)> {
+    for method in compression {
+        let mut path = filename.as_os_str().to_owned();
+        path.push(".");
+        path.push(method.extension());
+
+        let Ok(file) = File::open(&path).await else {
+            continue;
+        };
+        match file.metadata().await {
+            Ok(metadata) if metadata.is_file() => return Some((file, metadata.len(), *method)),
+            _ => continue,
+        }
     }
+
     None
 }
 
+/// Returns the acceptable compression methods, most preferred first.
+fn extract_compression_methods(headers: &http::HeaderMap) -> Vec<CompressionMethod> {
+    let encodings: Vec<&str> = headers
+        .get_all(header::ACCEPT_ENCODING)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .collect();
+
+    CompressionMethod::from_accept_encoding(&encodings.join(","))
+}
+
+fn extract_compression_method(headers: &http::HeaderMap) -> Option<CompressionMethod> {
+    extract_compression_methods(headers).into_iter().next()
+}
+
 impl ApiConfig {
     pub async fn handle_request(
         self: Arc<ApiConfig>,
@@ -851,8 +1052,8 @@ impl ApiConfig {
             Ok(self.get_index(rpcenv, parts).await)
         } else {
             let filename = self.find_alias(&components);
-            let compression = extract_compression_method(&parts.headers);
-            handle_static_file_download(&components, filename, compression).await
+            let compression = extract_compression_methods(&parts.headers);
+            handle_static_file_download(&components, filename, &parts.headers, compression).await
         }
     }
 }
@@ -965,6 +1166,10 @@ impl Formatted {
         if auth_required {
             match config.check_auth(&parts.headers, &parts.method).await {
                 Ok((authid, info)) => {
+                    if let Err(retry_after) = config.check_auth_id_limits(&authid) {
+                        let response = formatter.format_error(too_many_requests_error());
+                        return Ok(too_many_requests_response(response, retry_after));
+                    }
                     rpcenv.set_auth_id(Some(authid));
                     user_info = info;
                 }
@@ -1072,6 +1277,11 @@ impl Unformatted {
         if auth_required {
             match config.check_auth(&parts.headers, &parts.method).await {
                 Ok((authid, info)) => {
+                    if let Err(retry_after) = config.check_auth_id_limits(&authid) {
+                        let response =
+                            crate::formatter::error_to_response(too_many_requests_error());
+                        return Ok(too_many_requests_response(response, retry_after));
+                    }
                     rpcenv.set_auth_id(Some(authid));
                     user_info = info;
                 }
@@ -1135,3 +1345,107 @@ impl Unformatted {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::RequestLimiter;
+
+    fn request(forwarded: &str) -> Request<Body> {
+        Request::builder()
+            .method(hyper::Method::POST)
+            .uri("/")
+            .header(header::FORWARDED, forwarded)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    #[test]
+    fn spoofed_forwarded_header_is_rate_limited() {
+        let config = ApiConfig::new("/nonexistent", RpcEnvironmentType::PUBLIC)
+            .request_limiter(RequestLimiter::new().peer_rate(0.001, 2));
+        let mut service = ApiService {
+            peer: "192.0.2.1:8007".parse().unwrap(),
+            api_config: Arc::new(config),
+        };
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        for i in 0..3 {
+            let forwarded = format!("for=\"198.51.100.{i}:1234\"");
+            let response = runtime.block_on(service.call(request(&forwarded))).unwrap();
+            if i < 2 {
+                assert_ne!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+            } else {
+                assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+            }
+        }
+
+        // the header is used for requests from a trusted proxy
+        let config = ApiConfig::new("/nonexistent", RpcEnvironmentType::PUBLIC)
+            .trusted_proxies(["192.0.2.1".parse().unwrap()]);
+        let peer = client_peer(
+            &config,
+            "192.0.2.1:8007".parse().unwrap(),
+            request("for=\"198.51.100.1:1234\"").headers(),
+        );
+        assert_eq!(peer, "198.51.100.1:1234".parse().unwrap());
+    }
+
+    #[test]
+    fn forwarded_header_is_trusted_on_unix_socket() {
+        let headers = request("for=\"198.51.100.1:1234\"").headers().clone();
+        let tcp_peer = "192.0.2.1:8007".parse().unwrap();
+
+        let config = ApiConfig::new("/nonexistent", RpcEnvironmentType::PUBLIC)
+            .request_limiter(RequestLimiter::new().peer_rate(0.001, 2));
+        let peer = client_peer(&config, UNIX_SOCKET_PEER, &headers);
+        assert_eq!(peer, "198.51.100.1:1234".parse().unwrap());
+        assert_eq!(client_peer(&config, tcp_peer, &headers), tcp_peer);
+
+        // without a limiter, the header is trusted for all peers
+        let config = ApiConfig::new("/nonexistent", RpcEnvironmentType::PUBLIC);
+        let peer = client_peer(&config, tcp_peer, &headers);
+        assert_eq!(peer, "198.51.100.1:1234".parse().unwrap());
+    }
+
+    fn digest_method(
+        _param: Value,
+        _info: &ApiMethod,
+        rpcenv: &mut dyn RpcEnvironment,
+    ) -> Result<Value, Error> {
+        rpcenv["digest"] = "abc".into();
+        Ok(Value::Null)
+    }
+
+    const API_METHOD_DIGEST: ApiMethod = ApiMethod::new(
+        &ApiHandler::Sync(&digest_method),
+        &proxmox_schema::ObjectSchema::new("Digest test.", &[]),
+    )
+    .cacheable(true);
+
+    #[test]
+    fn cacheable_result_gets_etag() {
+        let config = Arc::new(ApiConfig::new("/nonexistent", RpcEnvironmentType::PUBLIC));
+        let (parts, body) = Request::get("/test")
+            .body(Body::empty())
+            .unwrap()
+            .into_parts();
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let rpcenv = RestEnvironment::new(RpcEnvironmentType::PUBLIC, config);
+        let response = runtime
+            .block_on(handle_api_request(
+                rpcenv,
+                &API_METHOD_DIGEST,
+                None,
+                parts,
+                body,
+                HashMap::<String, String>::new(),
+            ))
+            .unwrap();
+
+        let etag = http_cache::api_etag("abc", None, &"/test".parse().unwrap());
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+    }
+}
@@ -10,9 +10,11 @@ use proxmox_schema::*;
 
 use super::environment::CliEnvironment;
 use super::getopts;
+use super::prompt;
 use super::{
-    generate_nested_usage, generate_usage_str_do, print_help, print_nested_usage_error,
-    print_simple_usage_error_do, CliCommand, CliCommandMap, CommandLineInterface, GlobalOptions,
+    fish_completion_script, generate_man_page, generate_markdown, generate_nested_usage,
+    generate_usage_str_do, print_help, print_nested_usage_error, print_simple_usage_error_do,
+    zsh_completion_script, CliCommand, CliCommandMap, CommandLineInterface, GlobalOptions,
 };
 use crate::{ApiFuture, ApiHandler, ApiMethod, RpcEnvironment};
 
@@ -27,6 +29,12 @@ pub enum OutputFormat {
     Json,
     /// Prettified JSON output.
     JsonPretty,
+    /// YAML output.
+    Yaml,
+    /// Comma separated values, for table results.
+    Csv,
+    /// Tab separated values, for table results.
+    Tsv,
 }
 serde_plain::derive_display_from_serialize!(OutputFormat);
 serde_plain::derive_fromstr_from_deserialize!(OutputFormat);
@@ -40,12 +48,18 @@ impl ApiType for OutputFormat {
 /// - ``text``: command specific text format.
 /// - ``json``: JSON, single line.
 /// - ``json-pretty``: JSON, human readable.
+/// - ``yaml``: YAML, human readable.
+/// - ``csv``: comma separated values, using the table columns of the ``text`` format.
+/// - ``tsv``: tab separated values, using the table columns of the ``text`` format.
 ///
 pub const OUTPUT_FORMAT: Schema = StringSchema::new("Output format.")
     .format(&ApiStringFormat::Enum(&[
         EnumEntry::new("text", "plain text output"),
         EnumEntry::new("json", "single-line json formatted output"),
         EnumEntry::new("json-pretty", "pretty-printed json output"),
+        EnumEntry::new("yaml", "yaml formatted output"),
+        EnumEntry::new("csv", "comma separated values"),
+        EnumEntry::new("tsv", "tab separated values"),
     ]))
     .schema();
 
@@ -53,13 +67,24 @@ fn parse_arguments<'cli>(
     prefix: &str,
     cli_cmd: &CliCommand,
     args: Vec<String>,
+    prompt_missing: bool,
     global_options_iter: impl Iterator<Item = &'cli GlobalOptions>,
 ) -> Result<Value, Error> {
-    let (params, remaining) = match getopts::parse_arguments(
+    let mut prompt = |name: &str, schema: &'static Schema, data: &[(String, String)]| {
+        prompt::prompt_parameter(cli_cmd, name, schema, data)
+    };
+    let prompt: Option<getopts::PromptFn> = if prompt_missing && prompt::is_interactive() {
+        Some(&mut prompt)
+    } else {
+        None
+    };
+
+    let (params, remaining) = match getopts::parse_arguments_do(
         &args,
         cli_cmd.arg_param,
         &cli_cmd.fixed_param,
         cli_cmd.info.parameters,
+        prompt,
     ) {
         Ok((p, r)) => (p, r),
         Err(err) => {
@@ -84,7 +109,13 @@ async fn handle_simple_command_future(
     args: Vec<String>,
     mut rpcenv: CliEnvironment,
 ) -> Result<(), Error> {
-    let params = parse_arguments(prefix, cli_cmd, args, [].into_iter())?;
+    let params = parse_arguments(
+        prefix,
+        cli_cmd,
+        args,
+        rpcenv.prompt_missing(),
+        [].into_iter(),
+    )?;
 
     let result = match cli_cmd.info.handler {
         ApiHandler::Sync(handler) => (handler)(params, cli_cmd.info, &mut rpcenv),
@@ -138,7 +169,13 @@ pub(crate) fn handle_simple_command<'cli>(
     run: Option<fn(ApiFuture) -> Result<Value, Error>>,
     global_options_iter: impl Iterator<Item = &'cli GlobalOptions>,
 ) -> Result<(), Error> {
-    let params = parse_arguments(prefix, cli_cmd, args, global_options_iter)?;
+    let params = parse_arguments(
+        prefix,
+        cli_cmd,
+        args,
+        rpcenv.prompt_missing(),
+        global_options_iter,
+    )?;
 
     let result = match cli_cmd.info.handler {
         ApiHandler::Sync(handler) => (handler)(params, cli_cmd.info, rpcenv),
@@ -399,7 +436,53 @@ where
             std::process::exit(0);
         }
 
+        if args[0] == "zshcomplete" {
+            def.print_zsh_completion();
+            std::process::exit(0);
+        }
+
+        if args[0] == "fishcomplete" {
+            def.print_fish_completion();
+            std::process::exit(0);
+        }
+
+        if args[0] == "printcompletion" {
+            match args.get(1).map(String::as_str) {
+                Some("zsh") => print!("{}", zsh_completion_script(&prefix)),
+                Some("fish") => print!("{}", fish_completion_script(&prefix)),
+                _ => {
+                    eprintln!("Usage: {prefix} printcompletion <zsh|fish>");
+                    std::process::exit(-1);
+                }
+            }
+            std::process::exit(0);
+        }
+
         if args[0] == "printdoc" {
+            let summary = match def {
+                CommandLineInterface::Simple(cli_cmd) => {
+                    cli_cmd.info.parameters.description().lines().next()
+                }
+                CommandLineInterface::Nested(map) => map.description,
+            };
+            match args.get(1).map(String::as_str) {
+                Some("man") => {
+                    print!(
+                        "{}",
+                        generate_man_page(&prefix, "1", summary.unwrap_or_default(), def)
+                    );
+                    std::process::exit(0);
+                }
+                Some("markdown") => {
+                    print!(
+                        "{}",
+                        generate_markdown(&prefix, summary.unwrap_or_default(), def)
+                    );
+                    std::process::exit(0);
+                }
+                _ => (),
+            }
+
             let usage = match def {
                 CommandLineInterface::Simple(cli_cmd) => generate_usage_str_do(
                     &prefix,
@@ -427,11 +510,14 @@ where
 /// argument is assumed to be the program name, and is passed as ``prefix`` to
 /// ``handle_command()``.
 ///
-/// This helper automatically add the help command, and two special
-/// sub-command:
+/// This helper automatically add the help command, and some special
+/// sub-commands:
 ///
 /// - ``bashcomplete``: Output bash completions instead of running the command.
-/// - ``printdoc``: Output ReST documentation.
+/// - ``zshcomplete``, ``fishcomplete``: Output zsh or fish completions.
+/// - ``printcompletion <zsh|fish>``: Output the zsh or fish completion script.
+/// - ``printdoc [man|markdown]``: Output ReST documentation, a man page or a
+///   Markdown reference.
 ///
 pub async fn run_async_cli_command<C: Into<CommandLineInterface>>(def: C, rpcenv: CliEnvironment) {
     run_async_cli_command_with_args(def, rpcenv, std::env::args()).await
@@ -455,11 +541,14 @@ pub fn run_cli_command<C: Into<CommandLineInterface>>(
 /// The first argument is assumed to be the program name, and is passed as ``prefix`` to
 /// ``handle_command()``.
 ///
-/// This helper automatically add the help command, and two special
-/// sub-command:
+/// This helper automatically add the help command, and some special
+/// sub-commands:
 ///
 /// - ``bashcomplete``: Output bash completions instead of running the command.
-/// - ``printdoc``: Output ReST documentation.
+/// - ``zshcomplete``, ``fishcomplete``: Output zsh or fish completions.
+/// - ``printcompletion <zsh|fish>``: Output the zsh or fish completion script.
+/// - ``printdoc [man|markdown]``: Output ReST documentation, a man page or a
+///   Markdown reference.
 ///
 pub async fn run_async_cli_command_with_args<A, C>(def: C, rpcenv: CliEnvironment, args: A)
 where
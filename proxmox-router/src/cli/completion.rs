@@ -2,11 +2,25 @@ use std::collections::HashMap;
 
 use proxmox_schema::*;
 
+use super::docgen::schema_description;
 use super::help_command_def;
 use super::{
     shellword_split_unclosed, CliCommand, CliCommandMap, CommandLineInterface, CompletionFunction,
 };
 
+/// Read the command line to complete from ``COMP_LINE``, truncated to
+/// the cursor position ``COMP_POINT``.
+fn completion_line_from_env() -> Option<String> {
+    let comp_point: usize = std::env::var("COMP_POINT").ok()?.parse().ok()?;
+
+    let mut cmdline = std::env::var("COMP_LINE").ok()?;
+    if let Some((byte_pos, _)) = cmdline.char_indices().nth(comp_point) {
+        cmdline.truncate(byte_pos);
+    }
+
+    Some(cmdline)
+}
+
 fn record_done_argument(
     done: &mut HashMap<String, String>,
     parameters: ParameterSchema,
@@ -23,7 +37,7 @@ fn record_done_argument(
     }
 }
 
-fn get_property_completion(
+pub(super) fn get_property_completion(
     schema: &Schema,
     name: &str,
     completion_functions: &HashMap<String, CompletionFunction>,
@@ -79,14 +93,15 @@ fn get_property_completion(
 fn get_simple_completion(
     cli_cmd: &CliCommand,
     global_option_schemas: &HashMap<&'static str, &'static Schema>,
-    global_option_completions: HashMap<&'static str, CompletionFunction>,
+    global_option_completions: &HashMap<&'static str, CompletionFunction>,
     done: &mut HashMap<String, String>,
     arg_param: &[&str], // we remove done arguments
     args: &[String],
+    context: &mut CompletionContext,
 ) -> Vec<String> {
     let mut completions: HashMap<String, CompletionFunction> = global_option_completions
-        .into_iter()
-        .map(|(key, value)| (key.to_string(), value))
+        .iter()
+        .map(|(key, value)| (key.to_string(), *value))
         .collect();
     completions.extend(
         cli_cmd
@@ -101,6 +116,7 @@ fn get_simple_completion(
         done,
         arg_param,
         args,
+        context,
     )
 }
 
@@ -111,6 +127,7 @@ fn get_simple_completion_do(
     done: &mut HashMap<String, String>,
     arg_param: &[&str], // we remove done arguments
     args: &[String],
+    context: &mut CompletionContext,
 ) -> Vec<String> {
     //eprintln!("COMPL: {:?} {:?} {}", arg_param, args, args.len());
 
@@ -134,6 +151,7 @@ fn get_simple_completion_do(
                             done,
                             arg_param,
                             &args[1..],
+                            context,
                         );
                     } else {
                         return get_simple_completion_do(
@@ -143,11 +161,13 @@ fn get_simple_completion_do(
                             done,
                             &arg_param[1..],
                             &args[1..],
+                            context,
                         );
                     }
                 }
 
                 if args.len() == 1 {
+                    context.value_schema = Some(schema);
                     return get_property_completion(
                         schema,
                         prop_name,
@@ -195,6 +215,7 @@ fn get_simple_completion_do(
                     .lookup(prop_name)
                     .map(|(_, schema)| schema)
             }) {
+                context.value_schema = Some(schema);
                 return get_property_completion(
                     schema,
                     prop_name,
@@ -233,19 +254,29 @@ fn get_simple_completion_do(
 }
 
 impl CommandLineInterface {
-    fn get_help_completion(&self, help_cmd: &CliCommand, args: &[String]) -> Vec<String> {
+    fn get_help_completion<'cli>(
+        &'cli self,
+        help_cmd: &CliCommand,
+        args: &[String],
+        context: &mut CompletionContext<'cli>,
+    ) -> Vec<String> {
         let mut done = HashMap::new();
 
         match self {
-            CommandLineInterface::Simple(_) => get_simple_completion(
-                help_cmd,
-                &HashMap::new(),
-                HashMap::new(),
-                &mut done,
-                &[],
-                args,
-            ),
+            CommandLineInterface::Simple(_) => {
+                context.commands = None;
+                get_simple_completion(
+                    help_cmd,
+                    &HashMap::new(),
+                    &HashMap::new(),
+                    &mut done,
+                    &[],
+                    args,
+                    context,
+                )
+            }
             CommandLineInterface::Nested(map) => {
+                context.commands = Some(map);
                 if args.is_empty() {
                     let mut completions = Vec::new();
                     for cmd in map.commands.keys() {
@@ -258,7 +289,7 @@ impl CommandLineInterface {
                 if args.len() > 1 {
                     if let Some(sub_cmd) = map.commands.get(first) {
                         // do exact match here
-                        return sub_cmd.get_help_completion(help_cmd, &args[1..]);
+                        return sub_cmd.get_help_completion(help_cmd, &args[1..], context);
                     }
                     return Vec::new();
                 }
@@ -267,10 +298,11 @@ impl CommandLineInterface {
                     return get_simple_completion(
                         help_cmd,
                         &HashMap::new(),
-                        HashMap::new(),
+                        &HashMap::new(),
                         &mut done,
                         &[],
                         args,
+                        context,
                     );
                 }
 
@@ -292,22 +324,8 @@ impl CommandLineInterface {
     /// passed to ``get_completions()``. Returned values are printed to
     /// ``stdout``.
     pub fn print_bash_completion(&self) {
-        let comp_point: usize = match std::env::var("COMP_POINT") {
-            Ok(val) => match val.parse::<usize>() {
-                Ok(i) => i,
-                Err(_) => return,
-            },
-            Err(_) => return,
-        };
-
-        let cmdline = match std::env::var("COMP_LINE") {
-            Ok(mut val) => {
-                if let Some((byte_pos, _)) = val.char_indices().nth(comp_point) {
-                    val.truncate(byte_pos);
-                }
-                val
-            }
-            Err(_) => return,
+        let Some(cmdline) = completion_line_from_env() else {
+            return;
         };
 
         let (_start, completions) = self.get_completions(&cmdline, true);
@@ -317,8 +335,74 @@ impl CommandLineInterface {
         }
     }
 
+    /// Helper to generate zsh completions.
+    ///
+    /// Like [print_bash_completion](Self::print_bash_completion), but
+    /// prints ``value:description`` lines as expected by the script from
+    /// [zsh_completion_script].
+    pub fn print_zsh_completion(&self) {
+        let Some(cmdline) = completion_line_from_env() else {
+            return;
+        };
+
+        for (item, description) in self.get_described_completions(&cmdline, true) {
+            let item = item.replace('\\', "\\\\").replace(':', "\\:");
+            match description {
+                Some(description) => println!("{item}:{description}"),
+                None => println!("{item}"),
+            }
+        }
+    }
+
+    /// Helper to generate fish completions.
+    ///
+    /// Like [print_bash_completion](Self::print_bash_completion), but
+    /// prints tab separated ``value`` and ``description`` lines as
+    /// expected by the script from [fish_completion_script].
+    pub fn print_fish_completion(&self) {
+        let Some(cmdline) = completion_line_from_env() else {
+            return;
+        };
+
+        for (item, description) in self.get_described_completions(&cmdline, true) {
+            match description {
+                Some(description) => println!("{item}\t{description}"),
+                None => println!("{item}"),
+            }
+        }
+    }
+
+    /// Compute possible completions for a partial command, together
+    /// with a short description
+    ///
+    /// The descriptions are the first line of the schema description of
+    /// options and commands, or the description of enum values.
+    pub fn get_described_completions(
+        &self,
+        line: &str,
+        skip_first: bool,
+    ) -> Vec<(String, Option<&'static str>)> {
+        let (_start, completions, context) = self.complete(line, skip_first);
+
+        completions
+            .into_iter()
+            .map(|item| {
+                let description = context.describe(&item);
+                (item, description)
+            })
+            .collect()
+    }
+
     /// Compute possible completions for a partial command
     pub fn get_completions(&self, line: &str, skip_first: bool) -> (usize, Vec<String>) {
+        let (start, completions, _context) = self.complete(line, skip_first);
+        (start, completions)
+    }
+
+    /// Compute the completions and the context of the completed argument.
+    fn complete(&self, line: &str, skip_first: bool) -> (usize, Vec<String>, CompletionContext) {
+        let mut context = CompletionContext::default();
+
         let (mut args, start) = match shellword_split_unclosed(line, false) {
             (mut args, None) => {
                 args.push("".into());
@@ -332,19 +416,24 @@ impl CommandLineInterface {
 
         if skip_first {
             if args.is_empty() {
-                return (0, Vec::new());
+                return (0, Vec::new(), context);
             }
 
             args.remove(0); // no need for program name
         }
 
         let completions = if !args.is_empty() && args[0] == "help" {
-            self.get_help_completion(&help_command_def(), &args[1..])
+            let help_cmd = help_command_def();
+            context.parameters = Some(help_cmd.info.parameters);
+            self.get_help_completion(&help_cmd, &args[1..], &mut context)
         } else {
-            CompletionParser::default().get_completions(self, args)
+            let mut parser = CompletionParser::default();
+            let completions = parser.get_completions(self, args, &mut context);
+            context.global_options = parser.global_option_schemas;
+            completions
         };
 
-        (start, completions)
+        (start, completions, context)
     }
 }
 
@@ -388,13 +477,20 @@ impl CompletionParser {
         }
     }
 
-    fn get_completions(mut self, cli: &CommandLineInterface, mut args: Vec<String>) -> Vec<String> {
+    fn get_completions<'cli>(
+        &mut self,
+        cli: &'cli CommandLineInterface,
+        mut args: Vec<String>,
+        context: &mut CompletionContext<'cli>,
+    ) -> Vec<String> {
         match cli {
             CommandLineInterface::Simple(cli_cmd) => {
+                context.commands = None;
+                context.parameters = Some(cli_cmd.info.parameters);
                 cli_cmd.fixed_param.iter().for_each(|(key, value)| {
                     self.record_done_argument(cli_cmd.info.parameters, key, value);
                 });
-                let args = match self.handle_current_global_options(args) {
+                let args = match self.handle_current_global_options(args, context) {
                     Ok(GlobalArgs::Removed(args)) => args,
                     Ok(GlobalArgs::Completed(completion)) => return completion,
                     Err(_) => return Vec::new(),
@@ -402,17 +498,19 @@ impl CompletionParser {
                 get_simple_completion(
                     cli_cmd,
                     &self.global_option_schemas,
-                    self.global_option_completions,
+                    &self.global_option_completions,
                     &mut self.done_arguments,
                     cli_cmd.arg_param,
                     &args,
+                    context,
                 )
             }
             CommandLineInterface::Nested(map) => {
                 super::command::replace_aliases(&mut args, &map.aliases);
 
+                context.commands = Some(map);
                 self.enable_global_options(map);
-                let mut args = match self.handle_current_global_options(args) {
+                let mut args = match self.handle_current_global_options(args, context) {
                     Ok(GlobalArgs::Removed(args)) => args,
                     Ok(GlobalArgs::Completed(completion)) => return completion,
                     Err(_) => return Vec::new(),
@@ -421,7 +519,7 @@ impl CompletionParser {
                 if args.len() == 1 || args.len() == 2 {
                     if let Some(arg0) = args[0].strip_prefix("--") {
                         if let Some(completion) =
-                            self.try_complete_global_property(arg0, &args[1..])
+                            self.try_complete_global_property(arg0, &args[1..], context)
                         {
                             return completion;
                         }
@@ -460,7 +558,7 @@ impl CompletionParser {
 
                 let first = args.remove(0);
                 if let Some((_, sub_cmd)) = map.find_command(&first) {
-                    return self.get_completions(sub_cmd, args);
+                    return self.get_completions(sub_cmd, args, context);
                 }
 
                 Vec::new()
@@ -472,6 +570,7 @@ impl CompletionParser {
     fn handle_current_global_options(
         &mut self,
         args: Vec<String>,
+        context: &mut CompletionContext,
     ) -> Result<GlobalArgs, anyhow::Error> {
         let mut global_args = Vec::new();
         let args = super::getopts::ParseOptions::new(&mut global_args, &self.global_option_schemas)
@@ -483,9 +582,11 @@ impl CompletionParser {
         if args.is_empty() {
             // with no arguments remaining, the final global argument could need completion:
             if let Some((option, argument)) = global_args.last() {
-                if let Some(completion) =
-                    self.try_complete_global_property(option, &[argument.clone()])
-                {
+                if let Some(completion) = self.try_complete_global_property(
+                    option,
+                    std::slice::from_ref(argument),
+                    context,
+                ) {
                     return Ok(GlobalArgs::Completed(completion));
                 }
             }
@@ -499,9 +600,15 @@ impl CompletionParser {
         Ok(GlobalArgs::Removed(args))
     }
 
-    fn try_complete_global_property(&self, arg0: &str, args: &[String]) -> Option<Vec<String>> {
+    fn try_complete_global_property(
+        &self,
+        arg0: &str,
+        args: &[String],
+        context: &mut CompletionContext,
+    ) -> Option<Vec<String>> {
         let cb = self.global_option_completions.get(arg0)?;
         let to_complete = args.first().map(|s| s.as_str()).unwrap_or_default();
+        context.value_schema = self.global_option_schemas.get(arg0).copied();
         Some(cb(to_complete, &HashMap::new()))
     }
 }
@@ -511,6 +618,127 @@ enum GlobalArgs {
     Completed(Vec<String>),
 }
 
+/// The command and option the completed argument belongs to, recorded
+/// while computing the completions and used to describe them.
+#[derive(Default)]
+struct CompletionContext<'cli> {
+    /// Sub commands, if no simple command was selected yet
+    commands: Option<&'cli CliCommandMap>,
+    parameters: Option<ParameterSchema>,
+    global_options: HashMap<&'static str, &'static Schema>,
+    /// Schema of the option or positional argument whose value is completed
+    value_schema: Option<&'static Schema>,
+}
+
+impl CompletionContext<'_> {
+    fn lookup_option(&self, name: &str) -> Option<&'static Schema> {
+        self.global_options
+            .get(name)
+            .copied()
+            .or_else(|| lookup_parameter(self.parameters?, name))
+    }
+
+    fn describe(&self, item: &str) -> Option<&'static str> {
+        if let Some(schema) = self.value_schema {
+            return enum_description(schema, item);
+        }
+
+        if let Some(name) = item.strip_prefix("--") {
+            return first_line(schema_description(self.lookup_option(name)?).0);
+        }
+
+        match self.commands?.commands.get(item)? {
+            CommandLineInterface::Simple(cli_cmd) => {
+                first_line(cli_cmd.info.parameters.description())
+            }
+            CommandLineInterface::Nested(_) => None,
+        }
+    }
+}
+
+pub(super) fn lookup_parameter(parameters: ParameterSchema, name: &str) -> Option<&'static Schema> {
+    let (_optional, schema) = match parameters {
+        ParameterSchema::Object(schema) => schema.lookup(name),
+        ParameterSchema::AllOf(schema) => schema.lookup(name),
+        ParameterSchema::OneOf(schema) => schema.lookup(name),
+    }?;
+    Some(schema)
+}
+
+fn first_line(text: &'static str) -> Option<&'static str> {
+    text.lines().next().filter(|line| !line.is_empty())
+}
+
+/// Description of an enum value.
+fn enum_description(schema: &'static Schema, value: &str) -> Option<&'static str> {
+    match schema {
+        Schema::String(StringSchema {
+            format: Some(ApiStringFormat::Enum(variants)),
+            ..
+        }) => first_line(
+            variants
+                .iter()
+                .find(|entry| entry.value == value)?
+                .description,
+        ),
+        Schema::Array(ArraySchema { items, .. }) => enum_description(items, value),
+        _ => None,
+    }
+}
+
+/// Make a shell function name from a program name.
+fn completion_function_name(program: &str) -> String {
+    program
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Generate a zsh completion script for `program`
+///
+/// The script calls ``<program> zshcomplete`` (see
+/// [print_zsh_completion](CommandLineInterface::print_zsh_completion)),
+/// and can be installed to a directory in ``$fpath`` as ``_<program>``.
+pub fn zsh_completion_script(program: &str) -> String {
+    let func = completion_function_name(program);
+    format!(
+        r#"#compdef {program}
+
+_{func}() {{
+    local line="${{(j: :)words[1,CURRENT]}}"
+    local -a completions
+    completions=(${{(f)"$(COMP_LINE="$line" COMP_POINT="${{#line}}" {program} zshcomplete 2>/dev/null)"}})
+    _describe -t values '{program}' completions
+}}
+
+if [ "$funcstack[1]" = "_{func}" ]; then
+    _{func} "$@"
+else
+    compdef _{func} {program}
+fi
+"#
+    )
+}
+
+/// Generate a fish completion script for `program`
+///
+/// The script calls ``<program> fishcomplete`` (see
+/// [print_fish_completion](CommandLineInterface::print_fish_completion)),
+/// and can be installed as ``<program>.fish`` to a fish completions
+/// directory.
+pub fn fish_completion_script(program: &str) -> String {
+    let func = completion_function_name(program);
+    format!(
+        r#"function __{func}_complete
+    set -l line (commandline -cp)
+    env COMP_LINE="$line" COMP_POINT=(string length -- "$line") {program} fishcomplete 2>/dev/null
+end
+
+complete -c {program} -f -a '(__{func}_complete)'
+"#
+    )
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
@@ -764,4 +992,75 @@ mod test {
 
         test_completions(&cmd_def, "help l0sub l1c3", 11, &[]);
     }
+
+    fn test_described_completions(
+        cmd_def: &CommandLineInterface,
+        line: &str,
+        expect: &[(&str, Option<&str>)],
+    ) {
+        let mut expect: Vec<(String, Option<&str>)> = expect
+            .iter()
+            .map(|(item, description)| (item.to_string(), *description))
+            .collect();
+        expect.sort();
+
+        let mut completions = cmd_def.get_described_completions(line, false);
+        completions.sort();
+
+        assert_eq!(expect, completions);
+    }
+
+    #[test]
+    fn test_completion_descriptions() {
+        let cmd_def = get_complex_test_cmddef();
+        let simple_descr = Some("Simple API method with one required and one optionl argument.");
+
+        test_described_completions(
+            &cmd_def,
+            "",
+            &[
+                ("--global", Some("A global option.")),
+                (
+                    "help",
+                    Some("Get help about specified command (or sub-command)."),
+                ),
+                ("l0c1", simple_descr),
+                ("l0c2", simple_descr),
+                ("l0c3", simple_descr),
+                ("l0sub", None),
+            ],
+        );
+
+        test_described_completions(
+            &cmd_def,
+            "--global one l0sub l1c1 --r",
+            &[("--required-arg", Some("Required string argument."))],
+        );
+
+        test_described_completions(
+            &cmd_def,
+            "l0c1 --global ",
+            &[("one", Some("Option one.")), ("two", Some("Option two."))],
+        );
+
+        test_described_completions(&cmd_def, "l0c1 --optional-arg y", &[("yes", None)]);
+
+        test_described_completions(
+            &cmd_def,
+            "help l0sub l",
+            &[("l1c1", simple_descr), ("l1c2", simple_descr)],
+        );
+    }
+
+    #[test]
+    fn test_completion_scripts() {
+        let zsh = super::zsh_completion_script("proxmox-test");
+        assert!(zsh.starts_with("#compdef proxmox-test\n"));
+        assert!(zsh.contains("proxmox-test zshcomplete"));
+        assert!(zsh.contains("compdef _proxmox_test proxmox-test"));
+
+        let fish = super::fish_completion_script("proxmox-test");
+        assert!(fish.contains("proxmox-test fishcomplete"));
+        assert!(fish.contains("complete -c proxmox-test -f -a '(__proxmox_test_complete)'"));
+    }
 }
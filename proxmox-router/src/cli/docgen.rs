@@ -0,0 +1,402 @@
+//! Generate man pages and Markdown references for command line interfaces
+
+use std::collections::HashSet;
+use std::fmt::Write as _;
+
+use proxmox_schema::format::{get_schema_type_text, DocumentationFormat, ParameterDisplayStyle};
+use proxmox_schema::*;
+
+use super::{generate_usage_str_do, CliCommand, CommandLineInterface, GlobalOptions};
+
+/// A single documented argument or option.
+struct ParamDoc {
+    /// ``<name>`` for positional arguments, ``--name`` for options
+    name: String,
+    type_text: String,
+    default: Option<String>,
+    description: String,
+}
+
+struct CommandDoc {
+    name: String,
+    synopsis: String,
+    description: &'static str,
+    /// Positional arguments (in `arg_param` order) and required options
+    arguments: Vec<ParamDoc>,
+    options: Vec<ParamDoc>,
+    /// Names of the global options inherited from the command groups
+    inherited_options: Vec<&'static str>,
+}
+
+/// Global options of a command group.
+struct OptionGroup {
+    name: String,
+    options: Vec<ParamDoc>,
+}
+
+#[derive(Default)]
+struct Reference {
+    commands: Vec<CommandDoc>,
+    groups: Vec<OptionGroup>,
+}
+
+/// Returns the description and the default value of a schema.
+pub(crate) fn schema_description(schema: &Schema) -> (&'static str, Option<String>) {
+    match schema {
+        Schema::Null => ("", None),
+        Schema::Boolean(schema) => (schema.description, schema.default.map(|v| v.to_string())),
+        Schema::Integer(schema) => (schema.description, schema.default.map(|v| v.to_string())),
+        Schema::Number(schema) => (schema.description, schema.default.map(|v| v.to_string())),
+        Schema::String(schema) => (schema.description, schema.default.map(|v| v.to_string())),
+        Schema::Object(schema) => (schema.description, None),
+        Schema::Array(schema) => (schema.description, None),
+        Schema::AllOf(schema) => (schema.description, None),
+        Schema::OneOf(schema) => (schema.description, None),
+    }
+}
+
+fn param_doc(name: &str, schema: &Schema, style: ParameterDisplayStyle) -> ParamDoc {
+    let (description, default) = schema_description(schema);
+
+    let mut description = description.to_string();
+    if let Schema::Array(_) = schema {
+        description.push_str(" Can be specified more than once.");
+    }
+
+    ParamDoc {
+        name: match style {
+            ParameterDisplayStyle::Fixed => format!("<{name}>"),
+            _ => format!("--{name}"),
+        },
+        type_text: get_schema_type_text(schema, style),
+        default,
+        description,
+    }
+}
+
+fn command_doc(
+    name: &str,
+    cli_cmd: &CliCommand,
+    skip_options: &[&str],
+    global_options: &[&GlobalOptions],
+) -> CommandDoc {
+    let schema = cli_cmd.info.parameters;
+
+    let synopsis = generate_usage_str_do(
+        name,
+        cli_cmd,
+        DocumentationFormat::Short,
+        "",
+        skip_options,
+        global_options.iter().copied(),
+    );
+
+    let mut done: HashSet<&str> = skip_options.iter().copied().collect();
+
+    let mut arguments = Vec::new();
+    for arg in cli_cmd.arg_param {
+        let (_optional, arg_schema) = schema
+            .lookup(arg)
+            .unwrap_or_else(|| panic!("no such property '{arg}' in schema"));
+        arguments.push(param_doc(arg, arg_schema, ParameterDisplayStyle::Fixed));
+        done.insert(arg);
+    }
+
+    let mut properties: Vec<_> = schema.properties().collect();
+    properties.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut options = Vec::new();
+    for (prop, optional, prop_schema) in properties {
+        if done.contains(prop) || cli_cmd.fixed_param.contains_key(prop) {
+            continue;
+        }
+        let doc = param_doc(prop, prop_schema, ParameterDisplayStyle::Arg);
+        if *optional {
+            options.push(doc);
+        } else {
+            arguments.push(doc);
+        }
+        done.insert(prop);
+    }
+
+    let mut inherited_options: Vec<&'static str> = global_options
+        .iter()
+        .flat_map(|options| options.properties())
+        .map(|(name, _schema)| name)
+        .filter(|name| !done.contains(name))
+        .collect();
+    inherited_options.sort();
+
+    CommandDoc {
+        name: name.to_string(),
+        synopsis,
+        description: schema.description(),
+        arguments,
+        options,
+        inherited_options,
+    }
+}
+
+impl Reference {
+    fn new(program: &str, def: &CommandLineInterface) -> Self {
+        let mut reference = Self::default();
+        reference.add_commands(program, def, &[], &mut Vec::new());
+        reference
+    }
+
+    fn add_commands<'cli>(
+        &mut self,
+        name: &str,
+        def: &'cli CommandLineInterface,
+        skip_options: &[&str],
+        global_options: &mut Vec<&'cli GlobalOptions>,
+    ) {
+        match def {
+            CommandLineInterface::Simple(cli_cmd) => {
+                self.commands
+                    .push(command_doc(name, cli_cmd, skip_options, global_options));
+            }
+            CommandLineInterface::Nested(map) => {
+                let parent_options = global_options.len();
+
+                let mut group_options = Vec::new();
+                for options in map.global_options.values() {
+                    global_options.push(options);
+                    group_options.extend(options.properties());
+                }
+                if !group_options.is_empty() {
+                    group_options.sort_by(|a, b| a.0.cmp(b.0));
+                    self.groups.push(OptionGroup {
+                        name: name.to_string(),
+                        options: group_options
+                            .into_iter()
+                            .map(|(name, schema)| {
+                                param_doc(name, schema, ParameterDisplayStyle::Arg)
+                            })
+                            .collect(),
+                    });
+                }
+
+                let mut commands: Vec<&String> = map.commands.keys().collect();
+                commands.sort();
+
+                for command in commands {
+                    self.add_commands(
+                        &format!("{name} {command}"),
+                        &map.commands[command],
+                        map.usage_skip_options,
+                        global_options,
+                    );
+                }
+
+                global_options.truncate(parent_options);
+            }
+        }
+    }
+}
+
+/// Escape text for roff, including lines starting with control characters.
+fn roff_escape(text: &str) -> String {
+    let text = text.replace('\\', "\\e").replace('-', "\\-");
+
+    let mut escaped = String::with_capacity(text.len());
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            escaped.push('\n');
+        }
+        if line.starts_with(['.', '\'']) {
+            escaped.push_str("\\&");
+        }
+        escaped.push_str(line);
+    }
+    escaped
+}
+
+fn roff_paragraphs(out: &mut String, text: &str) {
+    for (i, paragraph) in text
+        .split("\n\n")
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .enumerate()
+    {
+        if i > 0 {
+            out.push_str(".PP\n");
+        }
+        let _ = writeln!(out, "{}", roff_escape(paragraph));
+    }
+}
+
+fn roff_params(out: &mut String, params: &[ParamDoc]) {
+    for param in params {
+        let _ = write!(
+            out,
+            ".TP\n\\fB{}\\fR \\fI{}\\fR",
+            roff_escape(&param.name),
+            roff_escape(&param.type_text)
+        );
+        if let Some(default) = &param.default {
+            let _ = write!(out, " (default=\\fI{}\\fR)", roff_escape(default));
+        }
+        out.push('\n');
+        roff_paragraphs(out, &param.description);
+    }
+}
+
+/// Generate a roff man page for a command line interface
+///
+/// The page contains a NAME section using `summary`, a SYNOPSIS with
+/// all (sub) commands, the options shared by command groups, and a
+/// section for each command with its description, arguments and
+/// options.
+pub fn generate_man_page(
+    program: &str,
+    section: &str,
+    summary: &str,
+    def: &CommandLineInterface,
+) -> String {
+    let reference = Reference::new(program, def);
+
+    let mut out = String::new();
+
+    let _ = writeln!(
+        out,
+        ".TH \"{}\" \"{section}\"",
+        roff_escape(&program.to_uppercase())
+    );
+
+    out.push_str(".SH NAME\n");
+    if summary.is_empty() {
+        let _ = writeln!(out, "{}", roff_escape(program));
+    } else {
+        let _ = writeln!(out, "{} \\- {}", roff_escape(program), roff_escape(summary));
+    }
+
+    out.push_str(".SH SYNOPSIS\n.nf\n");
+    for command in &reference.commands {
+        let _ = writeln!(out, "{}", roff_escape(&command.synopsis));
+    }
+    out.push_str(".fi\n");
+
+    if !reference.groups.is_empty() {
+        out.push_str(".SH GLOBAL OPTIONS\n");
+        for group in &reference.groups {
+            let _ = writeln!(
+                out,
+                ".SS Options available for command group \\fB{}\\fR",
+                roff_escape(&group.name)
+            );
+            roff_params(&mut out, &group.options);
+        }
+    }
+
+    out.push_str(".SH COMMANDS\n");
+    for command in &reference.commands {
+        let _ = writeln!(out, ".SS {}", roff_escape(&command.name));
+        let _ = writeln!(out, ".nf\n\\fB{}\\fR\n.fi", roff_escape(&command.synopsis));
+        out.push_str(".PP\n");
+        roff_paragraphs(&mut out, command.description);
+
+        roff_params(&mut out, &command.arguments);
+
+        if !command.options.is_empty() {
+            out.push_str(".PP\nOptional parameters:\n");
+            roff_params(&mut out, &command.options);
+        }
+
+        if !command.inherited_options.is_empty() {
+            let list: Vec<String> = command
+                .inherited_options
+                .iter()
+                .map(|name| format!("\\fB\\-\\-{}\\fR", roff_escape(name)))
+                .collect();
+            let _ = writeln!(out, ".PP\nInherited group parameters: {}", list.join(", "));
+        }
+    }
+
+    out
+}
+
+fn markdown_params(out: &mut String, params: &[ParamDoc]) {
+    for param in params {
+        let _ = write!(out, "- `{}` `{}`", param.name, param.type_text);
+        if let Some(default) = &param.default {
+            let _ = write!(out, " (default: `{default}`)");
+        }
+        out.push('\n');
+
+        for (i, paragraph) in param
+            .description
+            .split("\n\n")
+            .map(str::trim)
+            .filter(|p| !p.is_empty())
+            .enumerate()
+        {
+            let paragraph = paragraph.replace('\n', "\n  ");
+            if i > 0 {
+                out.push('\n');
+            }
+            let _ = writeln!(out, "  {paragraph}");
+        }
+    }
+}
+
+/// Generate a Markdown reference for a command line interface
+///
+/// Contains the same information as [generate_man_page], with a level 1
+/// heading for the program and level 2 headings for the sections.
+pub fn generate_markdown(program: &str, summary: &str, def: &CommandLineInterface) -> String {
+    let reference = Reference::new(program, def);
+
+    let mut out = String::new();
+
+    let _ = writeln!(out, "# {program}\n");
+    if !summary.is_empty() {
+        let _ = writeln!(out, "{summary}\n");
+    }
+
+    out.push_str("## Synopsis\n\n```text\n");
+    for command in &reference.commands {
+        let _ = writeln!(out, "{}", command.synopsis);
+    }
+    out.push_str("```\n");
+
+    if !reference.groups.is_empty() {
+        out.push_str("\n## Global Options\n");
+        for group in &reference.groups {
+            let _ = writeln!(
+                out,
+                "\n### Options available for command group `{}`\n",
+                group.name
+            );
+            markdown_params(&mut out, &group.options);
+        }
+    }
+
+    out.push_str("\n## Commands\n");
+    for command in &reference.commands {
+        let _ = writeln!(out, "\n### `{}`\n", command.name);
+        let _ = writeln!(out, "```text\n{}\n```\n", command.synopsis);
+        let _ = writeln!(out, "{}", command.description.trim());
+
+        if !command.arguments.is_empty() {
+            out.push('\n');
+            markdown_params(&mut out, &command.arguments);
+        }
+
+        if !command.options.is_empty() {
+            out.push_str("\nOptional parameters:\n\n");
+            markdown_params(&mut out, &command.options);
+        }
+
+        if !command.inherited_options.is_empty() {
+            let list: Vec<String> = command
+                .inherited_options
+                .iter()
+                .map(|name| format!("`--{name}`"))
+                .collect();
+            let _ = writeln!(out, "\nInherited group parameters: {}", list.join(", "));
+        }
+    }
+
+    out
+}
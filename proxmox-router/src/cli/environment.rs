@@ -12,6 +12,7 @@ use crate::{RpcEnvironment, RpcEnvironmentType};
 pub struct CliEnvironment {
     result_attributes: Value,
     auth_id: Option<String>,
+    prompt_missing: bool,
     pub(crate) global_options: HashMap<TypeId, Box<dyn Any + Send + Sync + 'static>>,
 }
 
@@ -20,6 +21,19 @@ impl CliEnvironment {
         Default::default()
     }
 
+    /// Interactively ask for missing required parameters.
+    ///
+    /// Only has an effect if both stdin and stdout are terminals,
+    /// otherwise missing parameters are reported as usage errors.
+    pub fn set_prompt_missing(&mut self, prompt_missing: bool) {
+        self.prompt_missing = prompt_missing;
+    }
+
+    /// Check whether missing required parameters are asked for.
+    pub fn prompt_missing(&self) -> bool {
+        self.prompt_missing
+    }
+
     /// Get a specific command line argument type.
     pub fn global_option<T>(&self) -> Option<&T>
     where
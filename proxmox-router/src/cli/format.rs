@@ -11,29 +11,31 @@ use proxmox_schema::format::{
 };
 use proxmox_schema::*;
 
-use super::{value_to_text, TableFormatOptions};
+use super::{value_to_csv, value_to_text, value_to_tsv, TableFormatOptions};
 use super::{CliCommand, CliCommandMap, CommandLineInterface, GlobalOptions};
 
 /// Helper function to format and print result.
 ///
-/// This is implemented for machine generatable formats 'json' and
-/// 'json-pretty'. The 'text' format needs to be handled somewhere
-/// else.
+/// This is implemented for machine generatable formats 'json',
+/// 'json-pretty' and 'yaml'. The 'text', 'csv' and 'tsv' formats need
+/// a schema (see [format_and_print_result_full]), so they fall back to
+/// 'json' here.
 pub fn format_and_print_result<T: Serialize>(result: &T, output_format: &str) {
     if output_format == "json-pretty" {
         println!("{}", serde_json::to_string_pretty(&result).unwrap());
-    } else if output_format == "json" {
-        println!("{}", serde_json::to_string(&result).unwrap());
+    } else if output_format == "yaml" {
+        print!("{}", serde_yaml_ng::to_string(&result).unwrap());
     } else {
-        unimplemented!();
+        println!("{}", serde_json::to_string(&result).unwrap());
     }
 }
 
 /// Helper function to format and print result.
 ///
-/// This is implemented for machine generatable formats 'json' and
-/// 'json-pretty', and for the 'text' format which generates nicely
-/// formatted tables with borders.
+/// This is implemented for machine generatable formats 'json',
+/// 'json-pretty' and 'yaml', for the 'text' format which generates
+/// nicely formatted tables with borders, and for the 'csv' and 'tsv'
+/// formats which print the same table columns as separated values.
 pub fn format_and_print_result_full(
     result: &mut Value,
     return_type: &ReturnType,
@@ -48,10 +50,20 @@ pub fn format_and_print_result_full(
         println!("{}", serde_json::to_string_pretty(&result).unwrap());
     } else if output_format == "json" {
         println!("{}", serde_json::to_string(&result).unwrap());
+    } else if output_format == "yaml" {
+        print!("{}", serde_yaml_ng::to_string(&result).unwrap());
     } else if output_format == "text" {
         if let Err(err) = value_to_text(std::io::stdout(), result, return_type.schema, options) {
             eprintln!("unable to format result: {}", err);
         }
+    } else if output_format == "csv" {
+        if let Err(err) = value_to_csv(std::io::stdout(), result, return_type.schema, options) {
+            eprintln!("unable to format result: {}", err);
+        }
+    } else if output_format == "tsv" {
+        if let Err(err) = value_to_tsv(std::io::stdout(), result, return_type.schema, options) {
+            eprintln!("unable to format result: {}", err);
+        }
     } else {
         eprintln!("undefined output format '{}'", output_format);
     }
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use anyhow::format_err;
+use anyhow::{format_err, Error};
 use serde_json::Value;
 
 use proxmox_schema::*;
@@ -128,6 +128,12 @@ pub(crate) fn parse_argument_list<T: AsRef<str>>(
     (data, remaining)
 }
 
+/// Callback asking for the values of a missing required parameter.
+///
+/// Gets the parameter name, its schema and the parameters parsed so far.
+pub(crate) type PromptFn<'a> =
+    &'a mut dyn FnMut(&str, &'static Schema, &[(String, String)]) -> Result<Vec<String>, Error>;
+
 /// Parses command line arguments using a `Schema`
 ///
 /// Returns parsed options as json object, together with the
@@ -137,6 +143,19 @@ pub fn parse_arguments<T: AsRef<str>>(
     arg_param: &[&str],
     fixed_param: &HashMap<&'static str, String>,
     schema: ParameterSchema,
+) -> Result<(Value, Vec<String>), ParameterError> {
+    parse_arguments_do(args, arg_param, fixed_param, schema, None)
+}
+
+/// Like [parse_arguments], but calls `prompt` for missing required
+/// parameters (positional arguments first, then the remaining options)
+/// instead of failing.
+pub(crate) fn parse_arguments_do<T: AsRef<str>>(
+    args: &[T],
+    arg_param: &[&str],
+    fixed_param: &HashMap<&'static str, String>,
+    schema: ParameterSchema,
+    mut prompt: Option<PromptFn>,
 ) -> Result<(Value, Vec<String>), ParameterError> {
     let mut errors = ParameterError::new();
 
@@ -168,7 +187,7 @@ pub fn parse_arguments<T: AsRef<str>>(
         let is_last_arg_param = i == (arg_param.len() - 1);
 
         if remaining.is_empty() {
-            if !(is_last_arg_param && last_arg_param_is_optional) {
+            if !(is_last_arg_param && last_arg_param_is_optional) && prompt.is_none() {
                 errors.push(name.to_string(), format_err!("missing argument"));
             }
         } else if is_last_arg_param && last_arg_param_is_array {
@@ -189,6 +208,31 @@ pub fn parse_arguments<T: AsRef<str>>(
         data.push((name.to_string(), value.to_string()));
     }
 
+    if let Some(prompt) = prompt.as_mut() {
+        let required = schema
+            .properties()
+            .filter(|(_, optional, _)| !optional)
+            .map(|(name, _, _)| *name);
+
+        for name in arg_param.iter().copied().chain(required) {
+            if data.iter().any(|(n, _)| n == name) {
+                continue;
+            }
+            let (optional, _) = schema.lookup(name).unwrap();
+            if optional {
+                continue;
+            }
+            let param_schema = super::completion::lookup_parameter(schema, name).unwrap();
+            match prompt(name, param_schema, &data) {
+                Ok(values) => data.extend(values.into_iter().map(|v| (name.to_string(), v))),
+                Err(err) => {
+                    errors.push(name.to_string(), err);
+                    return Err(errors);
+                }
+            }
+        }
+    }
+
     let options = schema.parse_parameter_strings(&data, true)?;
 
     Ok((options, remaining))
@@ -257,6 +301,64 @@ fn test_argument_parameter() {
     }
 }
 
+#[test]
+fn test_prompt_missing() {
+    use proxmox_schema::*;
+
+    const PARAMETERS: ObjectSchema = ObjectSchema::new(
+        "Parameters:",
+        &[
+            ("comment", true, &StringSchema::new("Comment.").schema()),
+            ("node", false, &StringSchema::new("Node.").schema()),
+            ("storage", false, &StringSchema::new("Storage.").schema()),
+            ("vmid", false, &IntegerSchema::new("VM ID.").schema()),
+        ],
+    );
+
+    let mut asked = Vec::new();
+    let mut prompt = |name: &str, _schema: &'static Schema, data: &[(String, String)]| {
+        asked.push((name.to_string(), data.len()));
+        Ok(vec![match name {
+            "vmid" => "100".to_string(),
+            _ => format!("{name}-value"),
+        }])
+    };
+
+    let fixed_param = HashMap::from([("node", "localhost".to_string())]);
+    let (options, remaining) = parse_arguments_do(
+        &["--comment", "test"],
+        &["vmid"],
+        &fixed_param,
+        ParameterSchema::from(&PARAMETERS),
+        Some(&mut prompt),
+    )
+    .expect("prompting for missing parameters failed");
+
+    assert_eq!(
+        asked,
+        [("vmid".to_string(), 2), ("storage".to_string(), 3)],
+        "positional arguments must be asked for first, fixed parameters never"
+    );
+    assert_eq!(options["vmid"], 100);
+    assert_eq!(options["storage"], "storage-value");
+    assert_eq!(options["node"], "localhost");
+    assert_eq!(options["comment"], "test");
+    assert!(remaining.is_empty());
+
+    let mut prompt = |_name: &str, _schema: &'static Schema, _data: &[(String, String)]| {
+        Err(format_err!("aborted"))
+    };
+    let err = parse_arguments_do(
+        &["100"],
+        &["vmid"],
+        &fixed_param,
+        ParameterSchema::from(&PARAMETERS),
+        Some(&mut prompt),
+    )
+    .expect_err("aborted prompt must fail");
+    assert_eq!(err.errors()[0].0, "storage");
+}
+
 pub(crate) struct ParseOptions<'t, 'o> {
     target: &'t mut Vec<(String, String)>,
     option_schemas: &'o HashMap<&'o str, &'static Schema>,
@@ -7,8 +7,8 @@
 //!
 //! - Use declarative API schema to define the CLI
 //! - Automatic parameter verification
-//! - Automatically generate documentation and manual pages
-//! - Automatically generate bash completion helpers
+//! - Automatically generate documentation, manual pages and Markdown references
+//! - Automatically generate bash, zsh and fish completion helpers
 //! - Ability to create interactive commands (using ``rustyline``)
 //! - Supports complex/nested commands
 
@@ -37,7 +37,11 @@ pub use format::*;
 mod text_table;
 pub use text_table::*;
 
+mod docgen;
+pub use docgen::{generate_man_page, generate_markdown};
+
 mod completion;
+pub use completion::{fish_completion_script, zsh_completion_script};
 
 mod completion_helpers;
 pub use completion_helpers::*;
@@ -48,6 +52,8 @@ pub use getopts::*;
 mod command;
 pub use command::*;
 
+mod prompt;
+
 mod readline;
 pub use readline::*;
 
@@ -191,6 +197,11 @@ pub struct CliCommand {
     pub arg_param: &'static [&'static str],
     /// Predefined parameters.
     pub fixed_param: HashMap<&'static str, String>,
+    /// Password parameter list.
+    ///
+    /// Input for those parameters is not echoed when they are asked
+    /// for interactively.
+    pub password_param: &'static [&'static str],
     /// Completion functions.
     ///
     /// Each parameter may have an associated completion function,
@@ -205,6 +216,7 @@ impl CliCommand {
             info,
             arg_param: &[],
             fixed_param: HashMap::new(),
+            password_param: &[],
             completion_functions: HashMap::new(),
         }
     }
@@ -221,6 +233,12 @@ impl CliCommand {
         self
     }
 
+    /// Set password parameter list.
+    pub fn password_param(mut self, names: &'static [&'static str]) -> Self {
+        self.password_param = names;
+        self
+    }
+
     /// Set completion functions.
     pub fn completion_cb(mut self, param_name: &str, cb: CompletionFunction) -> Self {
         self.completion_functions.insert(param_name.into(), cb);
@@ -237,6 +255,8 @@ pub struct CliCommandMap {
     pub aliases: Vec<(Vec<&'static str>, Vec<&'static str>)>,
     /// List of options to suppress in generate_usage
     pub usage_skip_options: &'static [&'static str],
+    /// Short description, used as summary in generated documentation
+    pub description: Option<&'static str>,
 
     /// A set of options common to all subcommands. Only object schemas can be used here.
     pub(crate) global_options: HashMap<TypeId, GlobalOptions>,
@@ -264,6 +284,14 @@ impl CliCommandMap {
         self
     }
 
+    /// Builder style method to set the short description of the command group.
+    ///
+    /// This is used as summary in the NAME section of the man page.
+    pub fn description(mut self, description: &'static str) -> Self {
+        self.description = Some(description);
+        self
+    }
+
     /// Insert the help command.
     pub fn insert_help(mut self) -> Self {
         self.commands
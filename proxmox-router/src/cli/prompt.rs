@@ -0,0 +1,179 @@
+//! Interactively ask for missing required parameters
+
+use std::collections::HashMap;
+use std::io::IsTerminal;
+
+use anyhow::{bail, Error};
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
+use serde_json::Value;
+
+use proxmox_schema::*;
+
+use super::completion::get_property_completion;
+use super::docgen::schema_description;
+use super::{CliCommand, CompletionFunction};
+
+/// Returns `true` if both stdin and stdout are terminals.
+pub(crate) fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stdout().is_terminal()
+}
+
+/// ``rustyline`` helper completing the value of a single parameter.
+struct ParameterHelper {
+    name: String,
+    schema: &'static Schema,
+    completion_functions: HashMap<String, CompletionFunction>,
+    param: HashMap<String, String>,
+}
+
+impl rustyline::completion::Completer for ParameterHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Self::Candidate>)> {
+        let completions = get_property_completion(
+            self.schema,
+            &self.name,
+            &self.completion_functions,
+            &line[..pos],
+            &self.param,
+        );
+
+        Ok((0, completions))
+    }
+}
+
+impl rustyline::hint::Hinter for ParameterHelper {
+    type Hint = String;
+}
+impl rustyline::validate::Validator for ParameterHelper {}
+impl rustyline::highlight::Highlighter for ParameterHelper {}
+impl rustyline::Helper for ParameterHelper {}
+
+/// Read a password from the terminal, see [proxmox_sys::linux::tty::read_password].
+fn read_password(prompt: &str) -> Result<String, Error> {
+    let password = proxmox_sys::linux::tty::read_password(prompt)?;
+    Ok(String::from_utf8(password)?)
+}
+
+fn read_value(
+    editor: &mut Editor<ParameterHelper, DefaultHistory>,
+    prompt: &str,
+) -> Result<String, Error> {
+    match editor.readline(prompt) {
+        Ok(line) => Ok(line.trim().to_string()),
+        Err(ReadlineError::Interrupted | ReadlineError::Eof) => bail!("aborted"),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Ask for the value of a required parameter on the terminal.
+///
+/// Shows the description, the default and the possible values of the
+/// parameter and repeats the question until the input is valid for its
+/// schema. Arrays are read one value per line, finished by an empty line.
+/// Input for parameters listed in [CliCommand::password_param] is not
+/// echoed.
+pub(crate) fn prompt_parameter(
+    cli_cmd: &CliCommand,
+    name: &str,
+    schema: &'static Schema,
+    data: &[(String, String)],
+) -> Result<Vec<String>, Error> {
+    let item_schema = match schema {
+        Schema::Array(array_schema) => array_schema.items,
+        Schema::Boolean(_) | Schema::Integer(_) | Schema::Number(_) | Schema::String(_) => schema,
+        _ => bail!("unable to ask for complex parameter value"),
+    };
+
+    let (description, default) = schema_description(schema);
+    println!("{name}: {}", description.trim());
+    if let Schema::String(StringSchema {
+        format: Some(ApiStringFormat::Enum(variants)),
+        ..
+    }) = item_schema
+    {
+        println!("Possible values:");
+        for variant in variants.iter() {
+            println!("  {} - {}", variant.value, variant.description);
+        }
+    }
+
+    let prompt = match &default {
+        Some(default) => format!("{name} [{default}]: "),
+        None => format!("{name}: "),
+    };
+
+    if cli_cmd.password_param.contains(&name) {
+        loop {
+            let mut value = read_password(&prompt)?;
+            if value.is_empty() {
+                match &default {
+                    Some(default) => value = default.clone(),
+                    None => continue,
+                }
+            }
+            match schema.parse_simple_value(&value) {
+                Ok(_) => return Ok(vec![value]),
+                Err(err) => eprintln!("invalid value - {err}"),
+            }
+        }
+    }
+
+    let mut editor = Editor::<ParameterHelper, DefaultHistory>::new()?;
+    editor.set_helper(Some(ParameterHelper {
+        name: name.to_string(),
+        schema: item_schema,
+        completion_functions: cli_cmd.completion_functions.clone(),
+        param: data.iter().cloned().collect(),
+    }));
+
+    if let Schema::Array(array_schema) = schema {
+        println!("Enter one value per line, finish with an empty line.");
+        let mut values = Vec::new();
+        let mut parsed = Vec::new();
+        loop {
+            let value = read_value(&mut editor, &prompt)?;
+            if !value.is_empty() {
+                match item_schema.parse_simple_value(&value) {
+                    Ok(item) => {
+                        values.push(value);
+                        parsed.push(item);
+                    }
+                    Err(err) => eprintln!("invalid value - {err}"),
+                }
+                continue;
+            }
+            if values.is_empty() {
+                continue;
+            }
+            match array_schema.verify_json(&Value::Array(std::mem::take(&mut parsed))) {
+                Ok(()) => return Ok(values),
+                Err(err) => {
+                    eprintln!("invalid value - {err}");
+                    values.clear();
+                }
+            }
+        }
+    }
+
+    loop {
+        let mut value = read_value(&mut editor, &prompt)?;
+        if value.is_empty() {
+            match &default {
+                Some(default) => value = default.clone(),
+                None => continue,
+            }
+        }
+        match schema.parse_simple_value(&value) {
+            Ok(_) => return Ok(vec![value]),
+            Err(err) => eprintln!("invalid value - {err}"),
+        }
+    }
+}
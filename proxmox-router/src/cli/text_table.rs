@@ -352,27 +352,13 @@ struct TableColumn {
     right_align: bool,
 }
 
-fn format_table<W: Write>(
-    output: W,
+/// Sort table rows using the configured sort keys (default: leftmost column).
+fn sort_table(
     list: &mut [Value],
     schema: &dyn ObjectSchemaType,
+    properties_to_print: &[String],
     options: &TableFormatOptions,
 ) -> Result<(), Error> {
-    let properties_to_print = if options.column_config.is_empty() {
-        extract_properties_to_print(schema.properties())
-    } else {
-        options
-            .column_config
-            .iter()
-            .map(|v| v.name.clone())
-            .collect()
-    };
-
-    let column_count = properties_to_print.len();
-    if column_count == 0 {
-        return Ok(());
-    };
-
     let sortkeys = if let Some(ref sortkeys) = options.sortkeys {
         sortkeys.clone()
     } else {
@@ -435,6 +421,32 @@ fn format_table<W: Write>(
         Ordering::Equal
     });
 
+    Ok(())
+}
+
+fn format_table<W: Write>(
+    output: W,
+    list: &mut [Value],
+    schema: &dyn ObjectSchemaType,
+    options: &TableFormatOptions,
+) -> Result<(), Error> {
+    let properties_to_print = if options.column_config.is_empty() {
+        extract_properties_to_print(schema.properties())
+    } else {
+        options
+            .column_config
+            .iter()
+            .map(|v| v.name.clone())
+            .collect()
+    };
+
+    let column_count = properties_to_print.len();
+    if column_count == 0 {
+        return Ok(());
+    };
+
+    sort_table(list, schema, &properties_to_print, options)?;
+
     let mut tabledata: Vec<TableColumn> = Vec::new();
 
     let mut column_names = Vec::new();
@@ -847,3 +859,306 @@ pub fn value_to_text<W: Write>(
     }
     Ok(())
 }
+
+/// Field separator and quoting style for [value_to_csv] and [value_to_tsv].
+#[derive(Clone, Copy)]
+enum FieldSeparator {
+    Comma,
+    Tab,
+}
+
+impl FieldSeparator {
+    fn write_record<W: Write>(self, output: &mut W, fields: &[String]) -> Result<(), Error> {
+        let mut line = String::new();
+
+        for (i, field) in fields.iter().enumerate() {
+            match self {
+                FieldSeparator::Comma => {
+                    if i > 0 {
+                        line.push(',');
+                    }
+                    // RFC 4180 quoting
+                    if field.contains([',', '"', '\n', '\r']) {
+                        line.push('"');
+                        line.push_str(&field.replace('"', "\"\""));
+                        line.push('"');
+                    } else {
+                        line.push_str(field);
+                    }
+                }
+                FieldSeparator::Tab => {
+                    if i > 0 {
+                        line.push('\t');
+                    }
+                    // TSV cannot quote, so escape separators like most tools do
+                    for c in field.chars() {
+                        match c {
+                            '\\' => line.push_str("\\\\"),
+                            '\t' => line.push_str("\\t"),
+                            '\n' => line.push_str("\\n"),
+                            '\r' => line.push_str("\\r"),
+                            c => line.push(c),
+                        }
+                    }
+                }
+            }
+        }
+
+        line.push('\n');
+        output.write_all(line.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+/// Column headers and rows of a table.
+type TableRecords = (Option<Vec<String>>, Vec<Vec<String>>);
+
+fn table_records(
+    list: &mut [Value],
+    schema: &dyn ObjectSchemaType,
+    properties_to_print: Vec<String>,
+    options: &TableFormatOptions,
+) -> Result<TableRecords, Error> {
+    if properties_to_print.is_empty() {
+        return Ok((None, Vec::new()));
+    }
+
+    sort_table(list, schema, &properties_to_print, options)?;
+
+    let mut header = Vec::new();
+    let mut columns = Vec::new();
+
+    for name in properties_to_print.iter() {
+        let (_optional, prop_schema) = match schema.lookup(name) {
+            Some(tup) => tup,
+            None => bail!("property {} does not exist in schema.", name),
+        };
+        let (column_header, _right_align, renderer) = options.lookup_column_info(name);
+        header.push(column_header);
+        columns.push((name, prop_schema, renderer));
+    }
+
+    let mut rows = Vec::new();
+    for entry in list.iter() {
+        let mut row = Vec::new();
+        for (name, prop_schema, renderer) in columns.iter() {
+            let result = if let Some(renderer) = renderer {
+                (renderer)(&entry[name], entry)
+            } else {
+                data_to_text(&entry[name], prop_schema)
+            };
+
+            match result {
+                Ok(text) => row.push(text),
+                Err(err) => bail!("unable to format property {} - {}", name, err),
+            }
+        }
+        rows.push(row);
+    }
+
+    Ok((Some(header), rows))
+}
+
+fn value_to_records(
+    data: &mut Value,
+    schema: &Schema,
+    options: &TableFormatOptions,
+) -> Result<TableRecords, Error> {
+    let configured_columns = || -> Option<Vec<String>> {
+        if options.column_config.is_empty() {
+            return None;
+        }
+        Some(
+            options
+                .column_config
+                .iter()
+                .map(|v| v.name.clone())
+                .collect(),
+        )
+    };
+
+    let columns = |properties| configured_columns().unwrap_or(properties);
+
+    match schema {
+        Schema::Null => {
+            if *data != Value::Null {
+                bail!("got unexpected data (expected null).");
+            }
+            Ok((None, Vec::new()))
+        }
+        // objects are printed as a table with a single row
+        Schema::Object(schema) => {
+            let properties = columns(extract_properties_to_print(schema.properties()));
+            table_records(std::slice::from_mut(data), schema, properties, options)
+        }
+        Schema::AllOf(schema) => {
+            let properties = columns(extract_properties_to_print(schema.properties()));
+            table_records(std::slice::from_mut(data), schema, properties, options)
+        }
+        Schema::OneOf(schema) => {
+            let properties = match configured_columns() {
+                Some(properties) => properties,
+                None => extract_one_of_variant_properties(data, schema)?,
+            };
+            table_records(std::slice::from_mut(data), schema, properties, options)
+        }
+        Schema::Array(array_schema) => {
+            let list = match data.as_array_mut() {
+                Some(list) => list,
+                None => bail!("got unexpected data (expected array)."),
+            };
+
+            match array_schema.items {
+                Schema::Object(schema) => {
+                    let properties = columns(extract_properties_to_print(schema.properties()));
+                    table_records(list, schema, properties, options)
+                }
+                Schema::AllOf(schema) => {
+                    let properties = columns(extract_properties_to_print(schema.properties()));
+                    table_records(list, schema, properties, options)
+                }
+                Schema::OneOf(schema) => {
+                    let properties = columns(extract_properties_to_print(schema.properties()));
+                    table_records(list, schema, properties, options)
+                }
+                item_schema => {
+                    let rows = list
+                        .iter()
+                        .map(|item| Ok(vec![data_to_text(item, item_schema)?]))
+                        .collect::<Result<_, Error>>()?;
+                    Ok((None, rows))
+                }
+            }
+        }
+        _ => Ok((None, vec![vec![data_to_text(data, schema)?]])),
+    }
+}
+
+fn value_to_separated_values<W: Write>(
+    mut output: W,
+    data: &mut Value,
+    schema: &Schema,
+    options: &TableFormatOptions,
+    separator: FieldSeparator,
+) -> Result<(), Error> {
+    let (header, rows) = value_to_records(data, schema, options)?;
+
+    if let Some(header) = header {
+        if !options.noheader {
+            separator.write_record(&mut output, &header)?;
+        }
+    }
+
+    for row in rows {
+        separator.write_record(&mut output, &row)?;
+    }
+
+    Ok(())
+}
+
+/// Format data as comma separated values (RFC 4180)
+///
+/// Arrays of objects are printed with one line per entry, using the
+/// columns, headers and renderers from the
+/// [column_config](TableFormatOptions::column_config) like
+/// [value_to_text]. Objects are printed as a single line. The header
+/// line is omitted if [noheader](TableFormatOptions::noheader) is set.
+pub fn value_to_csv<W: Write>(
+    output: W,
+    data: &mut Value,
+    schema: &Schema,
+    options: &TableFormatOptions,
+) -> Result<(), Error> {
+    value_to_separated_values(output, data, schema, options, FieldSeparator::Comma)
+}
+
+/// Format data as tab separated values
+///
+/// Like [value_to_csv], but fields are separated by tabs. Tabs, newlines
+/// and backslashes inside fields are escaped as `\t`, `\n`, `\r` and `\\`.
+pub fn value_to_tsv<W: Write>(
+    output: W,
+    data: &mut Value,
+    schema: &Schema,
+    options: &TableFormatOptions,
+) -> Result<(), Error> {
+    value_to_separated_values(output, data, schema, options, FieldSeparator::Tab)
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use proxmox_schema::{ArraySchema, IntegerSchema, ObjectSchema, StringSchema};
+
+    use super::*;
+
+    const ITEM_SCHEMA: Schema = ObjectSchema::new(
+        "Item.",
+        &[
+            ("comment", true, &StringSchema::new("Comment.").schema()),
+            ("id", false, &IntegerSchema::new("Id.").schema()),
+            ("name", false, &StringSchema::new("Name.").schema()),
+        ],
+    )
+    .schema();
+
+    const LIST_SCHEMA: Schema = ArraySchema::new("Items.", &ITEM_SCHEMA).schema();
+
+    fn data() -> Value {
+        json!([
+            { "id": 2, "name": "b\tc", "comment": "multi\nline" },
+            { "id": 1, "name": "a, \"quoted\"" },
+        ])
+    }
+
+    fn to_string(
+        separator: FieldSeparator,
+        data: &mut Value,
+        schema: &Schema,
+        options: &TableFormatOptions,
+    ) -> String {
+        let mut output = Vec::new();
+        value_to_separated_values(&mut output, data, schema, options, separator).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn csv_output() {
+        let options = TableFormatOptions::default();
+        assert_eq!(
+            to_string(FieldSeparator::Comma, &mut data(), &LIST_SCHEMA, &options),
+            "id,name,comment\n1,\"a, \"\"quoted\"\"\",\n2,b\tc,\"multi\nline\"\n",
+        );
+
+        let options = TableFormatOptions::default()
+            .noheader(true)
+            .column(ColumnConfig::new("name").header("Name"))
+            .column(ColumnConfig::new("id").renderer(|value, _record| Ok(format!("#{value}"))));
+        assert_eq!(
+            to_string(FieldSeparator::Comma, &mut data(), &LIST_SCHEMA, &options),
+            "\"a, \"\"quoted\"\"\",#1\nb\tc,#2\n",
+        );
+
+        let mut item = json!({ "id": 3, "name": "c" });
+        assert_eq!(
+            to_string(
+                FieldSeparator::Comma,
+                &mut item,
+                &ITEM_SCHEMA,
+                &TableFormatOptions::default()
+            ),
+            "id,name,comment\n3,c,\n",
+        );
+    }
+
+    #[test]
+    fn tsv_output() {
+        let options = TableFormatOptions::default().sortby("id", true);
+        assert_eq!(
+            to_string(FieldSeparator::Tab, &mut data(), &LIST_SCHEMA, &options),
+            "id\tname\tcomment\n2\tb\\tc\tmulti\\nline\n1\ta, \"quoted\"\t\n",
+        );
+    }
+}
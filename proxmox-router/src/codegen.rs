@@ -0,0 +1,751 @@
+//! Generate a typed Rust API client for a [Router]
+//!
+//! The generated code contains a client struct with one `async` method
+//! per API endpoint, using the [`HttpApiClient`] trait and helpers of
+//! the `proxmox-client` crate. It also requires the `serde`,
+//! `serde_json` and `http` crates.
+//!
+//! Parameters and return values use plain Rust types by default
+//! (`String`, `i64`, `Vec<T>`, ...), and [serde_json::Value] for
+//! objects. Register the API types used by the router to get the real
+//! structs and enums instead:
+//!
+//! ```
+//! # use proxmox_router::Router;
+//! # use proxmox_schema::{ApiType, ObjectSchema, Schema};
+//! use proxmox_router::codegen::ClientGenerator;
+//!
+//! # struct DataStoreConfig;
+//! # impl ApiType for DataStoreConfig {
+//! #     const API_SCHEMA: Schema = ObjectSchema::new("Datastore.", &[]).schema();
+//! # }
+//! const ROUTER: Router = Router::new();
+//!
+//! let code = ClientGenerator::new("PbsClient")
+//!     .register_type::<DataStoreConfig>("pbs_api_types::DataStoreConfig")
+//!     .generate(&ROUTER);
+//! ```
+//!
+//! Types are matched by identity of their schema, or by comparing the
+//! schema definitions field by field, because API schemas are usually
+//! constants without a fixed address.
+//!
+//! [`HttpApiClient`]: https://docs.rs/proxmox-client/latest/proxmox_client/trait.HttpApiClient.html
+
+use std::fmt::Write as _;
+
+use anyhow::Error;
+
+use proxmox_schema::{ApiStringFormat, ApiType, ObjectSchemaType, ParameterSchema, Schema};
+
+#[cfg(feature = "server")]
+use crate::ApiHandler;
+use crate::{ApiMethod, Router, SubRoute};
+
+/// Typed API client generator
+pub struct ClientGenerator {
+    struct_name: String,
+    path_prefix: String,
+    types: Vec<(&'static Schema, String)>,
+}
+
+/// A single method parameter.
+struct Param {
+    /// Name used in the API
+    api_name: String,
+    /// Rust identifier
+    ident: String,
+    rust_type: String,
+    optional: bool,
+    /// Flattened struct containing multiple API parameters
+    flatten: bool,
+}
+
+impl ClientGenerator {
+    /// Create a new generator for a client struct named `struct_name`.
+    pub fn new(struct_name: &str) -> Self {
+        Self {
+            struct_name: struct_name.to_string(),
+            path_prefix: "/api2/extjs".to_string(),
+            types: Vec::new(),
+        }
+    }
+
+    /// Set the path prefix of the API (default `/api2/extjs`)
+    ///
+    /// Note: the client expects responses in the `extjs` format.
+    pub fn path_prefix(mut self, prefix: &str) -> Self {
+        self.path_prefix = prefix.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Use the Rust type `name` (a full path) for `T`.
+    pub fn register_type<T: ApiType>(self, name: &str) -> Self {
+        self.register_schema(&T::API_SCHEMA, name)
+    }
+
+    /// Use the Rust type `name` (a full path) for `schema`.
+    ///
+    /// Types registered first take precedence if several schemas match.
+    pub fn register_schema(mut self, schema: &'static Schema, name: &str) -> Self {
+        self.types.push((schema, name.to_string()));
+        self
+    }
+
+    /// Generate the client code for `router`.
+    pub fn generate(&self, router: &Router) -> String {
+        let mut methods = String::new();
+        self.add_methods(&mut methods, router, "", &mut Vec::new());
+
+        let name = &self.struct_name;
+        let mut code = String::new();
+        code.push_str(
+            "// This file was generated by `proxmox_router::codegen::ClientGenerator`, do not edit.\n\n",
+        );
+        code.push_str("use proxmox_client::{encode_path_component, ApiPathBuilder, Error, HttpApiClient};\n\n");
+        let _ = writeln!(code, "/// Typed API client");
+        let _ = writeln!(code, "pub struct {name}<T> {{\n    client: T,\n}}\n");
+        let _ = writeln!(code, "impl<T: HttpApiClient> {name}<T> {{");
+        code.push_str("    /// Create a new client using the `client` HTTP backend.\n");
+        code.push_str("    pub fn new(client: T) -> Self {\n        Self { client }\n    }\n\n");
+        code.push_str("    /// Returns the HTTP backend.\n");
+        code.push_str("    pub fn inner(&self) -> &T {\n        &self.client\n    }\n");
+        code.push_str(&methods);
+        code.push_str("}\n");
+        code.push_str(HELPERS);
+
+        code
+    }
+
+    /// Generate the client code for `router` and write it to `output`.
+    pub fn write(&self, output: &mut dyn std::io::Write, router: &Router) -> Result<(), Error> {
+        output.write_all(self.generate(router).as_bytes())?;
+        Ok(())
+    }
+
+    fn add_methods(
+        &self,
+        code: &mut String,
+        router: &Router,
+        path: &str,
+        path_params: &mut Vec<&'static str>,
+    ) {
+        for (method, api_method) in [
+            ("get", router.get),
+            ("put", router.put),
+            ("post", router.post),
+            ("delete", router.delete),
+        ] {
+            if let Some(api_method) = api_method {
+                self.add_method(code, method, path, path_params, api_method);
+            }
+        }
+
+        match &router.subroute {
+            None => (),
+            Some(SubRoute::Map(dirmap)) => {
+                for (key, sub_router) in dirmap.iter() {
+                    self.add_methods(code, sub_router, &format!("{path}/{key}"), path_params);
+                }
+            }
+            Some(SubRoute::MatchAll { router, param_name }) => {
+                path_params.push(param_name);
+                self.add_methods(
+                    code,
+                    router,
+                    &format!("{path}/{{{param_name}}}"),
+                    path_params,
+                );
+                path_params.pop();
+            }
+        }
+    }
+
+    fn add_method(
+        &self,
+        code: &mut String,
+        method: &str,
+        path: &str,
+        path_params: &[&str],
+        api_method: &ApiMethod,
+    ) {
+        let http_method = method.to_uppercase();
+        let display_path = if path.is_empty() { "/" } else { path };
+
+        #[cfg(feature = "server")]
+        if let ApiHandler::AsyncHttp(_) | ApiHandler::AsyncHttpBodyParameters(_) =
+            api_method.handler
+        {
+            let _ = writeln!(
+                code,
+                "\n    // skipped `{http_method} {display_path}` (raw HTTP handler)"
+            );
+            return;
+        }
+
+        let params = self.method_params(api_method.parameters, path_params);
+        let with_body = matches!(method, "post" | "put");
+
+        // signature
+        let fn_name = crate::openapi::operation_id(method, path);
+        let mut args = String::new();
+        for name in path_params {
+            let param = self.path_param(api_method.parameters, name);
+            let _ = write!(args, ", {}: {}", param.ident, param.argument_type());
+        }
+        for param in params.iter().filter(|p| !p.optional) {
+            let _ = write!(args, ", {}: {}", param.ident, param.argument_type());
+        }
+        for param in params.iter().filter(|p| p.optional) {
+            let _ = write!(args, ", {}: {}", param.ident, param.argument_type());
+        }
+
+        let returns = &api_method.returns;
+        let no_data = matches!(returns.schema, Schema::Null) && !returns.optional;
+        let mut return_type = self.rust_type(returns.schema);
+        if returns.optional {
+            return_type = format!("Option<{return_type}>");
+        }
+
+        code.push('\n');
+        for line in api_method.parameters.description().lines() {
+            if line.is_empty() {
+                code.push_str("    ///\n");
+            } else {
+                let _ = writeln!(code, "    /// {line}");
+            }
+        }
+        code.push_str("    ///\n");
+        let _ = writeln!(code, "    /// `{http_method} {display_path}`");
+        if path_params.len() + params.len() > 6 {
+            code.push_str("    #[allow(clippy::too_many_arguments)]\n");
+        }
+        let _ = writeln!(
+            code,
+            "    pub async fn {fn_name}(&self{args}) -> Result<{return_type}, Error> {{"
+        );
+
+        // path
+        let mut format_str = self.path_prefix.clone();
+        let mut format_args = String::new();
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            format_str.push('/');
+            match component
+                .strip_prefix('{')
+                .and_then(|c| c.strip_suffix('}'))
+            {
+                Some(name) => {
+                    format_str.push_str("{}");
+                    let _ = write!(
+                        format_args,
+                        ", encode_path_component(&param_value(&{})?)",
+                        rust_ident(name)
+                    );
+                }
+                None => format_str.push_str(component),
+            }
+        }
+        let base = if format_args.is_empty() {
+            format!("{format_str:?}")
+        } else {
+            format!("format!({format_str:?}{format_args})")
+        };
+
+        let query_params: Vec<&Param> = if with_body {
+            Vec::new()
+        } else {
+            params.iter().collect()
+        };
+
+        if query_params.is_empty() {
+            let _ = writeln!(
+                code,
+                "        let path = ApiPathBuilder::new({base}).build();"
+            );
+        } else {
+            let _ = writeln!(code, "        let mut path = ApiPathBuilder::new({base});");
+            for param in query_params {
+                if param.flatten {
+                    let _ = writeln!(code, "        path = query_args(path, &{})?;", param.ident);
+                } else {
+                    let _ = writeln!(
+                        code,
+                        "        path = query_arg(path, {:?}, &{})?;",
+                        param.api_name, param.ident
+                    );
+                }
+            }
+            code.push_str("        let path = path.build();\n");
+        }
+
+        // body
+        let body = if with_body && !params.is_empty() {
+            code.push_str("        #[derive(::serde::Serialize)]\n");
+            code.push_str("        struct Params {\n");
+            for param in &params {
+                if param.flatten {
+                    code.push_str("            #[serde(flatten)]\n");
+                } else if param.optional {
+                    let _ = writeln!(
+                        code,
+                        "            #[serde(rename = {:?}, skip_serializing_if = \"Option::is_none\")]",
+                        param.api_name
+                    );
+                } else {
+                    let _ = writeln!(code, "            #[serde(rename = {:?})]", param.api_name);
+                }
+                let _ = writeln!(code, "            {}: {},", param.ident, param.field_type());
+            }
+            code.push_str("        }\n");
+            let fields: Vec<String> = params.iter().map(Param::field_init).collect();
+            format!("Some(Params {{ {} }})", fields.join(", "))
+        } else {
+            "None::<()>".to_string()
+        };
+
+        let request = format!(
+            "self.client\n            .request(::http::Method::{http_method}, &path, {body})\n            .await?"
+        );
+        if no_data {
+            let _ = writeln!(code, "        {request}\n            .nodata()");
+        } else {
+            let _ = writeln!(
+                code,
+                "        Ok({request}\n            .expect_json()?\n            .data)"
+            );
+        }
+        code.push_str("    }\n");
+    }
+
+    /// Collect the (non-path) parameters of a method, flattening registered types.
+    fn method_params(&self, parameters: ParameterSchema, path_params: &[&str]) -> Vec<Param> {
+        let mut params = Vec::new();
+
+        let add_properties = |params: &mut Vec<Param>, object: &dyn ObjectSchemaType| {
+            for (name, optional, schema) in object.properties() {
+                if path_params.contains(name) || params.iter().any(|p| p.api_name == *name) {
+                    continue;
+                }
+                params.push(Param {
+                    api_name: name.to_string(),
+                    ident: rust_ident(name),
+                    rust_type: self.rust_type(schema),
+                    optional: *optional,
+                    flatten: false,
+                });
+            }
+        };
+
+        match parameters {
+            ParameterSchema::AllOf(all_of) => {
+                for schema in all_of.list {
+                    match (self.registered_type(schema), schema.any_object()) {
+                        (Some(name), _) => params.push(Param {
+                            api_name: String::new(),
+                            ident: rust_ident(&type_to_snake_case(&name)),
+                            rust_type: name,
+                            optional: false,
+                            flatten: true,
+                        }),
+                        (None, Some(object)) => add_properties(&mut params, object),
+                        (None, None) => (),
+                    }
+                }
+            }
+            ParameterSchema::Object(object) => add_properties(&mut params, object),
+            ParameterSchema::OneOf(_) => params.push(Param {
+                api_name: String::new(),
+                ident: "params".to_string(),
+                rust_type: "::serde_json::Value".to_string(),
+                optional: false,
+                flatten: true,
+            }),
+        }
+
+        params
+    }
+
+    fn path_param(&self, parameters: ParameterSchema, name: &str) -> Param {
+        let rust_type = match parameters.lookup(name) {
+            Some((_optional, schema)) => self.rust_type(schema),
+            None => "String".to_string(),
+        };
+
+        Param {
+            api_name: name.to_string(),
+            ident: rust_ident(name),
+            rust_type,
+            optional: false,
+            flatten: false,
+        }
+    }
+
+    fn registered_type(&self, schema: &Schema) -> Option<String> {
+        self.types
+            .iter()
+            .find(|(registered, _)| same_schema(registered, schema))
+            .map(|(_, name)| name.clone())
+    }
+
+    /// Map a schema to a Rust type.
+    fn rust_type(&self, schema: &Schema) -> String {
+        if let Some(name) = self.registered_type(schema) {
+            return name;
+        }
+
+        match schema {
+            Schema::Null => "()".to_string(),
+            Schema::Boolean(_) => "bool".to_string(),
+            Schema::Integer(_) => "i64".to_string(),
+            Schema::Number(_) => "f64".to_string(),
+            Schema::String(_) => "String".to_string(),
+            Schema::Array(array) => format!("Vec<{}>", self.rust_type(array.items)),
+            Schema::Object(_) | Schema::AllOf(_) | Schema::OneOf(_) => {
+                "::serde_json::Value".to_string()
+            }
+        }
+    }
+}
+
+impl Param {
+    fn field_type(&self) -> String {
+        if self.optional {
+            format!("Option<{}>", self.rust_type)
+        } else {
+            self.rust_type.clone()
+        }
+    }
+
+    fn argument_type(&self) -> String {
+        if self.borrowed() {
+            "&str".to_string()
+        } else {
+            self.field_type()
+        }
+    }
+
+    /// Initializer of the body field, converting borrowed arguments.
+    fn field_init(&self) -> String {
+        if self.borrowed() {
+            format!("{0}: {0}.to_string()", self.ident)
+        } else {
+            self.ident.clone()
+        }
+    }
+
+    /// Required strings are passed as `&str`.
+    fn borrowed(&self) -> bool {
+        !self.optional && self.rust_type == "String"
+    }
+}
+
+/// Helper functions used by the generated code.
+const HELPERS: &str = r#"
+#[allow(dead_code)]
+fn param_value<T: ::serde::Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    match ::serde_json::to_value(value) {
+        Ok(::serde_json::Value::String(value)) => Ok(value),
+        Ok(value) => Ok(value.to_string()),
+        Err(err) => Err(Error::Internal("failed to serialize parameter", Box::new(err))),
+    }
+}
+
+#[allow(dead_code)]
+fn query_value(
+    path: ApiPathBuilder,
+    name: &str,
+    value: ::serde_json::Value,
+) -> ApiPathBuilder {
+    match value {
+        ::serde_json::Value::Null => path,
+        ::serde_json::Value::String(value) => path.arg(name, value),
+        ::serde_json::Value::Array(list) => list
+            .into_iter()
+            .fold(path, |path, value| query_value(path, name, value)),
+        value => path.arg(name, value),
+    }
+}
+
+#[allow(dead_code)]
+fn query_arg<T: ::serde::Serialize + ?Sized>(
+    path: ApiPathBuilder,
+    name: &str,
+    value: &T,
+) -> Result<ApiPathBuilder, Error> {
+    let value = ::serde_json::to_value(value)
+        .map_err(|err| Error::Internal("failed to serialize parameter", Box::new(err)))?;
+    Ok(query_value(path, name, value))
+}
+
+#[allow(dead_code)]
+fn query_args<T: ::serde::Serialize + ?Sized>(
+    path: ApiPathBuilder,
+    value: &T,
+) -> Result<ApiPathBuilder, Error> {
+    match ::serde_json::to_value(value) {
+        Ok(::serde_json::Value::Object(map)) => Ok(map
+            .into_iter()
+            .fold(path, |path, (name, value)| query_value(path, &name, value))),
+        Ok(_) => Err(Error::Other("expected object parameter")),
+        Err(err) => Err(Error::Internal("failed to serialize parameter", Box::new(err))),
+    }
+}
+"#;
+
+/// Returns `true` if both schemas describe the same type.
+///
+/// Compares the schema kind, descriptions, constraints and string
+/// formats, and the properties and items recursively. Defaults are
+/// ignored, verification functions are compared by address.
+fn same_schema(a: &Schema, b: &Schema) -> bool {
+    if std::ptr::eq(a, b) {
+        return true;
+    }
+
+    match (a, b) {
+        (Schema::Null, Schema::Null) => true,
+        (Schema::Boolean(a), Schema::Boolean(b)) => a.description == b.description,
+        (Schema::Integer(a), Schema::Integer(b)) => {
+            a.description == b.description && a.minimum == b.minimum && a.maximum == b.maximum
+        }
+        (Schema::Number(a), Schema::Number(b)) => {
+            a.description == b.description && a.minimum == b.minimum && a.maximum == b.maximum
+        }
+        (Schema::String(a), Schema::String(b)) => {
+            a.description == b.description
+                && a.min_length == b.min_length
+                && a.max_length == b.max_length
+                && match (a.format, b.format) {
+                    (Some(a), Some(b)) => same_format(a, b),
+                    (None, None) => true,
+                    _ => false,
+                }
+        }
+        (Schema::Array(a), Schema::Array(b)) => {
+            a.description == b.description
+                && a.min_length == b.min_length
+                && a.max_length == b.max_length
+                && same_schema(a.items, b.items)
+        }
+        (Schema::Object(a), Schema::Object(b)) => {
+            a.description == b.description
+                && a.additional_properties == b.additional_properties
+                && a.properties.len() == b.properties.len()
+                && a.properties.iter().zip(b.properties.iter()).all(
+                    |((a_name, a_optional, a), (b_name, b_optional, b))| {
+                        a_name == b_name && a_optional == b_optional && same_schema(a, b)
+                    },
+                )
+        }
+        (Schema::AllOf(a), Schema::AllOf(b)) => {
+            a.description == b.description
+                && a.list.len() == b.list.len()
+                && a.list
+                    .iter()
+                    .zip(b.list.iter())
+                    .all(|(a, b)| same_schema(a, b))
+        }
+        (Schema::OneOf(a), Schema::OneOf(b)) => {
+            a.description == b.description
+                && a.type_property_entry.0 == b.type_property_entry.0
+                && a.list.len() == b.list.len()
+                && a.list
+                    .iter()
+                    .zip(b.list.iter())
+                    .all(|((a_name, a), (b_name, b))| a_name == b_name && same_schema(a, b))
+        }
+        _ => false,
+    }
+}
+
+fn same_format(a: &ApiStringFormat, b: &ApiStringFormat) -> bool {
+    match (a, b) {
+        (ApiStringFormat::Enum(a), ApiStringFormat::Enum(b)) => {
+            a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.value == b.value)
+        }
+        (ApiStringFormat::Pattern(a), ApiStringFormat::Pattern(b)) => {
+            a.regex_string == b.regex_string
+        }
+        (ApiStringFormat::PropertyString(a), ApiStringFormat::PropertyString(b)) => {
+            same_schema(a, b)
+        }
+        (ApiStringFormat::VerifyFn(a), ApiStringFormat::VerifyFn(b)) => *a as usize == *b as usize,
+        _ => false,
+    }
+}
+
+/// Convert an API name like `max-depth` into a Rust identifier.
+fn rust_ident(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do",
+        "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let",
+        "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+        "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+        "virtual", "where", "while", "yield",
+    ];
+
+    let mut ident: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+
+    if KEYWORDS.contains(&ident.as_str()) {
+        format!("r#{ident}")
+    } else if matches!(ident.as_str(), "self" | "super" | "crate" | "path") {
+        // not usable as raw identifiers, or used by the generated code
+        format!("{ident}_")
+    } else {
+        ident
+    }
+}
+
+/// Convert a type path like `pbs_api_types::DataStoreConfig` into `data_store_config`.
+fn type_to_snake_case(name: &str) -> String {
+    let name = name.rsplit("::").next().unwrap_or(name);
+    let name = name.split('<').next().unwrap_or(name);
+
+    let mut result = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_ascii_uppercase() {
+            if i > 0 {
+                result.push('_');
+            }
+            result.push(c.to_ascii_lowercase());
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Error;
+    use serde_json::Value;
+
+    use proxmox_schema::{
+        AllOfSchema, ApiStringFormat, ApiType, ArraySchema, EnumEntry, IntegerSchema, ObjectSchema,
+        ReturnType, Schema, StringSchema,
+    };
+
+    use super::*;
+    use crate::{ApiHandler, ApiMethod, RpcEnvironment, SubdirMap};
+
+    fn dummy_method(
+        _param: Value,
+        _info: &ApiMethod,
+        _rpcenv: &mut dyn RpcEnvironment,
+    ) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+
+    struct Mode;
+
+    impl ApiType for Mode {
+        const API_SCHEMA: Schema = StringSchema::new("Mode.")
+            .format(&ApiStringFormat::Enum(&[
+                EnumEntry::new("fast", "Fast mode."),
+                EnumEntry::new("slow", "Slow mode."),
+            ]))
+            .schema();
+    }
+
+    struct ItemConfig;
+
+    impl ApiType for ItemConfig {
+        const API_SCHEMA: Schema = ObjectSchema::new(
+            "Item configuration.",
+            &[
+                ("comment", true, &StringSchema::new("Comment.").schema()),
+                ("name", false, &StringSchema::new("Item name.").schema()),
+            ],
+        )
+        .schema();
+    }
+
+    const API_METHOD_LIST: ApiMethod = ApiMethod::new(
+        &ApiHandler::Sync(&dummy_method),
+        &ObjectSchema::new(
+            "List items.",
+            &[
+                ("max-count", true, &IntegerSchema::new("Limit.").schema()),
+                ("mode", true, &Mode::API_SCHEMA),
+                ("node", false, &StringSchema::new("Node name.").schema()),
+            ],
+        ),
+    )
+    .returns(ReturnType::new(
+        false,
+        &ArraySchema::new("Item list.", &ItemConfig::API_SCHEMA).schema(),
+    ));
+
+    const API_METHOD_CREATE: ApiMethod = ApiMethod::new_full(
+        &ApiHandler::Sync(&dummy_method),
+        ParameterSchema::AllOf(&AllOfSchema::new(
+            "Create an item.",
+            &[
+                &ObjectSchema::new(
+                    "",
+                    &[("node", false, &StringSchema::new("Node name.").schema())],
+                )
+                .schema(),
+                &ItemConfig::API_SCHEMA,
+            ],
+        )),
+    );
+
+    const ITEMS_ROUTER: Router = Router::new().get(&API_METHOD_LIST).post(&API_METHOD_CREATE);
+
+    const NODE_SUBDIRS: SubdirMap = &[("items", &ITEMS_ROUTER)];
+
+    const ROUTER: Router = Router::new().match_all("node", &Router::new().subdirs(NODE_SUBDIRS));
+
+    #[test]
+    fn generate_client() {
+        let code = ClientGenerator::new("TestClient")
+            .register_type::<Mode>("crate::Mode")
+            .register_type::<ItemConfig>("crate::ItemConfig")
+            .generate(&ROUTER);
+
+        assert!(code.contains("pub struct TestClient<T> {"));
+        assert!(code.contains(
+            "    pub async fn get_node_items(&self, node: &str, max_count: Option<i64>, \
+             mode: Option<crate::Mode>) -> Result<Vec<crate::ItemConfig>, Error> {\n\
+             \x20       let mut path = ApiPathBuilder::new(format!(\"/api2/extjs/{}/items\", \
+             encode_path_component(&param_value(&node)?)));\n\
+             \x20       path = query_arg(path, \"max-count\", &max_count)?;\n\
+             \x20       path = query_arg(path, \"mode\", &mode)?;\n"
+        ));
+        assert!(code.contains(
+            "    pub async fn post_node_items(&self, node: &str, item_config: crate::ItemConfig) \
+             -> Result<(), Error> {\n"
+        ));
+        assert!(code.contains(
+            "            #[serde(flatten)]\n            item_config: crate::ItemConfig,\n"
+        ));
+        assert!(code.contains(".nodata()"));
+    }
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(rust_ident("max-depth"), "max_depth");
+        assert_eq!(rust_ident("type"), "r#type");
+        assert_eq!(rust_ident("self"), "self_");
+        assert_eq!(rust_ident("path"), "path_");
+        assert_eq!(rust_ident("2fa"), "_2fa");
+        assert_eq!(
+            type_to_snake_case("pbs_api_types::DataStoreConfig"),
+            "data_store_config"
+        );
+    }
+}
@@ -2,7 +2,9 @@
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
+pub mod codegen;
 pub mod format;
+pub mod openapi;
 
 #[cfg(feature = "cli")]
 pub mod cli;
@@ -0,0 +1,682 @@
+//! Generate an [OpenAPI 3.1](https://spec.openapis.org/oas/v3.1.0) document for a [Router]
+//!
+//! The generated document can be served by an API method, or written to
+//! a file at build time:
+//!
+//! ```
+//! # use proxmox_router::Router;
+//! use proxmox_router::openapi::OpenApiGenerator;
+//!
+//! const ROUTER: Router = Router::new();
+//!
+//! let generator = OpenApiGenerator::new("Example API", "1.0")
+//!     .server("https://localhost:8007/api2/json");
+//!
+//! let mut output = Vec::new();
+//! generator.write(&mut output, &ROUTER).unwrap();
+//! ```
+
+use std::io::Write;
+
+use anyhow::Error;
+use serde_json::{json, Map, Value};
+
+use proxmox_schema::{ApiStringFormat, ObjectSchemaType, ReturnType, Schema};
+
+#[cfg(feature = "server")]
+use crate::ApiHandler;
+use crate::{ApiMethod, Permission, Router, SubRoute};
+
+/// The OpenAPI version of the generated documents.
+pub const OPENAPI_VERSION: &str = "3.1.0";
+
+/// OpenAPI document generator
+pub struct OpenApiGenerator {
+    info: Map<String, Value>,
+    servers: Vec<Value>,
+    security_schemes: Map<String, Value>,
+    data_envelope: bool,
+}
+
+impl OpenApiGenerator {
+    /// Create a new generator, `title` and `version` describe the API.
+    pub fn new(title: &str, version: &str) -> Self {
+        let mut info = Map::new();
+        info.insert("title".to_string(), title.into());
+        info.insert("version".to_string(), version.into());
+
+        Self {
+            info,
+            servers: Vec::new(),
+            security_schemes: Map::new(),
+            data_envelope: true,
+        }
+    }
+
+    /// Set the description of the API.
+    pub fn description(mut self, description: &str) -> Self {
+        self.info
+            .insert("description".to_string(), description.into());
+        self
+    }
+
+    /// Add a server URL, including the path prefix of the API (for example `/api2/json`).
+    pub fn server(mut self, url: &str) -> Self {
+        self.servers.push(json!({ "url": url }));
+        self
+    }
+
+    /// Add a security scheme (OpenAPI `Security Scheme Object`)
+    ///
+    /// All security schemes are required for methods not accessible by
+    /// [Permission::World].
+    pub fn security_scheme(mut self, name: &str, scheme: Value) -> Self {
+        self.security_schemes.insert(name.to_string(), scheme);
+        self
+    }
+
+    /// Set whether results are wrapped in a `data` property (default)
+    ///
+    /// This is what the JSON formatter of the REST server does. Disable
+    /// this for routers which are served without formatter.
+    pub fn data_envelope(mut self, data_envelope: bool) -> Self {
+        self.data_envelope = data_envelope;
+        self
+    }
+
+    /// Generate the OpenAPI document for `router`.
+    pub fn generate(&self, router: &Router) -> Value {
+        let mut paths = Map::new();
+        self.add_paths(&mut paths, router, "", &mut Vec::new());
+
+        let mut document = json!({
+            "openapi": OPENAPI_VERSION,
+            "info": self.info,
+            "paths": paths,
+        });
+
+        if !self.servers.is_empty() {
+            document["servers"] = self.servers.clone().into();
+        }
+
+        if !self.security_schemes.is_empty() {
+            let requirement: Map<String, Value> = self
+                .security_schemes
+                .keys()
+                .map(|name| (name.clone(), json!([])))
+                .collect();
+            document["components"] = json!({ "securitySchemes": self.security_schemes });
+            document["security"] = json!([requirement]);
+        }
+
+        document
+    }
+
+    /// Generate the OpenAPI document for `router` and write it as JSON.
+    pub fn write(&self, output: &mut dyn Write, router: &Router) -> Result<(), Error> {
+        serde_json::to_writer_pretty(&mut *output, &self.generate(router))?;
+        writeln!(output)?;
+        Ok(())
+    }
+
+    fn add_paths(
+        &self,
+        paths: &mut Map<String, Value>,
+        router: &Router,
+        path: &str,
+        path_params: &mut Vec<&'static str>,
+    ) {
+        let mut item = Map::new();
+        for (method, api_method) in [
+            ("get", router.get),
+            ("put", router.put),
+            ("post", router.post),
+            ("delete", router.delete),
+        ] {
+            if let Some(api_method) = api_method {
+                item.insert(
+                    method.to_string(),
+                    self.operation(method, path, path_params, api_method),
+                );
+            }
+        }
+
+        if !item.is_empty() {
+            let path = if path.is_empty() { "/" } else { path };
+            paths.insert(path.to_string(), item.into());
+        }
+
+        match &router.subroute {
+            None => (),
+            Some(SubRoute::Map(dirmap)) => {
+                for (key, sub_router) in dirmap.iter() {
+                    self.add_paths(paths, sub_router, &format!("{path}/{key}"), path_params);
+                }
+            }
+            Some(SubRoute::MatchAll { router, param_name }) => {
+                path_params.push(param_name);
+                self.add_paths(
+                    paths,
+                    router,
+                    &format!("{path}/{{{param_name}}}"),
+                    path_params,
+                );
+                path_params.pop();
+            }
+        }
+    }
+
+    fn operation(
+        &self,
+        method: &str,
+        path: &str,
+        path_params: &[&str],
+        api_method: &ApiMethod,
+    ) -> Value {
+        #[cfg(feature = "server")]
+        let (raw_body, raw_response) = match api_method.handler {
+            ApiHandler::AsyncHttp(_) => (true, true),
+            ApiHandler::AsyncHttpBodyParameters(_) => (false, true),
+            _ => (false, false),
+        };
+        #[cfg(not(feature = "server"))]
+        let (raw_body, raw_response) = (false, false);
+
+        let with_body = matches!(method, "post" | "put");
+
+        let mut parameters = Vec::new();
+        let mut body_properties = Map::new();
+        let mut body_required = Vec::new();
+
+        for name in path_params {
+            let schema = match api_method.parameters.lookup(name) {
+                Some((_optional, schema)) => schema_to_json_schema(schema),
+                None => json!({ "type": "string" }),
+            };
+            parameters.push(parameter_object(name, "path", true, schema));
+        }
+
+        for (name, optional, schema) in api_method.parameters.properties() {
+            if path_params.contains(name) {
+                continue;
+            }
+            let schema = schema_to_json_schema(schema);
+            if with_body && !raw_body {
+                if !optional {
+                    body_required.push(Value::from(*name));
+                }
+                body_properties.insert(name.to_string(), schema);
+            } else {
+                parameters.push(parameter_object(name, "query", !optional, schema));
+            }
+        }
+
+        let description = api_method.parameters.description();
+        let mut operation = json!({
+            "operationId": operation_id(method, path),
+            "summary": description.lines().next().unwrap_or_default(),
+            "description": description,
+            "responses": {
+                "200": self.response_object(&api_method.returns, raw_response),
+            },
+            "x-proxmox-permissions": permissions_object(
+                api_method.access.description,
+                api_method.access.permission,
+            ),
+        });
+
+        if let Some(tag) = path
+            .split('/')
+            .find(|c| !c.is_empty() && !c.starts_with('{'))
+        {
+            operation["tags"] = json!([tag]);
+        }
+
+        if !parameters.is_empty() {
+            operation["parameters"] = parameters.into();
+        }
+
+        if raw_body && with_body {
+            operation["requestBody"] = json!({ "content": { "*/*": {} } });
+        } else if !body_properties.is_empty() {
+            let mut schema = json!({
+                "type": "object",
+                "properties": body_properties,
+                "additionalProperties": api_method.parameters.additional_properties(),
+            });
+            if !body_required.is_empty() {
+                schema["required"] = body_required.into();
+            }
+            operation["requestBody"] = json!({
+                "required": schema.get("required").is_some(),
+                "content": {
+                    "application/json": { "schema": schema },
+                    "application/x-www-form-urlencoded": { "schema": schema },
+                },
+            });
+        }
+
+        if let Permission::World = api_method.access.permission {
+            operation["security"] = json!([]);
+        }
+
+        if api_method.protected {
+            operation["x-proxmox-protected"] = true.into();
+        }
+
+        operation
+    }
+
+    fn response_object(&self, returns: &ReturnType, raw_response: bool) -> Value {
+        if raw_response {
+            return json!({
+                "description": "Success",
+                "content": { "*/*": {} },
+            });
+        }
+
+        let mut schema = return_type_to_json_schema(returns);
+        if self.data_envelope {
+            schema = json!({
+                "type": "object",
+                "properties": { "data": schema },
+            });
+        }
+
+        json!({
+            "description": "Success",
+            "content": {
+                "application/json": { "schema": schema },
+            },
+        })
+    }
+}
+
+fn parameter_object(name: &str, location: &str, required: bool, mut schema: Value) -> Value {
+    let description = schema
+        .as_object_mut()
+        .and_then(|schema| schema.remove("description"));
+    let is_array = schema["type"] == "array";
+
+    let mut parameter = json!({
+        "name": name,
+        "in": location,
+        "required": required,
+        "schema": schema,
+    });
+    if let Some(description) = description {
+        parameter["description"] = description;
+    }
+    if is_array {
+        parameter["style"] = "form".into();
+        parameter["explode"] = true.into();
+    }
+
+    parameter
+}
+
+/// Create an operation id like `get_nodes_node_status` for `GET /nodes/{node}/status`.
+pub(crate) fn operation_id(method: &str, path: &str) -> String {
+    let mut id = method.to_string();
+    for component in path.split('/').filter(|c| !c.is_empty()) {
+        id.push('_');
+        id.extend(component.chars().filter_map(|c| match c {
+            '{' | '}' => None,
+            c if c.is_ascii_alphanumeric() => Some(c),
+            _ => Some('_'),
+        }));
+    }
+    id
+}
+
+fn permissions_object(description: Option<&str>, permission: &Permission) -> Value {
+    let mut result = json!({ "check": permission_to_json(permission) });
+    if let Some(description) = description {
+        result["description"] = description.into();
+    }
+    result
+}
+
+/// Convert a [Permission] into a JSON value (used as OpenAPI extension).
+pub fn permission_to_json(permission: &Permission) -> Value {
+    match permission {
+        Permission::Superuser => json!({ "type": "superuser" }),
+        Permission::World => json!({ "type": "world" }),
+        Permission::Anybody => json!({ "type": "anybody" }),
+        Permission::User(user) => json!({ "type": "user", "user": user }),
+        Permission::UserParam(param) => json!({ "type": "user-param", "param": param }),
+        Permission::Group(group) => json!({ "type": "group", "group": group }),
+        Permission::WithParam(param, permission) => json!({
+            "type": "with-param",
+            "param": param,
+            "permission": permission_to_json(permission),
+        }),
+        Permission::Privilege(path, privileges, partial) => json!({
+            "type": "privilege",
+            "path": format!("/{}", path.join("/")),
+            "privileges": privileges,
+            "partial": partial,
+        }),
+        Permission::And(list) => json!({
+            "type": "and",
+            "list": list.iter().map(|p| permission_to_json(p)).collect::<Vec<_>>(),
+        }),
+        Permission::Or(list) => json!({
+            "type": "or",
+            "list": list.iter().map(|p| permission_to_json(p)).collect::<Vec<_>>(),
+        }),
+    }
+}
+
+/// Convert a [ReturnType] into a JSON Schema, optional return types may also be `null`.
+pub fn return_type_to_json_schema(returns: &ReturnType) -> Value {
+    let schema = schema_to_json_schema(returns.schema);
+    if returns.optional {
+        json!({ "oneOf": [{ "type": "null" }, schema] })
+    } else {
+        schema
+    }
+}
+
+/// Convert a [Schema] into a JSON Schema (draft 2020-12, as used by OpenAPI 3.1)
+///
+/// * [AllOfSchema](proxmox_schema::AllOfSchema)s are merged into a
+///   single object schema, because their members usually do not allow
+///   additional properties.
+/// * Each variant of a [OneOfSchema](proxmox_schema::OneOfSchema)
+///   becomes an object schema with a constant type property.
+/// * Property strings are strings with a `property-string` format, the
+///   schema of the encoded data is available as `contentSchema`.
+pub fn schema_to_json_schema(schema: &Schema) -> Value {
+    let mut result = Map::new();
+
+    let mut set = |key: &str, value: Option<Value>| {
+        if let Some(value) = value {
+            result.insert(key.to_string(), value);
+        }
+    };
+
+    match schema {
+        Schema::Null => set("type", Some("null".into())),
+        Schema::Boolean(s) => {
+            set("type", Some("boolean".into()));
+            set("description", Some(s.description.into()));
+            set("default", s.default.map(Value::from));
+        }
+        Schema::Integer(s) => {
+            set("type", Some("integer".into()));
+            set("description", Some(s.description.into()));
+            set("minimum", s.minimum.map(Value::from));
+            set("maximum", s.maximum.map(Value::from));
+            set("default", s.default.map(Value::from));
+        }
+        Schema::Number(s) => {
+            set("type", Some("number".into()));
+            set("description", Some(s.description.into()));
+            set("minimum", s.minimum.map(Value::from));
+            set("maximum", s.maximum.map(Value::from));
+            set("default", s.default.map(Value::from));
+        }
+        Schema::String(s) => {
+            set("type", Some("string".into()));
+            set("description", Some(s.description.into()));
+            set("minLength", s.min_length.map(Value::from));
+            set("maxLength", s.max_length.map(Value::from));
+            set("default", s.default.map(Value::from));
+            match s.format {
+                Some(ApiStringFormat::Enum(list)) => {
+                    let values = list.iter().map(|entry| entry.value.into()).collect();
+                    set("enum", Some(Value::Array(values)));
+                }
+                Some(ApiStringFormat::Pattern(regex)) => {
+                    set("pattern", Some(regex.regex_string.into()));
+                }
+                Some(ApiStringFormat::PropertyString(schema)) => {
+                    set("format", Some("property-string".into()));
+                    set("contentSchema", Some(schema_to_json_schema(schema)));
+                }
+                Some(ApiStringFormat::VerifyFn(_)) | None => (),
+            }
+        }
+        Schema::Array(s) => {
+            set("type", Some("array".into()));
+            set("description", Some(s.description.into()));
+            set("items", Some(schema_to_json_schema(s.items)));
+            set("minItems", s.min_length.map(Value::from));
+            set("maxItems", s.max_length.map(Value::from));
+        }
+        Schema::Object(s) => return object_to_json_schema(s, &[]),
+        Schema::AllOf(s) => return object_to_json_schema(s, &[]),
+        Schema::OneOf(s) => {
+            let (type_name, _optional, type_schema) = s.type_property_entry;
+            let variants: Vec<Value> = s
+                .list
+                .iter()
+                .map(|(variant, schema)| {
+                    let mut type_schema = schema_to_json_schema(type_schema);
+                    type_schema["const"] = (*variant).into();
+                    match schema.any_object() {
+                        Some(object) => object_to_json_schema(object, &[(type_name, type_schema)]),
+                        None => schema_to_json_schema(schema),
+                    }
+                })
+                .collect();
+
+            set("description", Some(s.description.into()));
+            set("oneOf", Some(variants.into()));
+        }
+    }
+
+    result.into()
+}
+
+/// Convert an object schema, `extra` properties are added as required properties.
+fn object_to_json_schema(schema: &dyn ObjectSchemaType, extra: &[(&str, Value)]) -> Value {
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+
+    for (name, value) in extra {
+        properties.insert(name.to_string(), value.clone());
+        required.push(Value::from(*name));
+    }
+
+    for (name, optional, schema) in schema.properties() {
+        if properties.contains_key(*name) {
+            continue;
+        }
+        properties.insert(name.to_string(), schema_to_json_schema(schema));
+        if !optional {
+            required.push(Value::from(*name));
+        }
+    }
+
+    let mut result = json!({
+        "type": "object",
+        "description": schema.description(),
+        "properties": properties,
+        "additionalProperties": schema.additional_properties(),
+    });
+    if !required.is_empty() {
+        result["required"] = required.into();
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::Error;
+    use serde_json::{json, Value};
+
+    use proxmox_schema::{
+        ApiStringFormat, ArraySchema, EnumEntry, IntegerSchema, ObjectSchema, Schema, StringSchema,
+    };
+
+    use super::*;
+    use crate::{ApiHandler, ApiMethod, RpcEnvironment, SubdirMap};
+
+    fn dummy_method(
+        _param: Value,
+        _info: &ApiMethod,
+        _rpcenv: &mut dyn RpcEnvironment,
+    ) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+
+    const MODE_SCHEMA: Schema = StringSchema::new("Mode.")
+        .format(&ApiStringFormat::Enum(&[
+            EnumEntry::new("fast", "Fast mode."),
+            EnumEntry::new("slow", "Slow mode."),
+        ]))
+        .schema();
+
+    const OPTIONS_SCHEMA: Schema = ObjectSchema::new(
+        "Options.",
+        &[(
+            "limit",
+            true,
+            &IntegerSchema::new("Limit.").minimum(1).schema(),
+        )],
+    )
+    .schema();
+
+    const OPTIONS_STRING_SCHEMA: Schema = StringSchema::new("Options as property string.")
+        .format(&ApiStringFormat::PropertyString(&OPTIONS_SCHEMA))
+        .schema();
+
+    const ITEM_LIST_SCHEMA: Schema =
+        ArraySchema::new("Item list.", &StringSchema::new("Item name.").schema()).schema();
+
+    const API_METHOD_LIST: ApiMethod = ApiMethod::new(
+        &ApiHandler::Sync(&dummy_method),
+        &ObjectSchema::new(
+            "List items.",
+            &[
+                ("mode", true, &MODE_SCHEMA),
+                ("node", false, &StringSchema::new("Node name.").schema()),
+            ],
+        ),
+    )
+    .returns(ReturnType::new(false, &ITEM_LIST_SCHEMA))
+    .access(None, &Permission::World);
+
+    const API_METHOD_CREATE: ApiMethod = ApiMethod::new(
+        &ApiHandler::Sync(&dummy_method),
+        &ObjectSchema::new(
+            "Create an item.",
+            &[
+                ("name", false, &StringSchema::new("Item name.").schema()),
+                ("node", false, &StringSchema::new("Node name.").schema()),
+                ("options", true, &OPTIONS_STRING_SCHEMA),
+            ],
+        ),
+    )
+    .protected(true)
+    .access(
+        Some("Requires modify privileges."),
+        &Permission::Privilege(&["nodes", "{node}"], 4, false),
+    );
+
+    const ITEMS_ROUTER: Router = Router::new().get(&API_METHOD_LIST).post(&API_METHOD_CREATE);
+
+    const NODE_SUBDIRS: SubdirMap = &[("items", &ITEMS_ROUTER)];
+
+    const ROUTER: Router = Router::new().match_all("node", &Router::new().subdirs(NODE_SUBDIRS));
+
+    #[test]
+    fn generate_document() {
+        let document = OpenApiGenerator::new("Test API", "1.0")
+            .server("https://localhost:8007/api2/json")
+            .security_scheme(
+                "ticket",
+                json!({ "type": "apiKey", "in": "cookie", "name": "Ticket" }),
+            )
+            .generate(&ROUTER);
+
+        assert_eq!(document["openapi"], OPENAPI_VERSION);
+        assert_eq!(document["security"], json!([{ "ticket": [] }]));
+
+        let paths = document["paths"].as_object().unwrap();
+        assert_eq!(paths.keys().collect::<Vec<_>>(), ["/{node}/items"]);
+
+        let get = &paths["/{node}/items"]["get"];
+        assert_eq!(get["operationId"], "get_node_items");
+        assert_eq!(get["security"], json!([]));
+        assert_eq!(
+            get["parameters"],
+            json!([
+                {
+                    "name": "node",
+                    "in": "path",
+                    "required": true,
+                    "description": "Node name.",
+                    "schema": { "type": "string" },
+                },
+                {
+                    "name": "mode",
+                    "in": "query",
+                    "required": false,
+                    "description": "Mode.",
+                    "schema": { "type": "string", "enum": ["fast", "slow"] },
+                },
+            ])
+        );
+        assert_eq!(
+            get["responses"]["200"]["content"]["application/json"]["schema"],
+            json!({
+                "type": "object",
+                "properties": {
+                    "data": {
+                        "type": "array",
+                        "description": "Item list.",
+                        "items": { "type": "string", "description": "Item name." },
+                    },
+                },
+            })
+        );
+
+        let post = &paths["/{node}/items"]["post"];
+        assert_eq!(post["x-proxmox-protected"], true);
+        assert!(post.get("security").is_none());
+        assert_eq!(
+            post["x-proxmox-permissions"],
+            json!({
+                "description": "Requires modify privileges.",
+                "check": {
+                    "type": "privilege",
+                    "path": "/nodes/{node}",
+                    "privileges": 4,
+                    "partial": false,
+                },
+            })
+        );
+        assert_eq!(
+            post["requestBody"]["content"]["application/json"]["schema"],
+            json!({
+                "type": "object",
+                "properties": {
+                    "name": { "type": "string", "description": "Item name." },
+                    "options": {
+                        "type": "string",
+                        "description": "Options as property string.",
+                        "format": "property-string",
+                        "contentSchema": {
+                            "type": "object",
+                            "description": "Options.",
+                            "properties": {
+                                "limit": {
+                                    "type": "integer",
+                                    "description": "Limit.",
+                                    "minimum": 1,
+                                },
+                            },
+                            "additionalProperties": false,
+                        },
+                    },
+                },
+                "required": ["name"],
+                "additionalProperties": false,
+            })
+        );
+    }
+}
@@ -788,6 +788,10 @@ pub struct ApiMethod {
     /// This flag indicates that the provided method may change the local timezone, so the server
     /// should do a tzset afterwards
     pub reload_timezone: bool,
+    /// This flag indicates that the result of a `GET` request may be cached by clients. The
+    /// handler needs to set the `digest` result attribute, which is then used as cache validator
+    /// (`ETag`), so it has to change whenever the result changes.
+    pub cacheable: bool,
     /// Parameter type Schema
     pub parameters: ParameterSchema,
     /// Return type Schema
@@ -817,6 +821,7 @@ impl ApiMethod {
             returns: ReturnType::new(false, &NULL_SCHEMA),
             protected: false,
             reload_timezone: false,
+            cacheable: false,
             access: ApiAccess {
                 description: None,
                 permission: &Permission::Superuser,
@@ -835,6 +840,7 @@ impl ApiMethod {
             returns: ReturnType::new(false, &NULL_SCHEMA),
             protected: false,
             reload_timezone: false,
+            cacheable: false,
             access: ApiAccess {
                 description: None,
                 permission: &Permission::Superuser,
@@ -860,6 +866,12 @@ impl ApiMethod {
         self
     }
 
+    pub const fn cacheable(mut self, cacheable: bool) -> Self {
+        self.cacheable = cacheable;
+
+        self
+    }
+
     pub const fn access(
         mut self,
         description: Option<&'static str>,
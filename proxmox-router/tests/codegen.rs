@@ -0,0 +1,260 @@
+//! Compile and run the client generated by `ClientGenerator` for a test router.
+//!
+//! The generated code is checked in as `tests/codegen/client.rs`, so that it
+//! gets compiled with the tests.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use proxmox_client::{HttpApiClient, HttpApiResponse, HttpApiResponseStream};
+use proxmox_router::codegen::ClientGenerator;
+use proxmox_router::{ApiHandler, ApiMethod, Router, RpcEnvironment, SubdirMap};
+use proxmox_schema::{
+    AllOfSchema, ApiStringFormat, ApiType, ArraySchema, BooleanSchema, EnumEntry, IntegerSchema,
+    ObjectSchema, ParameterSchema, ReturnType, Schema, StringSchema,
+};
+
+#[rustfmt::skip]
+#[path = "codegen/client.rs"]
+mod client;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Mode {
+    Fast,
+    Slow,
+}
+
+impl ApiType for Mode {
+    const API_SCHEMA: Schema = StringSchema::new("Mode.")
+        .format(&ApiStringFormat::Enum(&[
+            EnumEntry::new("fast", "Fast mode."),
+            EnumEntry::new("slow", "Slow mode."),
+        ]))
+        .schema();
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ItemConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comment: Option<String>,
+    pub name: String,
+}
+
+impl ApiType for ItemConfig {
+    const API_SCHEMA: Schema = ObjectSchema::new(
+        "Item configuration.",
+        &[
+            ("comment", true, &StringSchema::new("Comment.").schema()),
+            ("name", false, &StringSchema::new("Item name.").schema()),
+        ],
+    )
+    .schema();
+}
+
+fn dummy_method(
+    _param: Value,
+    _info: &ApiMethod,
+    _rpcenv: &mut dyn RpcEnvironment,
+) -> Result<Value, Error> {
+    Ok(Value::Null)
+}
+
+const API_METHOD_LIST: ApiMethod = ApiMethod::new(
+    &ApiHandler::Sync(&dummy_method),
+    &ObjectSchema::new(
+        "List items.",
+        &[
+            ("max-count", true, &IntegerSchema::new("Limit.").schema()),
+            ("mode", true, &Mode::API_SCHEMA),
+            ("node", false, &StringSchema::new("Node name.").schema()),
+            (
+                "type",
+                true,
+                &ArraySchema::new("Item types.", &StringSchema::new("Item type.").schema())
+                    .schema(),
+            ),
+        ],
+    ),
+)
+.returns(ReturnType::new(
+    false,
+    &ArraySchema::new("Item list.", &ItemConfig::API_SCHEMA).schema(),
+));
+
+const API_METHOD_CREATE: ApiMethod = ApiMethod::new_full(
+    &ApiHandler::Sync(&dummy_method),
+    ParameterSchema::AllOf(&AllOfSchema::new(
+        "Create an item.",
+        &[
+            &ObjectSchema::new(
+                "",
+                &[
+                    ("force", true, &BooleanSchema::new("Overwrite.").schema()),
+                    ("node", false, &StringSchema::new("Node name.").schema()),
+                    ("owner", false, &StringSchema::new("Item owner.").schema()),
+                ],
+            )
+            .schema(),
+            &ItemConfig::API_SCHEMA,
+        ],
+    )),
+);
+
+const API_METHOD_DELETE: ApiMethod = ApiMethod::new(
+    &ApiHandler::Sync(&dummy_method),
+    &ObjectSchema::new(
+        "Delete an item.",
+        &[
+            ("name", false, &StringSchema::new("Item name.").schema()),
+            ("node", false, &StringSchema::new("Node name.").schema()),
+        ],
+    ),
+);
+
+const ITEM_ROUTER: Router = Router::new().delete(&API_METHOD_DELETE);
+
+const ITEMS_ROUTER: Router = Router::new()
+    .get(&API_METHOD_LIST)
+    .post(&API_METHOD_CREATE)
+    .match_all("name", &ITEM_ROUTER);
+
+const NODE_SUBDIRS: SubdirMap = &[("items", &ITEMS_ROUTER)];
+
+const ROUTER: Router = Router::new().match_all("node", &Router::new().subdirs(NODE_SUBDIRS));
+
+fn generate() -> String {
+    ClientGenerator::new("TestClient")
+        .register_type::<Mode>("crate::Mode")
+        .register_type::<ItemConfig>("crate::ItemConfig")
+        .generate(&ROUTER)
+}
+
+/// Records the requests and returns `data` as response.
+#[derive(Default)]
+struct MockClient {
+    requests: Mutex<Vec<(http::Method, String, Option<Value>)>>,
+    data: Mutex<Option<Value>>,
+}
+
+impl HttpApiClient for MockClient {
+    type ResponseFuture<'a> =
+        Pin<Box<dyn Future<Output = Result<HttpApiResponse, proxmox_client::Error>> + 'a>>;
+
+    type Body = ();
+
+    type ResponseStreamFuture<'a> = Pin<
+        Box<dyn Future<Output = Result<HttpApiResponseStream<()>, proxmox_client::Error>> + 'a>,
+    >;
+
+    fn request<'a, T>(
+        &'a self,
+        method: http::Method,
+        path_and_query: &'a str,
+        params: Option<T>,
+    ) -> Self::ResponseFuture<'a>
+    where
+        T: Serialize + 'a,
+    {
+        let params = params.map(|params| serde_json::to_value(params).unwrap());
+        self.requests
+            .lock()
+            .unwrap()
+            .push((method, path_and_query.to_string(), params));
+
+        let mut response = json!({ "success": true });
+        if let Some(data) = self.data.lock().unwrap().take() {
+            response["data"] = data;
+        }
+
+        Box::pin(async move {
+            Ok(HttpApiResponse {
+                status: 200,
+                content_type: Some("application/json".to_string()),
+                body: serde_json::to_vec(&response).unwrap(),
+            })
+        })
+    }
+
+    fn streaming_request<'a, T>(
+        &'a self,
+        _method: http::Method,
+        _path_and_query: &'a str,
+        _params: Option<T>,
+    ) -> Self::ResponseStreamFuture<'a>
+    where
+        T: Serialize + 'a,
+    {
+        Box::pin(async { Err(proxmox_client::Error::Other("streaming is not supported")) })
+    }
+}
+
+#[test]
+fn generated_client_is_up_to_date() {
+    assert_eq!(
+        generate(),
+        include_str!("codegen/client.rs"),
+        "generated client changed, update tests/codegen/client.rs"
+    );
+}
+
+#[test]
+fn generated_client_requests() -> Result<(), proxmox_client::Error> {
+    let client = client::TestClient::new(MockClient::default());
+
+    *client.inner().data.lock().unwrap() = Some(json!([{ "name": "a" }]));
+    let list = futures::executor::block_on(client.get_node_items(
+        "node 1",
+        Some(10),
+        Some(Mode::Fast),
+        Some(vec!["x".to_string(), "y".to_string()]),
+    ))?;
+    assert_eq!(
+        list,
+        [ItemConfig {
+            comment: None,
+            name: "a".to_string(),
+        }]
+    );
+
+    futures::executor::block_on(client.post_node_items(
+        "node1",
+        "root",
+        ItemConfig {
+            comment: Some("test".to_string()),
+            name: "b".to_string(),
+        },
+        None,
+    ))?;
+
+    futures::executor::block_on(client.delete_node_items_name("node1", "b"))?;
+
+    let requests = client.inner().requests.lock().unwrap();
+    assert_eq!(
+        *requests,
+        [
+            (
+                http::Method::GET,
+                "/api2/extjs/node%201/items?max%2Dcount=10&mode=fast&type=x&type=y".to_string(),
+                None,
+            ),
+            (
+                http::Method::POST,
+                "/api2/extjs/node1/items".to_string(),
+                Some(json!({ "comment": "test", "name": "b", "owner": "root" })),
+            ),
+            (
+                http::Method::DELETE,
+                "/api2/extjs/node1/items/b".to_string(),
+                None,
+            ),
+        ]
+    );
+
+    Ok(())
+}
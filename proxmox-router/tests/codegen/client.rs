@@ -0,0 +1,117 @@
+// This file was generated by `proxmox_router::codegen::ClientGenerator`, do not edit.
+
+use proxmox_client::{encode_path_component, ApiPathBuilder, Error, HttpApiClient};
+
+/// Typed API client
+pub struct TestClient<T> {
+    client: T,
+}
+
+impl<T: HttpApiClient> TestClient<T> {
+    /// Create a new client using the `client` HTTP backend.
+    pub fn new(client: T) -> Self {
+        Self { client }
+    }
+
+    /// Returns the HTTP backend.
+    pub fn inner(&self) -> &T {
+        &self.client
+    }
+
+    /// List items.
+    ///
+    /// `GET /{node}/items`
+    pub async fn get_node_items(&self, node: &str, max_count: Option<i64>, mode: Option<crate::Mode>, r#type: Option<Vec<String>>) -> Result<Vec<crate::ItemConfig>, Error> {
+        let mut path = ApiPathBuilder::new(format!("/api2/extjs/{}/items", encode_path_component(&param_value(&node)?)));
+        path = query_arg(path, "max-count", &max_count)?;
+        path = query_arg(path, "mode", &mode)?;
+        path = query_arg(path, "type", &r#type)?;
+        let path = path.build();
+        Ok(self.client
+            .request(::http::Method::GET, &path, None::<()>)
+            .await?
+            .expect_json()?
+            .data)
+    }
+
+    /// Create an item.
+    ///
+    /// `POST /{node}/items`
+    pub async fn post_node_items(&self, node: &str, owner: &str, item_config: crate::ItemConfig, force: Option<bool>) -> Result<(), Error> {
+        let path = ApiPathBuilder::new(format!("/api2/extjs/{}/items", encode_path_component(&param_value(&node)?))).build();
+        #[derive(::serde::Serialize)]
+        struct Params {
+            #[serde(rename = "force", skip_serializing_if = "Option::is_none")]
+            force: Option<bool>,
+            #[serde(rename = "owner")]
+            owner: String,
+            #[serde(flatten)]
+            item_config: crate::ItemConfig,
+        }
+        self.client
+            .request(::http::Method::POST, &path, Some(Params { force, owner: owner.to_string(), item_config }))
+            .await?
+            .nodata()
+    }
+
+    /// Delete an item.
+    ///
+    /// `DELETE /{node}/items/{name}`
+    pub async fn delete_node_items_name(&self, node: &str, name: &str) -> Result<(), Error> {
+        let path = ApiPathBuilder::new(format!("/api2/extjs/{}/items/{}", encode_path_component(&param_value(&node)?), encode_path_component(&param_value(&name)?))).build();
+        self.client
+            .request(::http::Method::DELETE, &path, None::<()>)
+            .await?
+            .nodata()
+    }
+}
+
+#[allow(dead_code)]
+fn param_value<T: ::serde::Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+    match ::serde_json::to_value(value) {
+        Ok(::serde_json::Value::String(value)) => Ok(value),
+        Ok(value) => Ok(value.to_string()),
+        Err(err) => Err(Error::Internal("failed to serialize parameter", Box::new(err))),
+    }
+}
+
+#[allow(dead_code)]
+fn query_value(
+    path: ApiPathBuilder,
+    name: &str,
+    value: ::serde_json::Value,
+) -> ApiPathBuilder {
+    match value {
+        ::serde_json::Value::Null => path,
+        ::serde_json::Value::String(value) => path.arg(name, value),
+        ::serde_json::Value::Array(list) => list
+            .into_iter()
+            .fold(path, |path, value| query_value(path, name, value)),
+        value => path.arg(name, value),
+    }
+}
+
+#[allow(dead_code)]
+fn query_arg<T: ::serde::Serialize + ?Sized>(
+    path: ApiPathBuilder,
+    name: &str,
+    value: &T,
+) -> Result<ApiPathBuilder, Error> {
+    let value = ::serde_json::to_value(value)
+        .map_err(|err| Error::Internal("failed to serialize parameter", Box::new(err)))?;
+    Ok(query_value(path, name, value))
+}
+
+#[allow(dead_code)]
+fn query_args<T: ::serde::Serialize + ?Sized>(
+    path: ApiPathBuilder,
+    value: &T,
+) -> Result<ApiPathBuilder, Error> {
+    match ::serde_json::to_value(value) {
+        Ok(::serde_json::Value::Object(map)) => Ok(map
+            .into_iter()
+            .fold(path, |path, (name, value)| query_value(path, &name, value))),
+        Ok(_) => Err(Error::Other("expected object parameter")),
+        Err(err) => Err(Error::Internal("failed to serialize parameter", Box::new(err))),
+    }
+}
@@ -300,3 +300,215 @@ fn test_group_help() {
     // println!("--- END EXPECTED DOC OUTPUT ---");
     assert_eq!(help, expected_group_help_text());
 }
+
+fn expected_markdown_text() -> &'static str {
+    r##"
+# clicmd
+
+A test command.
+
+## Synopsis
+
+```text
+clicmd help [{<command>}] [OPTIONS]
+clicmd l0c1 --another-required-arg <string> --required-arg <string> [OPTIONS]
+clicmd l0c2 <required-arg> --another-required-arg <string> [OPTIONS]
+clicmd l0sub l1c1 --another-required-arg <string> --required-arg <string> [OPTIONS]
+clicmd l0sub l1c2 --another-required-arg <string> --required-arg <string> [OPTIONS]
+```
+
+## Global Options
+
+### Options available for command group `clicmd l0sub`
+
+- `--global1` `one|two`
+  A global option.
+- `--global2` `<string>`
+  A second global option.
+
+## Commands
+
+### `clicmd help`
+
+```text
+clicmd help [{<command>}] [OPTIONS]
+```
+
+Get help about specified command (or sub-command).
+
+- `<command>` `<string>`
+  Command. This may be a list in order to specify nested sub-commands. Can be specified more than once.
+
+Optional parameters:
+
+- `--verbose` `<boolean>`
+  Verbose help.
+
+### `clicmd l0c1`
+
+```text
+clicmd l0c1 --another-required-arg <string> --required-arg <string> [OPTIONS]
+```
+
+Simple API method with one required and one optional argument.
+
+- `--another-required-arg` `<string>`
+  A second required string argument.
+- `--required-arg` `<string>`
+  Required string argument.
+
+Optional parameters:
+
+- `--optional-arg` `<boolean>` (default: `false`)
+  Optional boolean argument.
+
+### `clicmd l0c2`
+
+```text
+clicmd l0c2 <required-arg> --another-required-arg <string> [OPTIONS]
+```
+
+Simple API method with one required and one optional argument.
+
+- `<required-arg>` `<string>`
+  Required string argument.
+- `--another-required-arg` `<string>`
+  A second required string argument.
+
+Optional parameters:
+
+- `--optional-arg` `<boolean>` (default: `false`)
+  Optional boolean argument.
+
+### `clicmd l0sub l1c1`
+
+```text
+clicmd l0sub l1c1 --another-required-arg <string> --required-arg <string> [OPTIONS]
+```
+
+Simple API method with one required and one optional argument.
+
+- `--another-required-arg` `<string>`
+  A second required string argument.
+- `--required-arg` `<string>`
+  Required string argument.
+
+Optional parameters:
+
+- `--optional-arg` `<boolean>` (default: `false`)
+  Optional boolean argument.
+
+Inherited group parameters: `--global1`, `--global2`
+
+### `clicmd l0sub l1c2`
+
+```text
+clicmd l0sub l1c2 --another-required-arg <string> --required-arg <string> [OPTIONS]
+```
+
+Simple API method with one required and one optional argument.
+
+- `--another-required-arg` `<string>`
+  A second required string argument.
+- `--required-arg` `<string>`
+  Required string argument.
+
+Optional parameters:
+
+- `--optional-arg` `<boolean>` (default: `false`)
+  Optional boolean argument.
+
+Inherited group parameters: `--global1`, `--global2`
+"##
+        .trim_start()
+}
+
+#[test]
+fn test_markdown_reference() {
+    let doc = proxmox_router::cli::generate_markdown(
+        "clicmd",
+        "A test command.",
+        &get_complex_test_cmddef().into(),
+    );
+    // println!("--- BEGIN EXPECTED DOC OUTPUT ---");
+    // print!("{doc}");
+    // println!("--- END EXPECTED DOC OUTPUT ---");
+    assert_eq!(doc, expected_markdown_text());
+}
+
+#[test]
+fn test_man_page() {
+    let doc = proxmox_router::cli::generate_man_page(
+        "clicmd",
+        "1",
+        "A test command.",
+        &get_complex_test_cmddef().into(),
+    );
+
+    assert!(doc.starts_with(
+        r##".TH "CLICMD" "1"
+.SH NAME
+clicmd \- A test command.
+.SH SYNOPSIS
+.nf
+clicmd help [{<command>}] [OPTIONS]
+clicmd l0c1 \-\-another\-required\-arg <string> \-\-required\-arg <string> [OPTIONS]
+clicmd l0c2 <required\-arg> \-\-another\-required\-arg <string> [OPTIONS]
+clicmd l0sub l1c1 \-\-another\-required\-arg <string> \-\-required\-arg <string> [OPTIONS]
+clicmd l0sub l1c2 \-\-another\-required\-arg <string> \-\-required\-arg <string> [OPTIONS]
+.fi
+.SH GLOBAL OPTIONS
+.SS Options available for command group \fBclicmd l0sub\fR
+.TP
+\fB\-\-global1\fR \fIone|two\fR
+A global option.
+"##
+    ));
+
+    assert!(doc.contains(
+        r##".SS clicmd l0c2
+.nf
+\fBclicmd l0c2 <required\-arg> \-\-another\-required\-arg <string> [OPTIONS]\fR
+.fi
+.PP
+Simple API method with one required and one optional argument.
+.TP
+\fB<required\-arg>\fR \fI<string>\fR
+Required string argument.
+.TP
+\fB\-\-another\-required\-arg\fR \fI<string>\fR
+A second required string argument.
+.PP
+Optional parameters:
+.TP
+\fB\-\-optional\-arg\fR \fI<boolean>\fR (default=\fIfalse\fR)
+Optional boolean argument.
+"##
+    ));
+
+    assert!(doc.ends_with(
+        ".PP\nInherited group parameters: \\fB\\-\\-global1\\fR, \\fB\\-\\-global2\\fR\n"
+    ));
+}
+
+const API_METHOD_ESCAPE: ApiMethod = ApiMethod::new(
+    &ApiHandler::Sync(&dummy_method),
+    &ObjectSchema::new(
+        "Escape test.\n  indented text\n.starts with a dot\n'starts with a quote",
+        &[],
+    ),
+);
+
+#[test]
+fn test_man_page_escape() {
+    let doc = proxmox_router::cli::generate_man_page(
+        "clicmd",
+        "1",
+        "",
+        &CliCommand::new(&API_METHOD_ESCAPE).into(),
+    );
+
+    assert!(doc.ends_with(
+        ".PP\nEscape test.\n  indented text\n\\&.starts with a dot\n\\&'starts with a quote\n"
+    ));
+}
@@ -1,4 +1,6 @@
 //! RRD toolkit - create/manage/update proxmox RRD (v2) file
+//!
+//! Also exports and imports RRD files as rrdtool XML or JSON dumps.
 
 use std::path::PathBuf;
 
@@ -14,7 +16,7 @@ use proxmox_schema::{api, ApiStringFormat, ApiType, IntegerSchema, Schema, Strin
 
 use proxmox_sys::fs::CreateOptions;
 
-use proxmox_rrd::rrd::{AggregationFn, Archive, DataSourceType, Database};
+use proxmox_rrd::rrd::{AggregationFn, Archive, DataSourceType, Database, MultiDatabase};
 
 pub const RRA_INDEX_SCHEMA: Schema = IntegerSchema::new("Index of the RRA.").minimum(0).schema();
 
@@ -53,6 +55,87 @@ pub fn dump_rrd(path: String) -> Result<(), Error> {
     Ok(())
 }
 
+#[api()]
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+/// Export/import format
+pub enum DumpFormat {
+    /// rrdtool compatible XML (`rrdtool dump`)
+    Xml,
+    /// JSON dump format
+    Json,
+}
+
+#[api(
+   input: {
+       properties: {
+          path: {
+              description: "The filename."
+          },
+          format: {
+              type: DumpFormat,
+          },
+       },
+   },
+)]
+/// Export the RRD file to rrdtool XML or JSON (printed to stdout)
+pub fn export_rrd(path: String, format: DumpFormat) -> Result<(), Error> {
+    let rrd = MultiDatabase::load(&PathBuf::from(path), false)?;
+
+    match format {
+        DumpFormat::Xml => print!("{}", rrd.to_rrdtool_xml()?),
+        DumpFormat::Json => {
+            serde_json::to_writer_pretty(std::io::stdout(), &rrd.to_dump())?;
+            println!();
+        }
+    }
+
+    Ok(())
+}
+
+#[api(
+   input: {
+       properties: {
+          input: {
+              description: "The file to import."
+          },
+          path: {
+              description: "The RRD filename to create."
+          },
+          format: {
+              type: DumpFormat,
+          },
+       },
+   },
+)]
+/// Import an RRD file from rrdtool XML or JSON
+///
+/// Dumps with a single data source are stored as RRD v2 file,
+/// dumps with multiple data sources as RRD v3 file.
+pub fn import_rrd(input: String, path: String, format: DumpFormat) -> Result<(), Error> {
+    let data = std::fs::read_to_string(input)?;
+
+    let rrd = match format {
+        DumpFormat::Xml => MultiDatabase::from_rrdtool_xml(&data)?,
+        DumpFormat::Json => MultiDatabase::from_dump(serde_json::from_str(&data)?)?,
+    };
+
+    let path = PathBuf::from(path);
+
+    if rrd.sources.len() == 1 {
+        let named = rrd.sources.into_iter().next().unwrap();
+        let rrd = Database {
+            source: named.source,
+            rra_list: named.rra_list,
+        };
+        rrd.save(&path, CreateOptions::new(), false)?;
+    } else {
+        rrd.save(&path, CreateOptions::new(), false)?;
+    }
+
+    Ok(())
+}
+
 #[api(
    input: {
        properties: {
@@ -261,6 +344,43 @@ pub fn create_rrd(dst: DataSourceType, path: String, rra: Vec<String>) -> Result
     Ok(())
 }
 
+#[api(
+   input: {
+       properties: {
+           path: {
+               description: "The filename."
+           },
+           rra: {
+               description: "New configuration of contained RRAs.",
+               type: Array,
+               items: {
+                   schema:  RRA_CONFIG_STRING_SCHEMA,
+               }
+           },
+       },
+   },
+)]
+/// Reconfigure. Replace all RRAs, resampling the existing data.
+pub fn reconfigure_rrd(path: String, rra: Vec<String>) -> Result<(), Error> {
+    let mut rra_list = Vec::new();
+
+    for item in rra.iter() {
+        let rra: RRAConfig =
+            serde_json::from_value(RRAConfig::API_SCHEMA.parse_property_string(item)?)?;
+        rra_list.push(Archive::new(rra.cf, rra.r, rra.n as usize));
+    }
+
+    let path = PathBuf::from(path);
+
+    let mut rrd = Database::load(&path, false)?;
+
+    rrd.reconfigure(rra_list)?;
+
+    rrd.save(&path, CreateOptions::new(), false)?;
+
+    Ok(())
+}
+
 #[api(
    input: {
        properties: {
@@ -338,6 +458,12 @@ fn main() -> Result<(), Error> {
                 .arg_param(&["path"])
                 .completion_cb("path", complete_file_name),
         )
+        .insert(
+            "export",
+            CliCommand::new(&API_METHOD_EXPORT_RRD)
+                .arg_param(&["path"])
+                .completion_cb("path", complete_file_name),
+        )
         .insert(
             "fetch",
             CliCommand::new(&API_METHOD_FETCH_RRD)
@@ -350,6 +476,13 @@ fn main() -> Result<(), Error> {
                 .arg_param(&["path"])
                 .completion_cb("path", complete_file_name),
         )
+        .insert(
+            "import",
+            CliCommand::new(&API_METHOD_IMPORT_RRD)
+                .arg_param(&["input", "path"])
+                .completion_cb("input", complete_file_name)
+                .completion_cb("path", complete_file_name),
+        )
         .insert(
             "info",
             CliCommand::new(&API_METHOD_RRD_INFO)
@@ -368,6 +501,12 @@ fn main() -> Result<(), Error> {
                 .arg_param(&["path"])
                 .completion_cb("path", complete_file_name),
         )
+        .insert(
+            "reconfigure",
+            CliCommand::new(&API_METHOD_RECONFIGURE_RRD)
+                .arg_param(&["path"])
+                .completion_cb("path", complete_file_name),
+        )
         .insert(
             "resize",
             CliCommand::new(&API_METHOD_RESIZE_RRD)
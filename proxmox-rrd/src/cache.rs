@@ -5,26 +5,33 @@ use std::os::unix::io::AsRawFd;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 use std::thread::spawn;
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use anyhow::{bail, format_err, Error};
 use crossbeam_channel::{bounded, TryRecvError};
 
-use proxmox_sys::fs::{create_path, CreateOptions};
+use proxmox_sys::fs::{create_path, open_file_locked, CreateOptions};
 
+use crate::query::{combine_entries, expand_glob, glob_match, is_glob, CombineFn};
 use crate::rrd::{AggregationFn, DataSourceType, Database};
 use crate::Entry;
 
 mod journal;
 use journal::*;
+pub use journal::{JournalStats, DEFAULT_MAX_JOURNAL_SIZE};
 
 mod rrd_map;
 use rrd_map::*;
 
+const RRD_LOCK_NAME: &str = "rrd.lock";
+const RRD_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// RRD cache - keep RRD data in RAM, but write updates to disk
 ///
-/// This cache is designed to run as single instance (no concurrent
-/// access from other processes).
+/// A cache created with [Cache::new] is designed to run as single
+/// instance (no concurrent access from other processes). Use
+/// [Cache::new_shared] if several processes need to access the same
+/// base directory.
 pub struct Cache {
     config: Arc<CacheConfig>,
     state: Arc<RwLock<JournalState>>,
@@ -36,6 +43,7 @@ pub(crate) struct CacheConfig {
     basedir: PathBuf,
     file_options: CreateOptions,
     dir_options: CreateOptions,
+    shared: bool,
 }
 
 impl Cache {
@@ -60,6 +68,69 @@ impl Cache {
         apply_interval: f64,
         load_rrd_cb: fn(path: &Path, rel_path: &str) -> Option<Database>,
         create_rrd_cb: fn(dst: DataSourceType) -> Database,
+    ) -> Result<Self, Error> {
+        Self::new_impl(
+            basedir,
+            file_options,
+            dir_options,
+            apply_interval,
+            load_rrd_cb,
+            create_rrd_cb,
+            false,
+        )
+    }
+
+    /// Creates a new instance, which can be used concurrently by
+    /// several processes
+    ///
+    /// All processes accessing `basedir` need to use this mode. Updates
+    /// are not journaled, but directly written to the RRD files while
+    /// holding an exclusive lock on `basedir/rrd.lock`. Reads reload RRD
+    /// files which were changed by other processes, so they always
+    /// return the latest data.
+    ///
+    /// Existing journals (from a previous cache created with
+    /// [Cache::new]) are applied first.
+    ///
+    /// See [Cache::new] for the parameters.
+    pub fn new_shared<P: AsRef<Path>>(
+        basedir: P,
+        file_options: Option<CreateOptions>,
+        dir_options: Option<CreateOptions>,
+        load_rrd_cb: fn(path: &Path, rel_path: &str) -> Option<Database>,
+        create_rrd_cb: fn(dst: DataSourceType) -> Database,
+    ) -> Result<Self, Error> {
+        let cache = Self::new_impl(
+            basedir,
+            file_options,
+            dir_options,
+            0.0,
+            load_rrd_cb,
+            create_rrd_cb,
+            true,
+        )?;
+
+        let _lock = cache.lock()?;
+        if cache.journal_stats().pending_size > 0 {
+            apply_and_commit_journal_thread(
+                Arc::clone(&cache.config),
+                Arc::clone(&cache.state),
+                Arc::clone(&cache.rrd_map),
+                false,
+            )?;
+        }
+
+        Ok(cache)
+    }
+
+    fn new_impl<P: AsRef<Path>>(
+        basedir: P,
+        file_options: Option<CreateOptions>,
+        dir_options: Option<CreateOptions>,
+        apply_interval: f64,
+        load_rrd_cb: fn(path: &Path, rel_path: &str) -> Option<Database>,
+        create_rrd_cb: fn(dst: DataSourceType) -> Database,
+        shared: bool,
     ) -> Result<Self, Error> {
         let basedir = basedir.as_ref().to_owned();
 
@@ -74,6 +145,7 @@ impl Cache {
             file_options,
             dir_options,
             apply_interval,
+            shared,
         });
 
         let state = JournalState::new(Arc::clone(&config))?;
@@ -122,13 +194,41 @@ impl Cache {
         Database::new(dst, rra_list)
     }
 
+    /// Set the journal size limit in bytes
+    ///
+    /// The journal gets applied early once its size exceeds this limit.
+    /// If applying the journal fails, updates are no longer written to
+    /// the journal once the uncommitted journals reach twice this size.
+    /// Default is [DEFAULT_MAX_JOURNAL_SIZE].
+    pub fn set_max_journal_size(&self, max_journal_size: u64) {
+        self.state.write().unwrap().max_journal_size = max_journal_size;
+    }
+
+    /// Returns journal statistics, to be exposed as metrics
+    pub fn journal_stats(&self) -> JournalStats {
+        self.state.read().unwrap().stats()
+    }
+
+    /// Acquire the exclusive lock used in shared mode
+    fn lock(&self) -> Result<File, Error> {
+        let mut path = self.config.basedir.clone();
+        path.push(RRD_LOCK_NAME);
+        open_file_locked(&path, RRD_LOCK_TIMEOUT, true, self.config.file_options)
+    }
+
     /// Sync the journal data to disk (using `fdatasync` syscall)
     pub fn sync_journal(&self) -> Result<(), Error> {
         self.state.read().unwrap().sync_journal()
     }
 
     /// Apply and commit the journal. Should be used at server startup.
+    ///
+    /// Does nothing in shared mode, see [Cache::new_shared].
     pub fn apply_journal(&self) -> Result<bool, Error> {
+        if self.config.shared {
+            return Ok(true);
+        }
+
         let config = Arc::clone(&self.config);
         let state = Arc::clone(&self.state);
         let rrd_map = Arc::clone(&self.rrd_map);
@@ -160,7 +260,8 @@ impl Cache {
         }
 
         let now = proxmox_time::epoch_f64();
-        let wants_commit = (now - state_guard.last_journal_flush) > self.config.apply_interval;
+        let wants_commit = (now - state_guard.last_journal_flush) > self.config.apply_interval
+            || state_guard.journal_size() >= state_guard.max_journal_size;
 
         if journal_applied && !wants_commit {
             return Ok(journal_applied);
@@ -188,7 +289,7 @@ impl Cache {
         value: f64,
         dst: DataSourceType,
     ) -> Result<(), Error> {
-        self.update_value_impl(rel_path, time, value, dst, false)
+        self.update_impl(rel_path, time, UpdateValues::Single(value, dst), false)
     }
 
     /// Update data in RAM and write file back to disk (journal)
@@ -203,29 +304,64 @@ impl Cache {
         value: f64,
         dst: DataSourceType,
     ) -> Result<(), Error> {
-        self.update_value_impl(rel_path, time, value, dst, true)
+        self.update_impl(rel_path, time, UpdateValues::Single(value, dst), true)
     }
 
-    fn update_value_impl(
+    /// Update several data sources of a multi-source RRD (format version 3)
+    ///
+    /// Works like [Cache::update_value], but writes a single journal
+    /// entry for all `values`. Data sources missing in the RRD are
+    /// added. New RRD files use the RRA layout `create_rrd_cb` returns
+    /// for the type of the first data source, existing single-source
+    /// files are converted (see [MultiDatabase::load](crate::rrd::MultiDatabase::load)).
+    ///
+    /// Data source names must not contain `:`, `,`, `=` or newlines.
+    pub fn update_values(
         &self,
         rel_path: &str,
         time: f64,
-        value: f64,
-        dst: DataSourceType,
+        values: &[(&str, f64, DataSourceType)],
+    ) -> Result<(), Error> {
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        let mut list = Vec::with_capacity(values.len());
+        for (name, value, dst) in values {
+            if !is_valid_source_name(name) {
+                bail!("invalid data source name '{name}'");
+            }
+            list.push((name.to_string(), *value, *dst));
+        }
+
+        self.update_impl(rel_path, time, UpdateValues::Multi(list), false)
+    }
+
+    fn update_impl(
+        &self,
+        rel_path: &str,
+        time: f64,
+        values: UpdateValues,
         new_only: bool,
     ) -> Result<(), Error> {
+        if self.config.shared {
+            let mut rrd_map = self.rrd_map.write().unwrap();
+            let _lock = self.lock()?;
+            return rrd_map.update_and_save(rel_path, time, &values, new_only);
+        }
+
         let journal_applied = self.apply_journal()?;
 
         self.state
             .write()
             .unwrap()
-            .append_journal_entry(time, value, dst, rel_path)?;
+            .append_journal_entry(time, &values, rel_path)?;
 
         if journal_applied {
             self.rrd_map
                 .write()
                 .unwrap()
-                .update(rel_path, time, value, dst, new_only)?;
+                .update(rel_path, time, &values, new_only)?;
         }
 
         Ok(())
@@ -245,25 +381,203 @@ impl Cache {
         start: Option<u64>,
         end: Option<u64>,
     ) -> Result<Option<Entry>, Error> {
+        self.extract_cached_path(&format!("{base}/{name}"), cf, resolution, start, end)
+    }
+
+    fn extract_cached_path(
+        &self,
+        rel_path: &str,
+        cf: AggregationFn,
+        resolution: u64,
+        start: Option<u64>,
+        end: Option<u64>,
+    ) -> Result<Option<Entry>, Error> {
+        if self.config.shared {
+            let mut map = self.rrd_map.write().unwrap();
+            map.refresh(rel_path, false)?;
+            return map.extract_cached_data(rel_path, cf, resolution, start, end);
+        }
+
         let res = {
             let map = self.rrd_map.read().unwrap();
-            map.extract_cached_data(base, name, cf, resolution, start, end)?
+            map.extract_cached_data(rel_path, cf, resolution, start, end)?
         };
 
         match res {
             Some(entry) => Ok(Some(entry)),
             None => {
                 let mut map = self.rrd_map.write().unwrap();
-                let loaded = map.load(&format!("{base}/{name}"))?;
+                let loaded = map.load(rel_path, false)?;
 
                 if loaded {
-                    map.extract_cached_data(base, name, cf, resolution, start, end)
+                    map.extract_cached_data(rel_path, cf, resolution, start, end)
                 } else {
                     Ok(None)
                 }
             }
         }
     }
+
+    /// Extract data of several data sources from a cached multi-source RRD
+    ///
+    /// Returns the data of the data sources listed in `sources`, or of
+    /// all data sources if `sources` is `None` (see
+    /// [MultiDatabase::extract_data](crate::rrd::MultiDatabase::extract_data)).
+    ///
+    /// `start`: Start time. If not specified, we simply extract 10 data points.
+    ///
+    /// `end`: End time. Default is to use the current time.
+    pub fn extract_cached_sources(
+        &self,
+        rel_path: &str,
+        cf: AggregationFn,
+        resolution: u64,
+        start: Option<u64>,
+        end: Option<u64>,
+        sources: Option<&[&str]>,
+    ) -> Result<Option<Vec<(String, Entry)>>, Error> {
+        if self.config.shared {
+            let mut map = self.rrd_map.write().unwrap();
+            map.refresh(rel_path, true)?;
+            return map.extract_cached_sources(rel_path, cf, resolution, start, end, sources);
+        }
+
+        let res = {
+            let map = self.rrd_map.read().unwrap();
+            map.extract_cached_sources(rel_path, cf, resolution, start, end, sources)?
+        };
+
+        match res {
+            Some(list) => Ok(Some(list)),
+            None => {
+                let mut map = self.rrd_map.write().unwrap();
+                let loaded = map.load(rel_path, true)?;
+
+                if loaded {
+                    map.extract_cached_sources(rel_path, cf, resolution, start, end, sources)
+                } else {
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    /// Extract data from several cached RRDs and combine it into a single series
+    ///
+    /// `paths`: Relative paths of the RRDs. Paths may contain `*` and
+    /// `?` wildcards, which never match a `/`.
+    ///
+    /// `combine`: Function used to combine the values of all RRDs, see
+    /// [combine_entries] for how the series are aligned.
+    ///
+    /// `start`: Start time. If not specified, we simply extract 10 data points.
+    ///
+    /// `end`: End time. Default is to use the current time.
+    ///
+    /// Returns `None` if none of the RRDs exists.
+    pub fn extract_combined_data(
+        &self,
+        paths: &[&str],
+        cf: AggregationFn,
+        resolution: u64,
+        start: Option<u64>,
+        end: Option<u64>,
+        combine: CombineFn,
+    ) -> Result<Option<Entry>, Error> {
+        let mut rel_paths = BTreeSet::new();
+
+        for path in paths {
+            if is_glob(path) {
+                rel_paths.extend(expand_glob(&self.config.basedir, path)?);
+                // RRDs created since the last commit only exist in memory
+                let cached = self.rrd_map.read().unwrap().file_list();
+                rel_paths.extend(cached.into_iter().filter(|rel| glob_match(path, rel)));
+            } else {
+                rel_paths.insert(path.to_string());
+            }
+        }
+
+        let mut entries = Vec::with_capacity(rel_paths.len());
+        for rel_path in rel_paths {
+            if let Some(entry) = self.extract_cached_path(&rel_path, cf, resolution, start, end)? {
+                entries.push(entry);
+            }
+        }
+
+        if entries.is_empty() {
+            return Ok(None);
+        }
+
+        combine_entries(&entries, combine).map(Some)
+    }
+
+    /// Change the RRA layout of all RRD files to the one produced by
+    /// `create_rrd_cb`
+    ///
+    /// Files with a different layout are resampled (see
+    /// [Database::reconfigure]) and written back to disk. Files which
+    /// are not in the RRD v2 format (including multi-source RRDs) are
+    /// skipped.
+    ///
+    /// Returns the number of changed files.
+    pub fn reconfigure_rrd_files(&self) -> Result<usize, Error> {
+        let mut files = BTreeSet::new();
+        list_rrd_files(&self.config.basedir, "", &mut files)?;
+
+        // block updates, so that no old layout gets written back
+        let mut rrd_map = self.rrd_map.write().unwrap();
+        let _lock = if self.config.shared {
+            Some(self.lock()?)
+        } else {
+            None
+        };
+        files.extend(rrd_map.file_list());
+
+        let mut changed = 0;
+        for rel_path in files {
+            match rrd_map.reconfigure(&rel_path) {
+                Ok(true) => {
+                    log::info!("changed RRA layout of rrd {rel_path}");
+                    changed += 1;
+                }
+                Ok(false) => (),
+                Err(err) => log::warn!("unable to reconfigure rrd {rel_path} (skip) - {err}"),
+            }
+        }
+
+        Ok(changed)
+    }
+}
+
+/// Recursively list all files below `dir`, except the journal and lock files.
+fn list_rrd_files(dir: &Path, rel_dir: &str, list: &mut BTreeSet<String>) -> Result<(), Error> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|err| format_err!("unable to read directory {dir:?} - {err}"))?;
+
+    for entry in entries {
+        let entry = entry?;
+        let Some(name) = entry.file_name().to_str().map(String::from) else {
+            continue;
+        };
+
+        let rel_path = if rel_dir.is_empty() {
+            if name.starts_with(RRD_JOURNAL_NAME) || name == RRD_LOCK_NAME {
+                continue;
+            }
+            name
+        } else {
+            format!("{rel_dir}/{name}")
+        };
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            list_rrd_files(&entry.path(), &rel_path, list)?;
+        } else if file_type.is_file() {
+            list.insert(rel_path);
+        }
+    }
+
+    Ok(())
 }
 
 fn apply_and_commit_journal_thread(
@@ -290,8 +604,9 @@ fn apply_and_commit_journal_thread(
     let start_time = SystemTime::now();
     log::debug!("commit rrd journal");
 
-    match commit_journal_impl(config, state, rrd_map) {
+    match commit_journal_impl(config, Arc::clone(&state), rrd_map) {
         Ok(rrd_file_count) => {
+            state.write().unwrap().last_commit = Some(proxmox_time::epoch_f64());
             let elapsed = start_time.elapsed().unwrap().as_secs_f64();
             log::info!(
                 "rrd journal successfully committed ({rrd_file_count} files in {elapsed:.3} seconds)"
@@ -308,8 +623,10 @@ fn apply_journal_lines(
     journal_name: &str, // used for logging
     reader: &mut BufReader<File>,
     lock_read_line: bool,
+    replay_errors: &mut u64,
 ) -> Result<usize, Error> {
     let mut linenr = 0;
+    let mut entries = 0;
 
     loop {
         linenr += 1;
@@ -334,19 +651,18 @@ fn apply_journal_lines(
                     linenr,
                     err,
                 );
-                continue; // skip unparsable lines
+                *replay_errors += 1;
+                continue; // skip corrupt or unparsable lines
             }
         };
+        entries += 1;
 
-        rrd_map.write().unwrap().update(
-            &entry.rel_path,
-            entry.time,
-            entry.value,
-            entry.dst,
-            true,
-        )?;
+        rrd_map
+            .write()
+            .unwrap()
+            .update(&entry.rel_path, entry.time, &entry.values, true)?;
     }
-    Ok(linenr)
+    Ok(entries)
 }
 
 fn apply_journal_impl(
@@ -354,6 +670,7 @@ fn apply_journal_impl(
     rrd_map: Arc<RwLock<RRDMap>>,
 ) -> Result<usize, Error> {
     let mut lines = 0;
+    let mut replay_errors = 0;
 
     // Apply old journals first
     let journal_list = state.read().unwrap().list_old_journals()?;
@@ -368,6 +685,7 @@ fn apply_journal_impl(
             &entry.name,
             &mut reader,
             false,
+            &mut replay_errors,
         )?;
     }
 
@@ -379,6 +697,7 @@ fn apply_journal_impl(
         "rrd.journal",
         &mut journal,
         true,
+        &mut replay_errors,
     )?;
 
     {
@@ -390,9 +709,12 @@ fn apply_journal_impl(
             "rrd.journal",
             &mut journal,
             false,
+            &mut replay_errors,
         )?;
 
         state_guard.rotate_journal()?; // start new journal, keep old one
+        state_guard.set_replayed_entries(lines as u64);
+        state_guard.replay_errors += replay_errors;
 
         // We need to apply the journal only once, because further updates
         // are always directly applied.
@@ -1,6 +1,7 @@
 use std::ffi::OsStr;
 use std::fs::File;
 use std::io::{BufReader, Write};
+use std::os::unix::fs::FileExt;
 use std::os::unix::io::AsRawFd;
 use std::path::PathBuf;
 use std::str::FromStr;
@@ -12,7 +13,13 @@ use nix::fcntl::OFlag;
 
 use proxmox_sys::fs::atomic_open_or_create_file;
 
-const RRD_JOURNAL_NAME: &str = "rrd.journal";
+pub(crate) const RRD_JOURNAL_NAME: &str = "rrd.journal";
+
+// used instead of the data source type in records of multi-source RRDs
+const MULTI_VALUES_MARKER: &str = "m";
+
+/// Default journal size limit (see [Cache::set_max_journal_size](crate::Cache::set_max_journal_size))
+pub const DEFAULT_MAX_JOURNAL_SIZE: u64 = 64 * 1024 * 1024;
 
 use crate::cache::CacheConfig;
 use crate::rrd::DataSourceType;
@@ -24,20 +31,116 @@ pub struct JournalState {
     pub last_journal_flush: f64,
     pub journal_applied: bool,
     pub apply_thread_result: Option<Receiver<Result<(), String>>>,
+    pub max_journal_size: u64,
+    // size and number of entries of the current journal
+    journal_size: u64,
+    journal_entries: u64,
+    // size and number of entries of rotated, not yet committed journals
+    old_journal_size: u64,
+    old_journal_entries: u64,
+    pub replay_errors: u64,
+    dropped_entries: u64,
+    journal_full: bool,
+    pub last_commit: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// Journal statistics, see [Cache::journal_stats](crate::Cache::journal_stats)
+pub struct JournalStats {
+    /// Number of journal entries not yet committed to the RRD files.
+    ///
+    /// Entries of journals written before the cache was started are
+    /// only counted once they got replayed.
+    pub pending_entries: u64,
+    /// Size of all journal files not yet committed, in bytes.
+    pub pending_size: u64,
+    /// Time of the last successful journal commit.
+    pub last_commit: Option<f64>,
+    /// Number of corrupt or unparsable records skipped on replay.
+    pub replay_errors: u64,
+    /// Number of updates not written to the journal, because the journal
+    /// size limit was exceeded.
+    pub dropped_entries: u64,
+}
+
+/// Values of a single RRD update
+#[derive(Debug, Clone, PartialEq)]
+pub enum UpdateValues {
+    /// Value of a single-source RRD
+    Single(f64, DataSourceType),
+    /// Named values of a multi-source RRD (format version 3)
+    Multi(Vec<(String, f64, DataSourceType)>),
 }
 
 pub struct JournalEntry {
     pub time: f64,
-    pub value: f64,
-    pub dst: DataSourceType,
+    pub values: UpdateValues,
     pub rel_path: String,
 }
 
+fn parse_dst(dst: &str) -> Result<DataSourceType, Error> {
+    let dst: u8 = dst
+        .parse()
+        .map_err(|_| format_err!("unable to parse data source type"))?;
+
+    match dst {
+        0 => Ok(DataSourceType::Gauge),
+        1 => Ok(DataSourceType::Derive),
+        _ => bail!("got strange value for data source type '{}'", dst),
+    }
+}
+
+fn parse_value(value: &str) -> Result<f64, Error> {
+    value
+        .parse()
+        .map_err(|_| format_err!("unable to parse value"))
+}
+
+// multi-source values are written as 'name=value=dst' list, separated by ','
+fn parse_multi_values(values: &str) -> Result<Vec<(String, f64, DataSourceType)>, Error> {
+    values
+        .split(',')
+        .map(|item| {
+            let parts: Vec<&str> = item.split('=').collect();
+            if parts.len() != 3 || parts[0].is_empty() {
+                bail!("unable to parse data source value '{item}'");
+            }
+            Ok((
+                parts[0].to_string(),
+                parse_value(parts[1])?,
+                parse_dst(parts[2])?,
+            ))
+        })
+        .collect()
+}
+
+/// Returns `true` if `name` can be stored in a journal record.
+pub(crate) fn is_valid_source_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains([':', ',', '=', '\n'])
+}
+
 impl FromStr for JournalEntry {
     type Err = Error;
 
     fn from_str(line: &str) -> Result<Self, Self::Err> {
-        let line = line.trim();
+        let line = match line.strip_suffix('\n') {
+            Some(line) => line,
+            None => bail!("incomplete record"),
+        };
+
+        // records are prefixed by a CRC32 checksum, but accept lines
+        // without one as written by older versions
+        let line = match line.split_once('|') {
+            Some((checksum, record)) if checksum.len() == 8 => {
+                let checksum = u32::from_str_radix(checksum, 16)
+                    .map_err(|_| format_err!("unable to parse checksum"))?;
+                if checksum != crc32fast::hash(record.as_bytes()) {
+                    bail!("wrong checksum");
+                }
+                record
+            }
+            _ => line.trim(),
+        };
 
         let parts: Vec<&str> = line.splitn(4, ':').collect();
         if parts.len() != 4 {
@@ -47,25 +150,18 @@ impl FromStr for JournalEntry {
         let time: f64 = parts[0]
             .parse()
             .map_err(|_| format_err!("unable to parse time"))?;
-        let value: f64 = parts[1]
-            .parse()
-            .map_err(|_| format_err!("unable to parse value"))?;
-        let dst: u8 = parts[2]
-            .parse()
-            .map_err(|_| format_err!("unable to parse data source type"))?;
 
-        let dst = match dst {
-            0 => DataSourceType::Gauge,
-            1 => DataSourceType::Derive,
-            _ => bail!("got strange value for data source type '{}'", dst),
+        let values = if parts[2] == MULTI_VALUES_MARKER {
+            UpdateValues::Multi(parse_multi_values(parts[1])?)
+        } else {
+            UpdateValues::Single(parse_value(parts[1])?, parse_dst(parts[2])?)
         };
 
         let rel_path = parts[3].to_string();
 
         Ok(JournalEntry {
             time,
-            value,
-            dst,
+            values,
             rel_path,
         })
     }
@@ -77,16 +173,91 @@ pub struct JournalFileInfo {
     pub path: PathBuf,
 }
 
+impl JournalEntry {
+    /// Format the entry as journal record, including the trailing newline.
+    pub fn to_record(&self) -> String {
+        let record = match &self.values {
+            UpdateValues::Single(value, dst) => {
+                format!("{}:{}:{}:{}", self.time, value, *dst as u8, self.rel_path)
+            }
+            UpdateValues::Multi(values) => {
+                let values: Vec<String> = values
+                    .iter()
+                    .map(|(name, value, dst)| format!("{name}={value}={}", *dst as u8))
+                    .collect();
+                format!(
+                    "{}:{}:{MULTI_VALUES_MARKER}:{}",
+                    self.time,
+                    values.join(","),
+                    self.rel_path
+                )
+            }
+        };
+        format!("{:08x}|{record}\n", crc32fast::hash(record.as_bytes()))
+    }
+}
+
 impl JournalState {
     pub(crate) fn new(config: Arc<CacheConfig>) -> Result<Self, Error> {
-        let journal = JournalState::open_journal_writer(&config)?;
-        Ok(Self {
+        let mut journal = JournalState::open_journal_writer(&config)?;
+        let mut journal_size = journal.metadata()?.len();
+
+        // terminate a torn record (e.g. after a crash), so that it does
+        // not get merged with the next one
+        if journal_size > 0 {
+            let mut last = [0u8];
+            let reader = File::open(config.basedir.join(RRD_JOURNAL_NAME))?;
+            reader.read_exact_at(&mut last, journal_size - 1)?;
+            if last[0] != b'\n' {
+                journal.write_all(b"\n")?;
+                journal_size += 1;
+            }
+        }
+
+        let mut this = Self {
             config,
             journal,
             last_journal_flush: 0.0,
             journal_applied: false,
             apply_thread_result: None,
-        })
+            max_journal_size: DEFAULT_MAX_JOURNAL_SIZE,
+            journal_size,
+            journal_entries: 0,
+            old_journal_size: 0,
+            old_journal_entries: 0,
+            replay_errors: 0,
+            dropped_entries: 0,
+            journal_full: false,
+            last_commit: None,
+        };
+
+        for entry in this.list_old_journals()? {
+            this.old_journal_size += entry.path.metadata()?.len();
+        }
+
+        Ok(this)
+    }
+
+    /// Size of the current journal in bytes
+    pub fn journal_size(&self) -> u64 {
+        self.journal_size
+    }
+
+    pub fn stats(&self) -> JournalStats {
+        JournalStats {
+            pending_entries: self.journal_entries + self.old_journal_entries,
+            pending_size: self.journal_size + self.old_journal_size,
+            last_commit: self.last_commit,
+            replay_errors: self.replay_errors,
+            dropped_entries: self.dropped_entries,
+        }
+    }
+
+    /// Set the number of entries after replaying all journals.
+    ///
+    /// Must be called directly after [Self::rotate_journal].
+    pub fn set_replayed_entries(&mut self, entries: u64) {
+        self.old_journal_entries = entries;
     }
 
     pub fn sync_journal(&self) -> Result<(), Error> {
@@ -97,12 +268,35 @@ impl JournalState {
     pub fn append_journal_entry(
         &mut self,
         time: f64,
-        value: f64,
-        dst: DataSourceType,
+        values: &UpdateValues,
         rel_path: &str,
     ) -> Result<(), Error> {
-        let journal_entry = format!("{}:{}:{}:{}\n", time, value, dst as u8, rel_path);
-        self.journal.write_all(journal_entry.as_bytes())?;
+        // applying the journal failed for some time - do not fill up the disk
+        if self.journal_size + self.old_journal_size >= self.max_journal_size.saturating_mul(2) {
+            if !self.journal_full {
+                log::warn!("rrd journal size limit exceeded - skip journal entries");
+                self.journal_full = true;
+            }
+            self.dropped_entries += 1;
+            return Ok(());
+        }
+
+        let journal_entry = JournalEntry {
+            time,
+            values: values.clone(),
+            rel_path: rel_path.to_string(),
+        };
+        let record = journal_entry.to_record();
+
+        if let Err(err) = self.journal.write_all(record.as_bytes()) {
+            // do not leave a partial record behind (e.g. disk full)
+            let _ = self.journal.set_len(self.journal_size);
+            return Err(err.into());
+        }
+
+        self.journal_size += record.len() as u64;
+        self.journal_entries += 1;
+
         Ok(())
     }
 
@@ -138,19 +332,28 @@ impl JournalState {
 
         self.journal = Self::open_journal_writer(&self.config)?;
 
+        self.old_journal_size += self.journal_size;
+        self.old_journal_entries += self.journal_entries;
+        self.journal_size = 0;
+        self.journal_entries = 0;
+
         // make sure the old journal data landed on the disk
         super::fsync_file_and_parent(&new_name)?;
 
         Ok(())
     }
 
-    pub fn remove_old_journals(&self) -> Result<(), Error> {
+    pub fn remove_old_journals(&mut self) -> Result<(), Error> {
         let journal_list = self.list_old_journals()?;
 
         for entry in journal_list {
             std::fs::remove_file(entry.path)?;
         }
 
+        self.old_journal_size = 0;
+        self.old_journal_entries = 0;
+        self.journal_full = false;
+
         Ok(())
     }
 
@@ -188,3 +391,70 @@ impl JournalState {
         Ok(list)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn journal_record() -> Result<(), Error> {
+        let entry = JournalEntry {
+            time: 1700000000.5,
+            values: UpdateValues::Single(0.25, DataSourceType::Derive),
+            rel_path: "host/node1:cpu".to_string(),
+        };
+
+        let record = entry.to_record();
+        let parsed: JournalEntry = record.parse()?;
+        assert_eq!(parsed.time, entry.time);
+        assert_eq!(parsed.values, entry.values);
+        assert_eq!(parsed.rel_path, entry.rel_path);
+
+        // torn write
+        assert!(record[..record.len() - 3].parse::<JournalEntry>().is_err());
+        // corrupt data
+        let corrupt = record.replace("0.25", "0.35");
+        assert!(corrupt.parse::<JournalEntry>().is_err());
+
+        // record without checksum, as written by older versions
+        let legacy: JournalEntry = "1700000000:1.5:0:host/node1\n".parse()?;
+        assert_eq!(
+            legacy.values,
+            UpdateValues::Single(1.5, DataSourceType::Gauge)
+        );
+        assert_eq!(legacy.rel_path, "host/node1");
+
+        Ok(())
+    }
+
+    #[test]
+    fn journal_record_multi() -> Result<(), Error> {
+        let entry = JournalEntry {
+            time: 1700000000.0,
+            values: UpdateValues::Multi(vec![
+                ("cpu".to_string(), 0.5, DataSourceType::Gauge),
+                ("netin".to_string(), 1024.0, DataSourceType::Derive),
+            ]),
+            rel_path: "guest/100".to_string(),
+        };
+
+        let record = entry.to_record();
+        let parsed: JournalEntry = record.parse()?;
+        assert_eq!(parsed.time, entry.time);
+        assert_eq!(parsed.values, entry.values);
+        assert_eq!(parsed.rel_path, entry.rel_path);
+
+        assert!("1700000000:cpu=0.5:m:guest/100\n"
+            .parse::<JournalEntry>()
+            .is_err());
+        assert!("1700000000:=0.5=0:m:guest/100\n"
+            .parse::<JournalEntry>()
+            .is_err());
+
+        assert!(is_valid_source_name("netin"));
+        assert!(!is_valid_source_name("net:in"));
+        assert!(!is_valid_source_name(""));
+
+        Ok(())
+    }
+}
@@ -1,19 +1,67 @@
 use std::collections::HashMap;
+use std::os::unix::fs::MetadataExt;
 use std::path::Path;
 use std::sync::Arc;
 
-use anyhow::{bail, Error};
+use anyhow::{bail, format_err, Error};
 
-use proxmox_sys::fs::create_path;
+use proxmox_sys::fs::{create_path, CreateOptions};
 
-use crate::rrd::{AggregationFn, DataSourceType, Database};
+use crate::rrd::{AggregationFn, ArchiveDefinition, DataSourceType, Database, MultiDatabase};
 
+use super::journal::UpdateValues;
 use super::CacheConfig;
 use crate::Entry;
 
+/// Identifies the content of an RRD file, which gets replaced on each save
+/// (device, inode, mtime seconds and nanoseconds).
+type FileId = (u64, u64, i64, i64);
+
+fn file_id(path: &Path) -> Result<Option<FileId>, Error> {
+    match std::fs::metadata(path) {
+        Ok(stat) => Ok(Some((
+            stat.dev(),
+            stat.ino(),
+            stat.mtime(),
+            stat.mtime_nsec(),
+        ))),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(format_err!("unable to stat rrd file {path:?} - {err}")),
+    }
+}
+
+/// A loaded RRD file
+pub enum CachedRrd {
+    Single(Database),
+    Multi(MultiDatabase),
+}
+
+impl CachedRrd {
+    fn save(&self, path: &Path, options: CreateOptions) -> Result<(), Error> {
+        match self {
+            CachedRrd::Single(rrd) => rrd.save(path, options, true),
+            CachedRrd::Multi(rrd) => rrd.save(path, options, true),
+        }
+    }
+}
+
+fn load_multi_rrd(path: &Path) -> Option<MultiDatabase> {
+    match MultiDatabase::load(path, true) {
+        Ok(rrd) => Some(rrd),
+        Err(err) => {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("overwriting rrd file {path:?}, because of load error: {err}");
+            }
+            None
+        }
+    }
+}
+
 pub struct RRDMap {
     config: Arc<CacheConfig>,
-    map: HashMap<String, Database>,
+    map: HashMap<String, CachedRrd>,
+    // file versions of the loaded RRDs, only used in shared mode
+    file_ids: HashMap<String, FileId>,
     load_rrd_cb: fn(path: &Path, rel_path: &str) -> Option<Database>,
     create_rrd_cb: fn(dst: DataSourceType) -> Database,
 }
@@ -27,12 +75,28 @@ impl RRDMap {
         Self {
             config,
             map: HashMap::new(),
+            file_ids: HashMap::new(),
             load_rrd_cb,
             create_rrd_cb,
         }
     }
 
     pub fn update(
+        &mut self,
+        rel_path: &str,
+        time: f64,
+        values: &UpdateValues,
+        new_only: bool,
+    ) -> Result<(), Error> {
+        match values {
+            UpdateValues::Single(value, dst) => {
+                self.update_single(rel_path, time, *value, *dst, new_only)
+            }
+            UpdateValues::Multi(values) => self.update_multi(rel_path, time, values, new_only),
+        }
+    }
+
+    fn update_single(
         &mut self,
         rel_path: &str,
         time: f64,
@@ -40,34 +104,155 @@ impl RRDMap {
         dst: DataSourceType,
         new_only: bool,
     ) -> Result<(), Error> {
-        if let Some(rrd) = self.map.get_mut(rel_path) {
-            if !new_only || time > rrd.last_update() {
-                rrd.update(time, value);
-            }
-        } else {
-            let mut path = self.config.basedir.clone();
-            path.push(rel_path);
-            let mut rrd = match (self.load_rrd_cb)(&path, rel_path) {
+        if !self.map.contains_key(rel_path) {
+            let path = self.config.basedir.join(rel_path);
+            let rrd = match (self.load_rrd_cb)(&path, rel_path) {
                 None => {
-                    create_path(
-                        path.parent().unwrap(),
-                        Some(self.config.dir_options),
-                        Some(self.config.dir_options),
-                    )?;
-
+                    self.create_parent_dir(&path)?;
                     (self.create_rrd_cb)(dst)
                 }
                 Some(rrd) => rrd,
             };
+            self.map
+                .insert(rel_path.to_string(), CachedRrd::Single(rrd));
+        }
+
+        let Some(CachedRrd::Single(rrd)) = self.map.get_mut(rel_path) else {
+            bail!("rrd {rel_path} is a multi-source rrd");
+        };
+
+        if !new_only || time > rrd.last_update() {
+            rrd.update(time, value);
+        }
+        Ok(())
+    }
+
+    /// Update a multi-source RRD, missing data sources are added
+    ///
+    /// New files use the RRA layout `create_rrd_cb` returns for the
+    /// type of the first data source.
+    fn update_multi(
+        &mut self,
+        rel_path: &str,
+        time: f64,
+        values: &[(String, f64, DataSourceType)],
+        new_only: bool,
+    ) -> Result<(), Error> {
+        let Some((_, _, first_dst)) = values.first() else {
+            return Ok(());
+        };
+
+        if !self.map.contains_key(rel_path) {
+            let path = self.config.basedir.join(rel_path);
+            let rrd = match load_multi_rrd(&path) {
+                None => {
+                    self.create_parent_dir(&path)?;
+                    let template = (self.create_rrd_cb)(*first_dst);
+                    MultiDatabase::new(
+                        template
+                            .rra_list
+                            .iter()
+                            .map(ArchiveDefinition::from)
+                            .collect(),
+                    )
+                }
+                Some(rrd) => rrd,
+            };
+            self.map.insert(rel_path.to_string(), CachedRrd::Multi(rrd));
+        }
 
-            if !new_only || time > rrd.last_update() {
-                rrd.update(time, value);
+        let Some(CachedRrd::Multi(rrd)) = self.map.get_mut(rel_path) else {
+            bail!("rrd {rel_path} is not a multi-source rrd");
+        };
+
+        let mut update = Vec::with_capacity(values.len());
+        for (name, value, dst) in values {
+            match rrd.source(name) {
+                Some(named) if new_only && time <= named.source.last_update => continue,
+                Some(_) => (),
+                None => rrd.add_source(name, *dst)?,
+            }
+            update.push((name.as_str(), *value));
+        }
+
+        rrd.update(time, &update)
+    }
+
+    fn create_parent_dir(&self, path: &Path) -> Result<(), Error> {
+        create_path(
+            path.parent().unwrap(),
+            Some(self.config.dir_options),
+            Some(self.config.dir_options),
+        )?;
+        Ok(())
+    }
+
+    fn load_file(&self, path: &Path, rel_path: &str, multi: bool) -> Option<CachedRrd> {
+        if multi {
+            load_multi_rrd(path).map(CachedRrd::Multi)
+        } else {
+            (self.load_rrd_cb)(path, rel_path).map(CachedRrd::Single)
+        }
+    }
+
+    /// Reload the RRD if the file was changed by another process (shared mode)
+    ///
+    /// `multi` selects the format used to load files which are not
+    /// loaded yet.
+    pub fn refresh(&mut self, rel_path: &str, multi: bool) -> Result<(), Error> {
+        let mut path = self.config.basedir.clone();
+        path.push(rel_path);
+
+        let id = file_id(&path)?;
+        if id.is_some() && id.as_ref() == self.file_ids.get(rel_path) {
+            return Ok(());
+        }
+
+        let multi = match self.map.remove(rel_path) {
+            Some(CachedRrd::Multi(_)) => true,
+            Some(CachedRrd::Single(_)) => false,
+            None => multi,
+        };
+        self.file_ids.remove(rel_path);
+
+        if let Some(id) = id {
+            if let Some(rrd) = self.load_file(&path, rel_path, multi) {
+                self.map.insert(rel_path.to_string(), rrd);
+                self.file_ids.insert(rel_path.to_string(), id);
             }
-            self.map.insert(rel_path.to_string(), rrd);
         }
+
         Ok(())
     }
 
+    fn update_file_id(&mut self, rel_path: &str) -> Result<(), Error> {
+        let mut path = self.config.basedir.clone();
+        path.push(rel_path);
+
+        match file_id(&path)? {
+            Some(id) => self.file_ids.insert(rel_path.to_string(), id),
+            None => self.file_ids.remove(rel_path),
+        };
+
+        Ok(())
+    }
+
+    /// Update the RRD and write it back to disk (shared mode)
+    ///
+    /// The caller needs to hold the exclusive cache lock.
+    pub fn update_and_save(
+        &mut self,
+        rel_path: &str,
+        time: f64,
+        values: &UpdateValues,
+        new_only: bool,
+    ) -> Result<(), Error> {
+        self.refresh(rel_path, matches!(values, UpdateValues::Multi(_)))?;
+        self.update(rel_path, time, values, new_only)?;
+        self.flush_rrd_file(rel_path)?;
+        self.update_file_id(rel_path)
+    }
+
     pub fn file_list(&self) -> Vec<String> {
         let mut list = Vec::new();
 
@@ -82,7 +267,7 @@ impl RRDMap {
         if let Some(rrd) = self.map.get(rel_path) {
             let mut path = self.config.basedir.clone();
             path.push(rel_path);
-            rrd.save(&path, self.config.file_options, true)
+            rrd.save(&path, self.config.file_options)
         } else {
             bail!("rrd file {} not loaded", rel_path);
         }
@@ -90,20 +275,72 @@ impl RRDMap {
 
     pub fn extract_cached_data(
         &self,
-        base: &str,
-        name: &str,
+        rel_path: &str,
         cf: AggregationFn,
         resolution: u64,
         start: Option<u64>,
         end: Option<u64>,
     ) -> Result<Option<Entry>, Error> {
-        match self.map.get(&format!("{}/{}", base, name)) {
-            Some(rrd) => Ok(Some(rrd.extract_data(cf, resolution, start, end)?)),
+        match self.map.get(rel_path) {
+            Some(CachedRrd::Single(rrd)) => Ok(Some(rrd.extract_data(cf, resolution, start, end)?)),
+            Some(CachedRrd::Multi(_)) | None => Ok(None),
+        }
+    }
+
+    pub fn extract_cached_sources(
+        &self,
+        rel_path: &str,
+        cf: AggregationFn,
+        resolution: u64,
+        start: Option<u64>,
+        end: Option<u64>,
+        sources: Option<&[&str]>,
+    ) -> Result<Option<Vec<(String, Entry)>>, Error> {
+        match self.map.get(rel_path) {
+            Some(CachedRrd::Multi(rrd)) => {
+                Ok(Some(rrd.extract_data(cf, resolution, start, end, sources)?))
+            }
+            Some(CachedRrd::Single(_)) => bail!("rrd {rel_path} is not a multi-source rrd"),
             None => Ok(None),
         }
     }
 
-    pub fn load(&mut self, rel_path: &str) -> Result<bool, Error> {
+    /// Resample the RRD file to the layout produced by `create_rrd_cb`
+    ///
+    /// Returns `false` if the file already uses that layout. Changed
+    /// files are written back to disk immediately.
+    pub fn reconfigure(&mut self, rel_path: &str) -> Result<bool, Error> {
+        let mut path = self.config.basedir.clone();
+        path.push(rel_path);
+
+        if self.config.shared {
+            self.refresh(rel_path, false)?;
+        }
+
+        let mut loaded = None;
+        let rrd = match self.map.get_mut(rel_path) {
+            Some(CachedRrd::Single(rrd)) => rrd,
+            Some(CachedRrd::Multi(_)) => bail!("unable to reconfigure multi-source rrd"),
+            None => loaded.insert(Database::load(&path, true)?),
+        };
+
+        let template = (self.create_rrd_cb)(rrd.source.dst);
+        if rrd.has_layout(&template.rra_list) {
+            return Ok(false);
+        }
+
+        rrd.reconfigure(template.rra_list)?;
+        rrd.save(&path, self.config.file_options, true)?;
+
+        if self.config.shared && loaded.is_none() {
+            self.update_file_id(rel_path)?;
+        }
+
+        Ok(true)
+    }
+
+    /// Load the RRD, `multi` selects the format (see [MultiDatabase])
+    pub fn load(&mut self, rel_path: &str, multi: bool) -> Result<bool, Error> {
         if self.map.contains_key(rel_path) {
             // Already loaded, do nothing
             return Ok(true);
@@ -112,7 +349,7 @@ impl RRDMap {
         let mut path = self.config.basedir.clone();
         path.push(rel_path);
 
-        if let Some(rrd) = (self.load_rrd_cb)(&path, rel_path) {
+        if let Some(rrd) = self.load_file(&path, rel_path, multi) {
             self.map.insert(rel_path.to_string(), rrd);
             Ok(true)
         } else {
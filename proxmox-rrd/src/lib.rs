@@ -2,9 +2,12 @@
 //!
 //! ## Features
 //!
-//! * One file stores a single data source
+//! * One file stores a single data source, or several named data sources
+//!   with shared RRAs (format version 3)
 //! * Stores data for different time resolution
-//! * Simple cache implementation with journal support
+//! * Simple cache implementation with journal support, optionally shared
+//!   by several processes
+//! * Consolidated queries across several RRDs
 
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
@@ -15,5 +18,7 @@ pub mod rrd;
 #[doc(inline)]
 pub use rrd::Entry;
 
+pub mod query;
+
 mod cache;
 pub use cache::*;
@@ -0,0 +1,271 @@
+//! # Consolidated queries across several RRDs
+//!
+//! Combines the [Entry] series extracted from several RRD files into a
+//! single series, for example to compute the total CPU usage of all
+//! guests. Series with different resolutions or time ranges are aligned
+//! to a common grid first, using the coarsest resolution.
+
+use std::path::Path;
+
+use anyhow::{bail, Error};
+
+use crate::Entry;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+/// Function used to combine the values of several series
+pub enum CombineFn {
+    /// Sum of all values
+    Sum,
+    /// Average of all values
+    Average,
+    /// Minimum of all values
+    Minimum,
+    /// Maximum of all values
+    Maximum,
+    /// Percentile (0 to 100) of all values, linearly interpolated
+    Percentile(f64),
+}
+
+impl CombineFn {
+    fn combine(&self, values: &mut [f64]) -> Option<f64> {
+        if values.is_empty() {
+            return None;
+        }
+
+        let value = match self {
+            CombineFn::Sum => values.iter().sum(),
+            CombineFn::Average => values.iter().sum::<f64>() / values.len() as f64,
+            CombineFn::Minimum => values.iter().copied().fold(f64::INFINITY, f64::min),
+            CombineFn::Maximum => values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            CombineFn::Percentile(percentile) => {
+                values.sort_unstable_by(f64::total_cmp);
+                let rank = (percentile / 100.0) * (values.len() - 1) as f64;
+                let lower = rank.floor() as usize;
+                let upper = rank.ceil() as usize;
+                values[lower] + (values[upper] - values[lower]) * (rank - lower as f64)
+            }
+        };
+
+        Some(value)
+    }
+}
+
+/// Combine several series into one
+///
+/// The result uses the coarsest resolution of all `entries` and spans
+/// the time range covered by any of them. Values of finer series are
+/// averaged into the slots of the coarser resolution first. Missing
+/// values (`None`) are ignored; a slot without any value stays `None`.
+pub fn combine_entries(entries: &[Entry], combine: CombineFn) -> Result<Entry, Error> {
+    if let CombineFn::Percentile(percentile) = combine {
+        if !(0.0..=100.0).contains(&percentile) {
+            bail!("percentile {percentile} out of range");
+        }
+    }
+
+    let Some(resolution) = entries.iter().map(|entry| entry.resolution).max() else {
+        bail!("no data to combine");
+    };
+    if resolution == 0 {
+        bail!("got zero resolution");
+    }
+
+    let start = entries
+        .iter()
+        .map(|entry| entry.start - entry.start % resolution)
+        .min()
+        .unwrap_or(0);
+    let end = entries
+        .iter()
+        .map(|entry| entry.start + entry.resolution * entry.data.len() as u64)
+        .max()
+        .unwrap_or(start);
+    let slots = end.saturating_sub(start).div_ceil(resolution) as usize;
+
+    let aligned: Vec<Vec<Option<f64>>> = entries
+        .iter()
+        .map(|entry| align_entry(entry, start, resolution, slots))
+        .collect();
+
+    let mut data = Vec::with_capacity(slots);
+    let mut values = Vec::with_capacity(aligned.len());
+
+    for slot in 0..slots {
+        values.clear();
+        values.extend(aligned.iter().filter_map(|entry| entry[slot]));
+        data.push(combine.combine(&mut values));
+    }
+
+    Ok(Entry::new(start, resolution, data))
+}
+
+/// Resample `entry` into `slots` slots of `resolution` starting at `start`.
+fn align_entry(entry: &Entry, start: u64, resolution: u64, slots: usize) -> Vec<Option<f64>> {
+    let mut sums = vec![(0.0, 0usize); slots];
+
+    for (i, value) in entry.data.iter().enumerate() {
+        let Some(value) = value else {
+            continue;
+        };
+        let time = entry.start + entry.resolution * i as u64;
+        let slot = ((time - start) / resolution) as usize;
+        if let Some((sum, count)) = sums.get_mut(slot) {
+            *sum += value;
+            *count += 1;
+        }
+    }
+
+    sums.into_iter()
+        .map(|(sum, count)| (count > 0).then(|| sum / count as f64))
+        .collect()
+}
+
+/// Returns `true` if `path` matches the glob `pattern`
+///
+/// `*` matches any sequence of characters except `/`, `?` matches any
+/// single character except `/`.
+pub(crate) fn glob_match(pattern: &str, path: &str) -> bool {
+    fn matches(pattern: &[u8], path: &[u8]) -> bool {
+        match (pattern.first(), path.first()) {
+            (None, None) => true,
+            (Some(b'*'), _) => {
+                matches(&pattern[1..], path)
+                    || (path.first().is_some_and(|c| *c != b'/') && matches(pattern, &path[1..]))
+            }
+            (Some(b'?'), Some(c)) if *c != b'/' => matches(&pattern[1..], &path[1..]),
+            (Some(p), Some(c)) if p == c => matches(&pattern[1..], &path[1..]),
+            _ => false,
+        }
+    }
+
+    matches(pattern.as_bytes(), path.as_bytes())
+}
+
+/// Returns `true` if `pattern` contains glob wildcards
+pub(crate) fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// List the relative paths of all files below `basedir` matching `pattern`
+pub(crate) fn expand_glob(basedir: &Path, pattern: &str) -> Result<Vec<String>, Error> {
+    fn walk(
+        dir: &Path,
+        rel_dir: &str,
+        components: &[&str],
+        list: &mut Vec<String>,
+    ) -> Result<(), Error> {
+        let Some((component, rest)) = components.split_first() else {
+            return Ok(());
+        };
+
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => bail!("unable to read directory {dir:?} - {err}"),
+        };
+
+        for entry in entries {
+            let entry = entry?;
+            let Some(name) = entry.file_name().to_str().map(String::from) else {
+                continue;
+            };
+            if !glob_match(component, &name) {
+                continue;
+            }
+
+            let rel_path = if rel_dir.is_empty() {
+                name
+            } else {
+                format!("{rel_dir}/{name}")
+            };
+
+            let file_type = entry.file_type()?;
+            if rest.is_empty() {
+                if file_type.is_file() {
+                    list.push(rel_path);
+                }
+            } else if file_type.is_dir() {
+                walk(&entry.path(), &rel_path, rest, list)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    let components: Vec<&str> = pattern
+        .split('/')
+        .filter(|component| !component.is_empty())
+        .collect();
+
+    let mut list = Vec::new();
+    walk(basedir, "", &components, &mut list)?;
+    list.sort();
+
+    Ok(list)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn combine_aligned_entries() -> Result<(), Error> {
+        let entries = [
+            Entry::new(60, 60, vec![Some(1.0), Some(2.0), None, Some(4.0)]),
+            Entry::new(60, 60, vec![Some(3.0), None, None, Some(8.0)]),
+            Entry::new(60, 60, vec![Some(5.0), Some(6.0), None, Some(0.0)]),
+        ];
+
+        let combined = combine_entries(&entries, CombineFn::Sum)?;
+        assert_eq!(combined.start, 60);
+        assert_eq!(combined.resolution, 60);
+        assert_eq!(combined.data, [Some(9.0), Some(8.0), None, Some(12.0)]);
+
+        let combined = combine_entries(&entries, CombineFn::Average)?;
+        assert_eq!(combined.data, [Some(3.0), Some(4.0), None, Some(4.0)]);
+
+        let combined = combine_entries(&entries, CombineFn::Minimum)?;
+        assert_eq!(combined.data, [Some(1.0), Some(2.0), None, Some(0.0)]);
+
+        let combined = combine_entries(&entries, CombineFn::Maximum)?;
+        assert_eq!(combined.data, [Some(5.0), Some(6.0), None, Some(8.0)]);
+
+        let combined = combine_entries(&entries, CombineFn::Percentile(50.0))?;
+        assert_eq!(combined.data, [Some(3.0), Some(4.0), None, Some(4.0)]);
+
+        let combined = combine_entries(&entries, CombineFn::Percentile(75.0))?;
+        assert_eq!(combined.data, [Some(4.0), Some(5.0), None, Some(6.0)]);
+
+        assert!(combine_entries(&entries, CombineFn::Percentile(101.0)).is_err());
+        assert!(combine_entries(&[], CombineFn::Sum).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn combine_mismatched_entries() -> Result<(), Error> {
+        let entries = [
+            // finer resolution, averaged into 120s slots
+            Entry::new(120, 60, vec![Some(1.0), Some(3.0), None, Some(4.0)]),
+            // shifted time range
+            Entry::new(240, 120, vec![Some(10.0), Some(20.0)]),
+        ];
+
+        let combined = combine_entries(&entries, CombineFn::Sum)?;
+        assert_eq!(combined.start, 120);
+        assert_eq!(combined.resolution, 120);
+        assert_eq!(combined.data, [Some(2.0), Some(14.0), Some(20.0)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn glob() {
+        assert!(glob_match("vm/*/cpu", "vm/100/cpu"));
+        assert!(!glob_match("vm/*/cpu", "vm/100/mem"));
+        assert!(!glob_match("vm/*", "vm/100/cpu"));
+        assert!(glob_match("vm/1??", "vm/101"));
+        assert!(!glob_match("vm/1??", "vm/1010"));
+        assert!(glob_match("*", ""));
+    }
+}
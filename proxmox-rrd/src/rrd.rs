@@ -10,6 +10,7 @@
 //! * Well defined data format [CBOR](https://datatracker.ietf.org/doc/html/rfc8949)
 //! * Platform independent (big endian f64, hopefully a standard format?)
 //! * Arbitrary number of RRAs (dynamically changeable)
+//! * Multiple data sources per file with format version 3 (see [MultiDatabase])
 
 use std::io::{Read, Write};
 use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd};
@@ -21,6 +22,14 @@ use serde::{Deserialize, Serialize};
 use proxmox_schema::api;
 use proxmox_sys::fs::{make_tmp_file, CreateOptions};
 
+mod multi;
+pub use multi::*;
+
+mod dump;
+pub use dump::{ArchiveDump, RrdDump, SourceDump, RRD_DUMP_VERSION};
+
+mod resample;
+
 /// Proxmox RRD v2 file magic number
 // openssl::sha::sha256(b"Proxmox Round Robin Database file v2.0")[0..8];
 pub const PROXMOX_RRD_MAGIC_2_0: [u8; 8] = [224, 200, 228, 27, 239, 112, 122, 159];
@@ -157,6 +166,48 @@ impl DataSource {
 
         Ok(value)
     }
+
+    /// Update the data source and its archives with a new value.
+    pub(crate) fn update(
+        &mut self,
+        rra_list: &mut [Archive],
+        time: f64,
+        value: f64,
+    ) -> Result<(), Error> {
+        let value = self.compute_new_value(time, value)?;
+
+        let last_update = self.last_update;
+        self.last_update = time;
+
+        for rra in rra_list.iter_mut() {
+            rra.delete_old_slots(time, last_update);
+            rra.compute_new_value(time, last_update, value);
+        }
+
+        Ok(())
+    }
+}
+
+/// Select the archive with the highest resolution not above
+/// `resolution`, returns its index in `rra_list`.
+///
+/// The first one wins if several archives have the same resolution.
+pub(crate) fn select_rra(
+    rra_list: impl Iterator<Item = (AggregationFn, u64)>,
+    cf: AggregationFn,
+    resolution: u64,
+) -> Option<usize> {
+    let mut selected: Option<(usize, u64)> = None;
+    for (index, (item_cf, item_resolution)) in rra_list.enumerate() {
+        if item_cf != cf || item_resolution > resolution {
+            continue;
+        }
+        match selected {
+            Some((_, current)) if current >= item_resolution => (),
+            _ => selected = Some((index, item_resolution)),
+        }
+    }
+    selected.map(|(index, _)| index)
 }
 
 #[derive(Serialize, Deserialize)]
@@ -373,6 +424,9 @@ impl Database {
             }
             magic if magic == PROXMOX_RRD_MAGIC_2_0 => serde_cbor::from_slice(&raw[8..])
                 .map_err(|err| format_err!("unable to decode RRD file - {err}"))?,
+            magic if magic == PROXMOX_RRD_MAGIC_3_0 => {
+                bail!("rrd file uses the multi-source format v3 (see MultiDatabase)")
+            }
             _ => bail!("not an rrd file - unknown magic number"),
         };
 
@@ -389,28 +443,9 @@ impl Database {
     /// `fadvise(..,POSIX_FADV_DONTNEED)` to avoid keeping the data in
     /// the linux page cache.
     pub fn load(path: &Path, avoid_page_cache: bool) -> Result<Self, std::io::Error> {
-        let mut file = std::fs::File::open(path)?;
-        let buffer_size = file.metadata().map(|m| m.len() as usize + 1).unwrap_or(0);
-        let mut raw = Vec::with_capacity(buffer_size);
-        file.read_to_end(&mut raw)?;
-
-        if avoid_page_cache {
-            nix::fcntl::posix_fadvise(
-                file.as_raw_fd(),
-                0,
-                buffer_size as i64,
-                nix::fcntl::PosixFadviseAdvice::POSIX_FADV_DONTNEED,
-            )
-            .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err.to_string()))?;
-        }
+        let raw = read_raw(path, avoid_page_cache)?;
 
-        match Self::from_raw(&raw) {
-            Ok(rrd) => Ok(rrd),
-            Err(err) => Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                err.to_string(),
-            )),
-        }
+        Self::from_raw(&raw).map_err(|err| std::io::Error::other(err.to_string()))
     }
 
     /// Store data into a file (atomic replace file)
@@ -424,41 +459,11 @@ impl Database {
         options: CreateOptions,
         avoid_page_cache: bool,
     ) -> Result<(), Error> {
-        let (fd, tmp_path) = make_tmp_file(path, options)?;
-        let mut file = unsafe { std::fs::File::from_raw_fd(fd.into_raw_fd()) };
-
-        let mut try_block = || -> Result<(), Error> {
-            let mut data: Vec<u8> = Vec::new();
-            data.extend(PROXMOX_RRD_MAGIC_2_0);
-            serde_cbor::to_writer(&mut data, self)?;
-            file.write_all(&data)?;
-
-            if avoid_page_cache {
-                nix::fcntl::posix_fadvise(
-                    file.as_raw_fd(),
-                    0,
-                    data.len() as i64,
-                    nix::fcntl::PosixFadviseAdvice::POSIX_FADV_DONTNEED,
-                )?;
-            }
+        let mut data: Vec<u8> = Vec::new();
+        data.extend(PROXMOX_RRD_MAGIC_2_0);
+        serde_cbor::to_writer(&mut data, self)?;
 
-            Ok(())
-        };
-
-        match try_block() {
-            Ok(()) => (),
-            error => {
-                let _ = nix::unistd::unlink(&tmp_path);
-                return error;
-            }
-        }
-
-        if let Err(err) = std::fs::rename(&tmp_path, path) {
-            let _ = nix::unistd::unlink(&tmp_path);
-            bail!("Atomic rename failed - {}", err);
-        }
-
-        Ok(())
+        write_raw(path, &data, options, avoid_page_cache)
     }
 
     /// Returns the last update time.
@@ -470,20 +475,8 @@ impl Database {
     ///
     /// Note: This does not call [Self::save].
     pub fn update(&mut self, time: f64, value: f64) {
-        let value = match self.source.compute_new_value(time, value) {
-            Ok(value) => value,
-            Err(err) => {
-                log::error!("rrd update failed: {}", err);
-                return;
-            }
-        };
-
-        let last_update = self.source.last_update;
-        self.source.last_update = time;
-
-        for rra in self.rra_list.iter_mut() {
-            rra.delete_old_slots(time, last_update);
-            rra.compute_new_value(time, last_update, value);
+        if let Err(err) = self.source.update(&mut self.rra_list, time, value) {
+            log::error!("rrd update failed: {}", err);
         }
     }
 
@@ -501,25 +494,13 @@ impl Database {
         start: Option<u64>,
         end: Option<u64>,
     ) -> Result<Entry, Error> {
-        let mut rra: Option<&Archive> = None;
-        for item in self.rra_list.iter() {
-            if item.cf != cf {
-                continue;
-            }
-            if item.resolution > resolution {
-                continue;
-            }
-
-            if let Some(current) = rra {
-                if item.resolution > current.resolution {
-                    rra = Some(item);
-                }
-            } else {
-                rra = Some(item);
-            }
-        }
+        let index = select_rra(
+            self.rra_list.iter().map(|rra| (rra.cf, rra.resolution)),
+            cf,
+            resolution,
+        );
 
-        match rra {
+        match index.map(|index| &self.rra_list[index]) {
             Some(rra) => {
                 let end = end.unwrap_or_else(|| proxmox_time::epoch_f64() as u64);
                 let start = start.unwrap_or_else(|| end.saturating_sub(10 * rra.resolution));
@@ -530,6 +511,67 @@ impl Database {
     }
 }
 
+/// Read the raw content of an RRD file.
+pub(crate) fn read_raw(path: &Path, avoid_page_cache: bool) -> Result<Vec<u8>, std::io::Error> {
+    let mut file = std::fs::File::open(path)?;
+    let buffer_size = file.metadata().map(|m| m.len() as usize + 1).unwrap_or(0);
+    let mut raw = Vec::with_capacity(buffer_size);
+    file.read_to_end(&mut raw)?;
+
+    if avoid_page_cache {
+        nix::fcntl::posix_fadvise(
+            file.as_raw_fd(),
+            0,
+            buffer_size as i64,
+            nix::fcntl::PosixFadviseAdvice::POSIX_FADV_DONTNEED,
+        )
+        .map_err(|err| std::io::Error::other(err.to_string()))?;
+    }
+
+    Ok(raw)
+}
+
+/// Atomically replace an RRD file with `data`.
+pub(crate) fn write_raw(
+    path: &Path,
+    data: &[u8],
+    options: CreateOptions,
+    avoid_page_cache: bool,
+) -> Result<(), Error> {
+    let (fd, tmp_path) = make_tmp_file(path, options)?;
+    let mut file = unsafe { std::fs::File::from_raw_fd(fd.into_raw_fd()) };
+
+    let mut try_block = || -> Result<(), Error> {
+        file.write_all(data)?;
+
+        if avoid_page_cache {
+            nix::fcntl::posix_fadvise(
+                file.as_raw_fd(),
+                0,
+                data.len() as i64,
+                nix::fcntl::PosixFadviseAdvice::POSIX_FADV_DONTNEED,
+            )?;
+        }
+
+        Ok(())
+    };
+
+    match try_block() {
+        Ok(()) => (),
+        error => {
+            let _ = nix::unistd::unlink(&tmp_path);
+            return error;
+        }
+    }
+
+    if let Err(err) = std::fs::rename(&tmp_path, path) {
+        let _ = nix::unistd::unlink(&tmp_path);
+        bail!("Atomic rename failed - {}", err);
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,779 @@
+//! # Export and import of RRD data
+//!
+//! Databases can be exported to, and imported from, two text formats:
+//!
+//! * The XML format written by `rrdtool dump` and read by `rrdtool
+//!   restore`. Timestamps are truncated to full seconds and the number
+//!   of values consolidated into the current slot (`last_count`) is not
+//!   preserved, because rrdtool has no equivalent. When importing, the
+//!   oldest row of each RRA is dropped, because its slot is used for the
+//!   current, not yet finished interval.
+//!
+//! * A lossless JSON format, see [RrdDump]:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "rra": [ { "cf": "average", "resolution": 60, "points": 3 } ],
+//!   "sources": [
+//!     {
+//!       "name": "cpu",
+//!       "type": "gauge",
+//!       "last-update": 1700000130.0,
+//!       "last-value": 0.25,
+//!       "rra": [
+//!         { "start": 1700000040, "last-count": 2, "data": [ 0.5, null, 0.25 ] }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! `data` lists the values of each RRA in chronological order, starting
+//! with the slot beginning at `start`. The last value belongs to the
+//! slot containing `last-update`. Unknown values are `null`.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
+use anyhow::{bail, format_err, Error};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    AggregationFn, Archive, ArchiveDefinition, DataSource, DataSourceType, Database, MultiDatabase,
+    NamedSource,
+};
+
+/// Version of the JSON dump format
+pub const RRD_DUMP_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// JSON dump of a database
+pub struct RrdDump {
+    /// Format version, see [RRD_DUMP_VERSION]
+    pub version: u32,
+    /// Archive definitions shared by all data sources
+    pub rra: Vec<ArchiveDefinition>,
+    /// The data sources
+    pub sources: Vec<SourceDump>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// JSON dump of a data source
+pub struct SourceDump {
+    /// Name of the data source
+    pub name: String,
+    /// Data source type
+    #[serde(rename = "type")]
+    pub dst: DataSourceType,
+    /// Last update time (epoch)
+    pub last_update: f64,
+    /// Last value, used to compute differential values for derive/counters
+    pub last_value: Option<f64>,
+    /// Data of each RRA, in the order of [RrdDump::rra]
+    pub rra: Vec<ArchiveDump>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+/// JSON dump of the data of a single RRA
+pub struct ArchiveDump {
+    /// Start time of the first slot (epoch)
+    pub start: i64,
+    /// Count of values computed inside the current update interval
+    pub last_count: u64,
+    /// Values in chronological order
+    pub data: Vec<Option<f64>>,
+}
+
+/// Borrowed view of a data source, shared by [Database] and [MultiDatabase]
+struct SourceRef<'a> {
+    name: &'a str,
+    source: &'a DataSource,
+    rra_list: &'a [Archive],
+}
+
+impl Database {
+    /// Export to the lossless JSON dump format, using `name` as data source name
+    pub fn to_dump(&self, name: &str) -> RrdDump {
+        let rra_list: Vec<_> = self.rra_list.iter().map(ArchiveDefinition::from).collect();
+        to_dump(&rra_list, &[self.as_source_ref(name)])
+    }
+
+    /// Import from the JSON dump format, which needs to contain a single data source
+    pub fn from_dump(dump: RrdDump) -> Result<Self, Error> {
+        MultiDatabase::from_dump(dump)?.into_single()
+    }
+
+    /// Export to the rrdtool XML dump format, using `name` as data source name
+    pub fn to_rrdtool_xml(&self, name: &str) -> Result<String, Error> {
+        let rra_list: Vec<_> = self.rra_list.iter().map(ArchiveDefinition::from).collect();
+        to_rrdtool_xml(&rra_list, &[self.as_source_ref(name)])
+    }
+
+    /// Import from the rrdtool XML dump format, which needs to contain a single data source
+    pub fn from_rrdtool_xml(xml: &str) -> Result<Self, Error> {
+        MultiDatabase::from_rrdtool_xml(xml)?.into_single()
+    }
+
+    fn as_source_ref<'a>(&'a self, name: &'a str) -> SourceRef<'a> {
+        SourceRef {
+            name,
+            source: &self.source,
+            rra_list: &self.rra_list,
+        }
+    }
+}
+
+impl MultiDatabase {
+    /// Export to the lossless JSON dump format
+    pub fn to_dump(&self) -> RrdDump {
+        to_dump(&self.rra_list, &self.source_refs())
+    }
+
+    /// Import from the JSON dump format
+    pub fn from_dump(dump: RrdDump) -> Result<Self, Error> {
+        if dump.version != RRD_DUMP_VERSION {
+            bail!("unsupported dump version {}", dump.version);
+        }
+        verify_definitions(&dump.rra)?;
+
+        let mut rrd = MultiDatabase::new(dump.rra);
+
+        for source in dump.sources {
+            if source.rra.len() != rrd.rra_list.len() {
+                bail!("data source '{}' has wrong number of RRAs", source.name);
+            }
+
+            let mut rra_list = Vec::with_capacity(source.rra.len());
+
+            for (def, dump) in rrd.rra_list.iter().zip(source.rra) {
+                if dump.data.len() != def.points {
+                    bail!(
+                        "data source '{}' has wrong number of data points",
+                        source.name
+                    );
+                }
+
+                let mut rra = Archive::new(def.cf, def.resolution, def.points);
+                rra.last_count = dump.last_count;
+                for (i, value) in dump.data.into_iter().enumerate() {
+                    let time = dump.start + (i as u64 * def.resolution) as i64;
+                    set_slot(&mut rra, time, value)?;
+                }
+                rra_list.push(rra);
+            }
+
+            rrd.sources.push(NamedSource {
+                name: source.name,
+                source: DataSource {
+                    dst: source.dst,
+                    last_update: source.last_update,
+                    last_value: source.last_value.unwrap_or(f64::NAN),
+                },
+                rra_list,
+            });
+        }
+
+        rrd.verify()?;
+
+        Ok(rrd)
+    }
+
+    /// Export to the rrdtool XML dump format
+    pub fn to_rrdtool_xml(&self) -> Result<String, Error> {
+        to_rrdtool_xml(&self.rra_list, &self.source_refs())
+    }
+
+    /// Import from the rrdtool XML dump format
+    pub fn from_rrdtool_xml(xml: &str) -> Result<Self, Error> {
+        let root = XmlElement::parse(xml)?;
+        if root.name != "rrd" {
+            bail!(
+                "not an rrdtool dump - unexpected root element '{}'",
+                root.name
+            );
+        }
+
+        let step: u64 = root.value("step")?;
+        let last_update: u64 = root.value("lastupdate")?;
+
+        let mut rrd = MultiDatabase::new(Vec::new());
+
+        for ds in root.children("ds") {
+            let name = ds.child("name")?.text.trim();
+            let dst = match ds.child("type")?.text.trim() {
+                "GAUGE" => DataSourceType::Gauge,
+                "DERIVE" => DataSourceType::Derive,
+                "COUNTER" => DataSourceType::Counter,
+                other => bail!("data source '{name}' has unsupported type '{other}'"),
+            };
+
+            rrd.add_source(name, dst)?;
+
+            let source = &mut rrd.sources.last_mut().unwrap().source;
+            source.last_update = last_update as f64;
+            source.last_value = parse_value(ds.child("last_ds")?.text.trim())?;
+        }
+
+        for xml_rra in root.children("rra") {
+            let cf = match xml_rra.child("cf")?.text.trim() {
+                "AVERAGE" => AggregationFn::Average,
+                "MIN" => AggregationFn::Minimum,
+                "MAX" => AggregationFn::Maximum,
+                "LAST" => AggregationFn::Last,
+                other => bail!("unsupported consolidation function '{other}'"),
+            };
+            let pdp_per_row: u64 = xml_rra.value("pdp_per_row")?;
+            let rows: Vec<&XmlElement> = xml_rra.child("database")?.children("row").collect();
+
+            let def = ArchiveDefinition::new(cf, step * pdp_per_row, rows.len());
+            verify_definitions(&[def])?;
+
+            let current = (last_update - last_update % def.resolution) as i64;
+            let resolution = def.resolution as i64;
+            let points = def.points as i64;
+
+            let prep: Vec<&XmlElement> = match xml_rra.children("cdp_prep").next() {
+                Some(prep) => prep.children("ds").collect(),
+                None => Vec::new(),
+            };
+
+            for (index, named) in rrd.sources.iter_mut().enumerate() {
+                let mut rra = Archive::new(def.cf, def.resolution, def.points);
+
+                // The first row shares its slot with the current interval.
+                for (i, row) in rows.iter().enumerate().skip(1) {
+                    let value = match row.children("v").nth(index) {
+                        Some(value) => parse_value(value.text.trim())?,
+                        None => bail!("row has too few values"),
+                    };
+                    // rows are labeled with the end of their interval
+                    let time = current - (points - i as i64) * resolution;
+                    set_slot(&mut rra, time, (!value.is_nan()).then_some(value))?;
+                }
+
+                if let Some(prep) = prep.get(index) {
+                    let value = parse_value(prep.child("value")?.text.trim())?;
+                    if !value.is_nan() {
+                        set_slot(&mut rra, current, Some(value))?;
+                        rra.last_count = 1;
+                    }
+                }
+
+                named.rra_list.push(rra);
+            }
+
+            rrd.rra_list.push(def);
+        }
+
+        if rrd.rra_list.is_empty() {
+            bail!("rrdtool dump does not contain any RRA");
+        }
+
+        rrd.verify()?;
+
+        Ok(rrd)
+    }
+
+    fn source_refs(&self) -> Vec<SourceRef<'_>> {
+        self.sources
+            .iter()
+            .map(|named| SourceRef {
+                name: &named.name,
+                source: &named.source,
+                rra_list: &named.rra_list,
+            })
+            .collect()
+    }
+
+    fn into_single(mut self) -> Result<Database, Error> {
+        if self.sources.len() != 1 {
+            bail!(
+                "expected a single data source, found {}",
+                self.sources.len()
+            );
+        }
+
+        let named = self.sources.pop().unwrap();
+
+        Ok(Database {
+            source: named.source,
+            rra_list: named.rra_list,
+        })
+    }
+}
+
+fn verify_definitions(rra_list: &[ArchiveDefinition]) -> Result<(), Error> {
+    for def in rra_list {
+        if def.resolution == 0 {
+            bail!("RRA resolution must not be zero");
+        }
+        if def.points == 0 {
+            bail!("RRA must contain at least one data point");
+        }
+    }
+    Ok(())
+}
+
+/// Start time of the first slot, if the last slot contains `last_update`
+fn first_slot_start(rra: &Archive, last_update: f64) -> i64 {
+    let current = rra.slot_start_time(last_update as u64) as i64;
+    current - (rra.data.len() as i64 - 1) * rra.resolution as i64
+}
+
+/// Value of the slot starting at `time`, if it is within the range covered by the RRA
+fn slot_value(rra: &Archive, last_update: f64, time: i64) -> Option<f64> {
+    let current = rra.slot_start_time(last_update as u64) as i64;
+    if time < 0 || time < first_slot_start(rra, last_update) || time > current {
+        return None;
+    }
+    let value = rra.data[rra.slot(time as u64)];
+    (!value.is_nan()).then_some(value)
+}
+
+fn set_slot(rra: &mut Archive, time: i64, value: Option<f64>) -> Result<(), Error> {
+    match value {
+        Some(_) if time < 0 => bail!("got value for negative time"),
+        Some(value) => {
+            let index = rra.slot(time as u64);
+            rra.data[index] = value;
+        }
+        None => {}
+    }
+    Ok(())
+}
+
+fn to_dump(rra_list: &[ArchiveDefinition], sources: &[SourceRef]) -> RrdDump {
+    let sources = sources
+        .iter()
+        .map(|source| {
+            let last_update = source.source.last_update;
+            let rra = source
+                .rra_list
+                .iter()
+                .map(|rra| {
+                    let start = first_slot_start(rra, last_update);
+                    let data = (0..rra.data.len() as i64)
+                        .map(|i| slot_value(rra, last_update, start + i * rra.resolution as i64))
+                        .collect();
+                    ArchiveDump {
+                        start,
+                        last_count: rra.last_count,
+                        data,
+                    }
+                })
+                .collect();
+
+            SourceDump {
+                name: source.name.to_string(),
+                dst: source.source.dst,
+                last_update,
+                last_value: Some(source.source.last_value).filter(|value| !value.is_nan()),
+                rra,
+            }
+        })
+        .collect();
+
+    RrdDump {
+        version: RRD_DUMP_VERSION,
+        rra: rra_list.to_vec(),
+        sources,
+    }
+}
+
+fn to_rrdtool_xml(rra_list: &[ArchiveDefinition], sources: &[SourceRef]) -> Result<String, Error> {
+    fn gcd(a: u64, b: u64) -> u64 {
+        if b == 0 {
+            a
+        } else {
+            gcd(b, a % b)
+        }
+    }
+
+    if rra_list.is_empty() {
+        bail!("database does not contain any RRA");
+    }
+    verify_definitions(rra_list)?;
+
+    let step = rra_list.iter().map(|def| def.resolution).fold(0, gcd);
+    let last_update = sources
+        .iter()
+        .map(|source| source.source.last_update)
+        .fold(0.0, f64::max) as u64;
+
+    let mut xml = String::new();
+
+    writeln!(xml, "<?xml version=\"1.0\" encoding=\"utf-8\"?>")?;
+    writeln!(
+        xml,
+        "<!DOCTYPE rrd SYSTEM \"https://oss.oetiker.ch/rrdtool/rrdtool.dtd\">"
+    )?;
+    writeln!(xml, "<!-- Exported by proxmox-rrd -->")?;
+    writeln!(xml, "<rrd>")?;
+    writeln!(xml, "\t<version>0003</version>")?;
+    writeln!(xml, "\t<step>{step}</step> <!-- Seconds -->")?;
+    writeln!(xml, "\t<lastupdate>{last_update}</lastupdate>")?;
+
+    for source in sources {
+        let dst = match source.source.dst {
+            DataSourceType::Gauge => "GAUGE",
+            DataSourceType::Derive => "DERIVE",
+            DataSourceType::Counter => "COUNTER",
+        };
+        let last_value = source.source.last_value;
+
+        writeln!(xml, "\t<ds>")?;
+        writeln!(xml, "\t\t<name> {} </name>", escape_xml(source.name))?;
+        writeln!(xml, "\t\t<type> {dst} </type>")?;
+        writeln!(
+            xml,
+            "\t\t<minimal_heartbeat>{}</minimal_heartbeat>",
+            step * 2
+        )?;
+        writeln!(xml, "\t\t<min>NaN</min>")?;
+        writeln!(xml, "\t\t<max>NaN</max>")?;
+        writeln!(xml, "\t\t<!-- PDP Status -->")?;
+        if last_value.is_nan() {
+            writeln!(xml, "\t\t<last_ds>U</last_ds>")?;
+        } else {
+            writeln!(xml, "\t\t<last_ds>{last_value:e}</last_ds>")?;
+        }
+        writeln!(xml, "\t\t<value>0e0</value>")?;
+        writeln!(xml, "\t\t<unknown_sec> 0 </unknown_sec>")?;
+        writeln!(xml, "\t</ds>")?;
+    }
+
+    writeln!(xml, "\t<!-- Round Robin Archives -->")?;
+
+    for (index, def) in rra_list.iter().enumerate() {
+        let cf = match def.cf {
+            AggregationFn::Average => "AVERAGE",
+            AggregationFn::Minimum => "MIN",
+            AggregationFn::Maximum => "MAX",
+            AggregationFn::Last => "LAST",
+        };
+        let current = (last_update - last_update % def.resolution) as i64;
+        let resolution = def.resolution as i64;
+        let points = def.points as i64;
+
+        writeln!(xml, "\t<rra>")?;
+        writeln!(xml, "\t\t<cf>{cf}</cf>")?;
+        writeln!(
+            xml,
+            "\t\t<pdp_per_row>{}</pdp_per_row> <!-- {} seconds -->",
+            def.resolution / step,
+            def.resolution
+        )?;
+        writeln!(xml, "\t\t<params>")?;
+        writeln!(xml, "\t\t<xff>5e-1</xff>")?;
+        writeln!(xml, "\t\t</params>")?;
+        writeln!(xml, "\t\t<cdp_prep>")?;
+        for source in sources {
+            let value = slot_value(&source.rra_list[index], source.source.last_update, current);
+            writeln!(xml, "\t\t\t<ds>")?;
+            writeln!(xml, "\t\t\t<primary_value>NaN</primary_value>")?;
+            writeln!(xml, "\t\t\t<secondary_value>NaN</secondary_value>")?;
+            writeln!(xml, "\t\t\t<value>{}</value>", format_value(value))?;
+            writeln!(xml, "\t\t\t<unknown_datapoints>0</unknown_datapoints>")?;
+            writeln!(xml, "\t\t\t</ds>")?;
+        }
+        writeln!(xml, "\t\t</cdp_prep>")?;
+        writeln!(xml, "\t\t<database>")?;
+
+        for row in 0..points {
+            // rows are labeled with the end of their interval
+            let time = current - (points - 1 - row) * resolution;
+            write!(xml, "\t\t\t<!-- {time} --> <row>")?;
+            for source in sources {
+                let rra = &source.rra_list[index];
+                let value = slot_value(rra, source.source.last_update, time - resolution);
+                write!(xml, "<v>{}</v>", format_value(value))?;
+            }
+            writeln!(xml, "</row>")?;
+        }
+
+        writeln!(xml, "\t\t</database>")?;
+        writeln!(xml, "\t</rra>")?;
+    }
+
+    writeln!(xml, "</rrd>")?;
+
+    Ok(xml)
+}
+
+fn format_value(value: Option<f64>) -> String {
+    match value {
+        Some(value) => format!("{value:e}"),
+        None => "NaN".to_string(),
+    }
+}
+
+fn parse_value(value: &str) -> Result<f64, Error> {
+    match value {
+        "U" | "UNKN" => Ok(f64::NAN),
+        value => value
+            .parse()
+            .map_err(|_| format_err!("unable to parse value '{value}'")),
+    }
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn unescape_xml(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Minimal XML element tree, sufficient for rrdtool dumps
+///
+/// Attributes, CDATA sections and DTD internal subsets are not supported.
+struct XmlElement {
+    name: String,
+    text: String,
+    children: Vec<XmlElement>,
+}
+
+impl XmlElement {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            text: String::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn parse(input: &str) -> Result<Self, Error> {
+        fn split_at_end<'a>(input: &'a str, end: &str) -> Result<(&'a str, &'a str), Error> {
+            let pos = input
+                .find(end)
+                .ok_or_else(|| format_err!("unterminated XML markup"))?;
+            Ok((&input[..pos], &input[pos + end.len()..]))
+        }
+
+        let mut stack = vec![XmlElement::new("")];
+        let mut rest = input;
+
+        while !rest.is_empty() {
+            if let Some(markup) = rest.strip_prefix("<!--") {
+                rest = split_at_end(markup, "-->")?.1;
+            } else if let Some(markup) = rest.strip_prefix("<?") {
+                rest = split_at_end(markup, "?>")?.1;
+            } else if let Some(markup) = rest.strip_prefix("<!") {
+                rest = split_at_end(markup, ">")?.1;
+            } else if let Some(markup) = rest.strip_prefix("</") {
+                let (name, remaining) = split_at_end(markup, ">")?;
+                rest = remaining;
+
+                let element = stack.pop().unwrap();
+                if element.name != name.trim() {
+                    bail!("unexpected closing tag '{}'", name.trim());
+                }
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => bail!("unexpected closing tag '{}'", name.trim()),
+                }
+            } else if let Some(markup) = rest.strip_prefix('<') {
+                let (tag, remaining) = split_at_end(markup, ">")?;
+                rest = remaining;
+
+                let (tag, empty) = match tag.strip_suffix('/') {
+                    Some(tag) => (tag, true),
+                    None => (tag, false),
+                };
+                let name = tag.split_whitespace().next().unwrap_or_default();
+                if name.is_empty() {
+                    bail!("got XML tag without name");
+                }
+
+                let element = XmlElement::new(name);
+                if empty {
+                    stack.last_mut().unwrap().children.push(element);
+                } else {
+                    stack.push(element);
+                }
+            } else {
+                let end = rest.find('<').unwrap_or(rest.len());
+                stack
+                    .last_mut()
+                    .unwrap()
+                    .text
+                    .push_str(&unescape_xml(&rest[..end]));
+                rest = &rest[end..];
+            }
+        }
+
+        if stack.len() != 1 {
+            bail!("unclosed XML element '{}'", stack.last().unwrap().name);
+        }
+
+        let mut document = stack.pop().unwrap();
+        match document.children.len() {
+            1 => Ok(document.children.pop().unwrap()),
+            _ => bail!("XML document needs exactly one root element"),
+        }
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlElement> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    fn child(&self, name: &str) -> Result<&XmlElement, Error> {
+        self.children
+            .iter()
+            .find(|child| child.name == name)
+            .ok_or_else(|| format_err!("missing XML element '{name}' in '{}'", self.name))
+    }
+
+    fn value<T: FromStr>(&self, name: &str) -> Result<T, Error> {
+        let text = self.child(name)?.text.trim();
+        text.parse()
+            .map_err(|_| format_err!("unable to parse '{name}' value '{text}'"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_rrd() -> MultiDatabase {
+        let mut rrd = MultiDatabase::new(vec![
+            ArchiveDefinition::new(AggregationFn::Average, 60, 5),
+            ArchiveDefinition::new(AggregationFn::Maximum, 120, 5),
+        ]);
+        rrd.add_source("cpu", DataSourceType::Gauge).unwrap();
+        rrd.add_source("net<in>", DataSourceType::Derive).unwrap();
+
+        for i in 20..30 {
+            rrd.update(
+                (i * 30) as f64,
+                &[("cpu", i as f64), ("net<in>", (i * 60) as f64)],
+            )
+            .unwrap();
+        }
+
+        rrd
+    }
+
+    fn extract_all(rrd: &MultiDatabase) -> Vec<Vec<Option<f64>>> {
+        let mut result = Vec::new();
+        for (cf, resolution) in [(AggregationFn::Average, 60), (AggregationFn::Maximum, 120)] {
+            for (_, entry) in rrd
+                .extract_data(cf, resolution, Some(0), Some(1200), None)
+                .unwrap()
+            {
+                result.push(entry.data);
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn json_roundtrip() -> Result<(), Error> {
+        let rrd = test_rrd();
+
+        let json = serde_json::to_string(&rrd.to_dump())?;
+        let imported = MultiDatabase::from_dump(serde_json::from_str(&json)?)?;
+
+        assert_eq!(extract_all(&rrd), extract_all(&imported));
+        for (a, b) in rrd.sources.iter().zip(imported.sources.iter()) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.source.last_update, b.source.last_update);
+            assert_eq!(a.source.last_value, b.source.last_value);
+            for (a, b) in a.rra_list.iter().zip(b.rra_list.iter()) {
+                assert_eq!(a.last_count, b.last_count);
+            }
+        }
+
+        assert!(Database::from_dump(rrd.to_dump()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn rrdtool_xml_roundtrip() -> Result<(), Error> {
+        let rrd = test_rrd();
+
+        let xml = rrd.to_rrdtool_xml()?;
+        assert!(xml.contains("<name> net&lt;in&gt; </name>"));
+        assert!(xml.contains("<pdp_per_row>2</pdp_per_row>"));
+
+        let imported = MultiDatabase::from_rrdtool_xml(&xml)?;
+
+        assert_eq!(
+            imported.source_names().collect::<Vec<_>>(),
+            ["cpu", "net<in>"]
+        );
+        assert_eq!(imported.rra_list, rrd.rra_list);
+        assert_eq!(extract_all(&rrd), extract_all(&imported));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rrdtool_xml_import() -> Result<(), Error> {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<!DOCTYPE rrd SYSTEM "http://oss.oetiker.ch/rrdtool/rrdtool.dtd">
+<!-- Round Robin Database Dump -->
+<rrd>
+	<version>0003</version>
+	<step>60</step> <!-- Seconds -->
+	<lastupdate>330</lastupdate> <!-- 1970-01-01 00:05:30 UTC -->
+	<ds>
+		<name> load </name>
+		<type> GAUGE </type>
+		<minimal_heartbeat>120</minimal_heartbeat>
+		<min>NaN</min>
+		<max>NaN</max>
+		<!-- PDP Status -->
+		<last_ds>2.5</last_ds>
+		<value>0.0000000000e+00</value>
+		<unknown_sec> 0 </unknown_sec>
+	</ds>
+	<!-- Round Robin Archives -->
+	<rra>
+		<cf>AVERAGE</cf>
+		<pdp_per_row>1</pdp_per_row> <!-- 60 seconds -->
+		<params>
+		<xff>5.0000000000e-01</xff>
+		</params>
+		<cdp_prep>
+			<ds>
+			<primary_value>NaN</primary_value>
+			<secondary_value>NaN</secondary_value>
+			<value>NaN</value>
+			<unknown_datapoints>0</unknown_datapoints>
+			</ds>
+		</cdp_prep>
+		<database>
+			<!-- 1970-01-01 00:02:00 UTC / 120 --> <row><v>1.0000000000e+00</v></row>
+			<!-- 1970-01-01 00:03:00 UTC / 180 --> <row><v>2.0000000000e+00</v></row>
+			<!-- 1970-01-01 00:04:00 UTC / 240 --> <row><v>NaN</v></row>
+			<!-- 1970-01-01 00:05:00 UTC / 300 --> <row><v>4.0000000000e+00</v></row>
+		</database>
+	</rra>
+</rrd>
+"#;
+
+        let rrd = Database::from_rrdtool_xml(xml)?;
+        assert_eq!(rrd.source.dst, DataSourceType::Gauge);
+        assert_eq!(rrd.source.last_update, 330.0);
+        assert_eq!(rrd.source.last_value, 2.5);
+
+        let entry = rrd.extract_data(AggregationFn::Average, 60, Some(120), Some(300))?;
+        assert_eq!(entry.data, [Some(2.0), None, Some(4.0), None]);
+
+        assert!(Database::from_rrdtool_xml("<rrd><step>60</step>").is_err());
+
+        Ok(())
+    }
+}
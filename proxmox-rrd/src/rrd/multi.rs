@@ -0,0 +1,455 @@
+//! # Proxmox RRD format version 3
+//!
+//! Version 3 stores several named data sources in a single file. All
+//! data sources share the same set of RRA definitions, so a product
+//! can keep related metrics (for example cpu, mem, netin and netout of
+//! a guest) in one file, instead of one file per metric.
+//!
+//! Files in the version 1 and 2 format are converted on load, see
+//! [MultiDatabase::load] and [MultiDatabase::from_v2].
+
+use std::path::Path;
+
+use anyhow::{bail, format_err, Error};
+use serde::{Deserialize, Serialize};
+
+use proxmox_sys::fs::CreateOptions;
+
+use super::{
+    read_raw, select_rra, write_raw, AggregationFn, Archive, DataSource, DataSourceType, Database,
+};
+use crate::Entry;
+
+/// Proxmox RRD v3 file magic number
+// openssl::sha::sha256(b"Proxmox Round Robin Database file v3.0")[0..8];
+pub const PROXMOX_RRD_MAGIC_3_0: [u8; 8] = [16, 251, 156, 247, 29, 194, 72, 222];
+
+#[derive(Debug, Serialize, Deserialize, Copy, Clone, PartialEq, Eq)]
+/// Definition of a round robin archive, shared by all data sources
+pub struct ArchiveDefinition {
+    /// Consolidation function.
+    pub cf: AggregationFn,
+    /// Number of seconds spanned by a single data entry.
+    pub resolution: u64,
+    /// Number of data entries.
+    pub points: usize,
+}
+
+impl ArchiveDefinition {
+    /// Creates a new instance
+    pub const fn new(cf: AggregationFn, resolution: u64, points: usize) -> Self {
+        Self {
+            cf,
+            resolution,
+            points,
+        }
+    }
+
+    fn matches(&self, rra: &Archive) -> bool {
+        self.cf == rra.cf && self.resolution == rra.resolution && self.points == rra.data.len()
+    }
+}
+
+impl From<&Archive> for ArchiveDefinition {
+    fn from(rra: &Archive) -> Self {
+        Self::new(rra.cf, rra.resolution, rra.data.len())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+/// A named data source with its archives
+pub struct NamedSource {
+    /// Name of the data source
+    pub name: String,
+    /// The data source definition
+    pub source: DataSource,
+    /// Round robin archives, one for each [ArchiveDefinition] of the database
+    pub rra_list: Vec<Archive>,
+}
+
+#[derive(Serialize, Deserialize)]
+/// Round Robin Database with multiple data sources
+pub struct MultiDatabase {
+    /// Archive definitions shared by all data sources
+    pub rra_list: Vec<ArchiveDefinition>,
+    /// The data sources
+    pub sources: Vec<NamedSource>,
+}
+
+impl MultiDatabase {
+    /// Creates a new instance without data sources
+    pub fn new(rra_list: Vec<ArchiveDefinition>) -> Self {
+        Self {
+            rra_list,
+            sources: Vec::new(),
+        }
+    }
+
+    /// Convert a list of named v2 databases into a single database
+    ///
+    /// All databases need to use the same RRA configuration.
+    pub fn from_v2<S: Into<String>>(
+        list: impl IntoIterator<Item = (S, Database)>,
+    ) -> Result<Self, Error> {
+        let mut rrd: Option<MultiDatabase> = None;
+
+        for (name, database) in list {
+            let name = name.into();
+            let rra_list: Vec<ArchiveDefinition> = database
+                .rra_list
+                .iter()
+                .map(ArchiveDefinition::from)
+                .collect();
+
+            let rrd = rrd.get_or_insert_with(|| MultiDatabase::new(rra_list.clone()));
+
+            if rrd.rra_list != rra_list {
+                bail!("unable to convert '{name}' - RRA configuration differs");
+            }
+            if rrd.source(&name).is_some() {
+                bail!("unable to convert '{name}' - duplicate data source");
+            }
+
+            rrd.sources.push(NamedSource {
+                name,
+                source: database.source,
+                rra_list: database.rra_list,
+            });
+        }
+
+        rrd.ok_or_else(|| format_err!("no databases to convert"))
+    }
+
+    fn from_raw(raw: &[u8], name: &str) -> Result<Self, Error> {
+        if raw.len() < 8 {
+            bail!("not an rrd file - file is too small ({})", raw.len());
+        }
+
+        let rrd: MultiDatabase = if raw[0..8] == PROXMOX_RRD_MAGIC_3_0 {
+            serde_cbor::from_slice(&raw[8..])
+                .map_err(|err| format_err!("unable to decode RRD file - {err}"))?
+        } else {
+            let v2 = Database::from_raw(raw)?;
+            Self::from_v2([(name, v2)])
+                .map_err(|err| format_err!("unable to convert from old V2 format - {err}"))?
+        };
+
+        rrd.verify()?;
+
+        Ok(rrd)
+    }
+
+    pub(crate) fn verify(&self) -> Result<(), Error> {
+        for (i, named) in self.sources.iter().enumerate() {
+            if named.source.last_update < 0.0 {
+                bail!("data source '{}' has negative last_update time", named.name);
+            }
+            if self.sources[..i]
+                .iter()
+                .any(|other| other.name == named.name)
+            {
+                bail!("duplicate data source '{}'", named.name);
+            }
+            if named.rra_list.len() != self.rra_list.len()
+                || !self
+                    .rra_list
+                    .iter()
+                    .zip(named.rra_list.iter())
+                    .all(|(def, rra)| def.matches(rra))
+            {
+                bail!("data source '{}' has inconsistent RRAs", named.name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Load data from a file
+    ///
+    /// Files using the version 1 or 2 format are converted to a
+    /// database with a single data source, named after the file.
+    ///
+    /// Setting `avoid_page_cache` uses
+    /// `fadvise(..,POSIX_FADV_DONTNEED)` to avoid keeping the data in
+    /// the linux page cache.
+    pub fn load(path: &Path, avoid_page_cache: bool) -> Result<Self, std::io::Error> {
+        let raw = read_raw(path, avoid_page_cache)?;
+
+        let name = path
+            .file_stem()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+
+        Self::from_raw(&raw, &name).map_err(|err| std::io::Error::other(err.to_string()))
+    }
+
+    /// Store data into a file (atomic replace file)
+    ///
+    /// Setting `avoid_page_cache` uses
+    /// `fadvise(..,POSIX_FADV_DONTNEED)` to avoid keeping the data in
+    /// the linux page cache.
+    pub fn save(
+        &self,
+        path: &Path,
+        options: CreateOptions,
+        avoid_page_cache: bool,
+    ) -> Result<(), Error> {
+        let mut data: Vec<u8> = Vec::new();
+        data.extend(PROXMOX_RRD_MAGIC_3_0);
+        serde_cbor::to_writer(&mut data, self)?;
+
+        write_raw(path, &data, options, avoid_page_cache)
+    }
+
+    /// Add a new, empty data source
+    pub fn add_source(&mut self, name: &str, dst: DataSourceType) -> Result<(), Error> {
+        if self.source(name).is_some() {
+            bail!("data source '{name}' already exists");
+        }
+
+        let rra_list = self
+            .rra_list
+            .iter()
+            .map(|def| Archive::new(def.cf, def.resolution, def.points))
+            .collect();
+
+        self.sources.push(NamedSource {
+            name: name.to_string(),
+            source: DataSource::new(dst),
+            rra_list,
+        });
+
+        Ok(())
+    }
+
+    /// Remove a data source, returns `false` if it did not exist
+    pub fn remove_source(&mut self, name: &str) -> bool {
+        let len = self.sources.len();
+        self.sources.retain(|named| named.name != name);
+        self.sources.len() != len
+    }
+
+    /// Returns the data source called `name`
+    pub fn source(&self, name: &str) -> Option<&NamedSource> {
+        self.sources.iter().find(|named| named.name == name)
+    }
+
+    /// Returns the names of all data sources
+    pub fn source_names(&self) -> impl Iterator<Item = &str> {
+        self.sources.iter().map(|named| named.name.as_str())
+    }
+
+    /// Returns the last update time of any data source.
+    pub fn last_update(&self) -> f64 {
+        self.sources
+            .iter()
+            .map(|named| named.source.last_update)
+            .fold(0.0, f64::max)
+    }
+
+    /// Update the values of several data sources (in memory)
+    ///
+    /// Fails if one of the data sources does not exist, in which case
+    /// no value is updated.
+    ///
+    /// Note: This does not call [Self::save].
+    pub fn update(&mut self, time: f64, values: &[(&str, f64)]) -> Result<(), Error> {
+        for (name, _) in values {
+            if self.source(name).is_none() {
+                bail!("no such data source '{name}'");
+            }
+        }
+
+        for (name, value) in values {
+            if let Some(named) = self.sources.iter_mut().find(|named| named.name == *name) {
+                named.update(time, *value);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Extract data from the archives of several data sources
+    ///
+    /// Works like [Database::extract_data], but returns the data of
+    /// all data sources listed in `sources`, or of all data sources if
+    /// `sources` is `None`. The entries are returned in the order of
+    /// `sources`, or in the order they were added.
+    pub fn extract_data(
+        &self,
+        cf: AggregationFn,
+        resolution: u64,
+        start: Option<u64>,
+        end: Option<u64>,
+        sources: Option<&[&str]>,
+    ) -> Result<Vec<(String, Entry)>, Error> {
+        let index = select_rra(
+            self.rra_list.iter().map(|def| (def.cf, def.resolution)),
+            cf,
+            resolution,
+        )
+        .ok_or_else(|| format_err!("unable to find RRA suitable ({:?}:{})", cf, resolution))?;
+
+        let selected: Vec<&NamedSource> = match sources {
+            Some(names) => names
+                .iter()
+                .map(|name| {
+                    self.source(name)
+                        .ok_or_else(|| format_err!("no such data source '{name}'"))
+                })
+                .collect::<Result<_, Error>>()?,
+            None => self.sources.iter().collect(),
+        };
+
+        let end = end.unwrap_or_else(|| proxmox_time::epoch_f64() as u64);
+        let start =
+            start.unwrap_or_else(|| end.saturating_sub(10 * self.rra_list[index].resolution));
+
+        Ok(selected
+            .into_iter()
+            .map(|named| {
+                let entry =
+                    named.rra_list[index].extract_data(start, end, named.source.last_update);
+                (named.name.clone(), entry)
+            })
+            .collect())
+    }
+}
+
+impl NamedSource {
+    fn update(&mut self, time: f64, value: f64) {
+        if let Err(err) = self.source.update(&mut self.rra_list, time, value) {
+            log::error!("rrd update of '{}' failed: {}", self.name, err);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rra_list() -> Vec<ArchiveDefinition> {
+        vec![
+            ArchiveDefinition::new(AggregationFn::Average, 60, 5),
+            ArchiveDefinition::new(AggregationFn::Maximum, 60, 5),
+        ]
+    }
+
+    #[test]
+    fn multi_source_update_and_extract() -> Result<(), Error> {
+        let mut rrd = MultiDatabase::new(rra_list());
+        rrd.add_source("cpu", DataSourceType::Gauge)?;
+        rrd.add_source("netin", DataSourceType::Derive)?;
+        assert!(rrd.add_source("cpu", DataSourceType::Gauge).is_err());
+
+        for i in 2..10 {
+            rrd.update(
+                (i as f64) * 30.0,
+                &[("cpu", i as f64), ("netin", (i * 60) as f64)],
+            )?;
+        }
+        assert!(rrd.update(330.0, &[("mem", 1.0)]).is_err());
+
+        let data = rrd.extract_data(AggregationFn::Maximum, 60, Some(0), Some(5 * 60), None)?;
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].0, "cpu");
+        assert_eq!(
+            data[0].1.data,
+            [None, Some(3.0), Some(5.0), Some(7.0), Some(9.0)]
+        );
+        assert_eq!(data[1].0, "netin");
+        assert_eq!(
+            data[1].1.data,
+            [None, Some(2.0), Some(2.0), Some(2.0), Some(2.0)]
+        );
+
+        let data = rrd.extract_data(
+            AggregationFn::Average,
+            60,
+            Some(60),
+            Some(5 * 60),
+            Some(&["cpu"]),
+        )?;
+        assert_eq!(data.len(), 1);
+        assert_eq!(
+            data[0].1.data,
+            [Some(2.5), Some(4.5), Some(6.5), Some(8.5), None]
+        );
+
+        assert!(rrd
+            .extract_data(AggregationFn::Last, 60, None, None, None)
+            .is_err());
+        assert!(rrd
+            .extract_data(AggregationFn::Average, 60, None, None, Some(&["mem"]))
+            .is_err());
+
+        assert!(rrd.remove_source("netin"));
+        assert_eq!(rrd.source_names().collect::<Vec<_>>(), ["cpu"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn select_first_rra_on_equal_resolution() -> Result<(), Error> {
+        let rra_list = vec![
+            ArchiveDefinition::new(AggregationFn::Average, 60, 5),
+            ArchiveDefinition::new(AggregationFn::Average, 60, 10),
+        ];
+
+        let mut rrd = MultiDatabase::new(rra_list.clone());
+        rrd.add_source("cpu", DataSourceType::Gauge)?;
+        rrd.update(60.0, &[("cpu", 1.0)])?;
+
+        let mut v2 = Database::new(
+            DataSourceType::Gauge,
+            rra_list
+                .iter()
+                .map(|def| Archive::new(def.cf, def.resolution, def.points))
+                .collect(),
+        );
+        v2.update(60.0, 1.0);
+
+        let data = rrd.extract_data(AggregationFn::Average, 60, Some(0), Some(600), None)?;
+        let entry = v2.extract_data(AggregationFn::Average, 60, Some(0), Some(600))?;
+        assert_eq!(data[0].1.data.len(), 5);
+        assert_eq!(entry.data.len(), 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn convert_from_v2() -> Result<(), Error> {
+        let v2 = |value: f64| {
+            let rra_list = rra_list()
+                .iter()
+                .map(|def| Archive::new(def.cf, def.resolution, def.points))
+                .collect();
+            let mut rrd = Database::new(DataSourceType::Gauge, rra_list);
+            rrd.update(60.0, value);
+            rrd
+        };
+
+        let rrd = MultiDatabase::from_v2([("cpu", v2(1.0)), ("mem", v2(2.0))])?;
+        assert_eq!(rrd.last_update(), 60.0);
+
+        let data = rrd.extract_data(AggregationFn::Average, 60, Some(60), Some(60), None)?;
+        assert_eq!(data[0].0, "cpu");
+        assert_eq!(data[0].1.start, 60);
+        assert_eq!(data[0].1.data, [Some(1.0)]);
+        assert_eq!(data[1].1.data, [Some(2.0)]);
+
+        let mut raw = PROXMOX_RRD_MAGIC_3_0.to_vec();
+        serde_cbor::to_writer(&mut raw, &rrd)?;
+        let rrd = MultiDatabase::from_raw(&raw, "unused")?;
+        assert_eq!(rrd.source_names().collect::<Vec<_>>(), ["cpu", "mem"]);
+
+        let mut other = Database::new(
+            DataSourceType::Gauge,
+            vec![Archive::new(AggregationFn::Average, 60, 10)],
+        );
+        other.update(60.0, 1.0);
+        assert!(MultiDatabase::from_v2([("cpu", v2(1.0)), ("other", other)]).is_err());
+        assert!(MultiDatabase::from_v2([("cpu", v2(1.0)), ("cpu", v2(2.0))]).is_err());
+
+        Ok(())
+    }
+}
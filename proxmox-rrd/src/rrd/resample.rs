@@ -0,0 +1,272 @@
+//! # Change the RRA layout of existing RRDs
+//!
+//! [Database::reconfigure] replaces the list of RRAs, and fills the new
+//! RRAs by resampling the data of the existing ones. This allows to
+//! change the retention policy without losing history.
+//!
+//! For each slot of a new RRA, the existing RRAs are tried in the
+//! following order, and the first one with data for that slot wins:
+//!
+//! 1. RRAs with the same [AggregationFn], finest resolution first
+//! 2. RRAs with any other [AggregationFn], finest resolution first
+//!
+//! Finer data is consolidated using the aggregation function of the
+//! new RRA. Coarser data is simply repeated for all covered slots.
+
+use anyhow::{bail, Error};
+
+use super::{AggregationFn, Archive, ArchiveDefinition, Database, MultiDatabase};
+
+impl Archive {
+    /// Returns the value of the slot starting at `slot_start`, or NaN
+    /// if that slot is outside the time range covered by this RRA.
+    fn slot_value(&self, slot_start: u64, last_update: u64) -> f64 {
+        let end = self.slot_end_time(last_update);
+        let start = end.saturating_sub(self.resolution * self.data.len() as u64);
+
+        if slot_start < start || slot_start >= end {
+            return f64::NAN;
+        }
+
+        self.data[self.slot(slot_start)]
+    }
+
+    /// Consolidate the data of this RRA for the time range `start` to
+    /// `start + resolution`, using `cf`.
+    fn consolidate(&self, cf: AggregationFn, start: u64, resolution: u64, last_update: u64) -> f64 {
+        if self.resolution > resolution {
+            return self.slot_value(self.slot_start_time(start), last_update);
+        }
+
+        let first = start.div_ceil(self.resolution) * self.resolution;
+        let end = start + resolution;
+
+        let mut result = f64::NAN;
+        let mut count = 0;
+
+        for time in (first..end).step_by(self.resolution as usize) {
+            let value = self.slot_value(time, last_update);
+            if value.is_nan() {
+                continue;
+            }
+
+            count += 1;
+            result = if result.is_nan() {
+                value
+            } else {
+                match cf {
+                    AggregationFn::Average => result + (value - result) / count as f64,
+                    AggregationFn::Maximum => result.max(value),
+                    AggregationFn::Minimum => result.min(value),
+                    AggregationFn::Last => value,
+                }
+            };
+        }
+
+        result
+    }
+}
+
+/// Check that all archives have a non-zero resolution and size.
+fn verify_archives<'a>(list: impl IntoIterator<Item = &'a Archive>) -> Result<(), Error> {
+    for rra in list {
+        if rra.resolution == 0 {
+            bail!("got RRA with zero resolution");
+        }
+        if rra.data.is_empty() {
+            bail!("got RRA without data slots");
+        }
+    }
+    Ok(())
+}
+
+/// Fill `new_list` with the data of `old_list`, see the module
+/// documentation for details.
+fn resample_archives(old_list: &[Archive], new_list: &mut [Archive], last_update: f64) {
+    if last_update <= 0.0 {
+        return; // no data
+    }
+    let last_update = last_update as u64;
+
+    for rra in new_list.iter_mut() {
+        // keep identical RRAs untouched, including the current update interval
+        if let Some(old) = old_list.iter().find(|old| {
+            old.cf == rra.cf && old.resolution == rra.resolution && old.data.len() == rra.data.len()
+        }) {
+            rra.data.clone_from(&old.data);
+            rra.last_count = old.last_count;
+            continue;
+        }
+
+        let mut sources: Vec<&Archive> = old_list.iter().collect();
+        sources.sort_by_key(|old| (old.cf != rra.cf, old.resolution));
+
+        let end = rra.slot_end_time(last_update);
+        let start = end.saturating_sub(rra.resolution * rra.data.len() as u64);
+
+        for time in (start..end).step_by(rra.resolution as usize) {
+            let value = sources
+                .iter()
+                .map(|old| old.consolidate(rra.cf, time, rra.resolution, last_update))
+                .find(|value| !value.is_nan());
+
+            if let Some(value) = value {
+                let index = rra.slot(time);
+                rra.data[index] = value;
+            }
+        }
+
+        // continue the current update interval as if it had a single value
+        let current = rra.data[rra.slot(last_update)];
+        rra.last_count = if current.is_nan() { 0 } else { 1 };
+    }
+}
+
+impl Database {
+    /// Returns `true` if the RRAs of this database match `rra_list`
+    /// (same order, aggregation function, resolution and size).
+    pub fn has_layout(&self, rra_list: &[Archive]) -> bool {
+        self.rra_list.len() == rra_list.len()
+            && self
+                .rra_list
+                .iter()
+                .zip(rra_list)
+                .all(|(a, b)| ArchiveDefinition::from(a) == ArchiveDefinition::from(b))
+    }
+
+    /// Replace the list of RRAs (in memory)
+    ///
+    /// The new RRAs are filled by resampling the data of the existing
+    /// RRAs. Any data already contained in `rra_list` is overwritten.
+    ///
+    /// Note: This does not call [Self::save].
+    pub fn reconfigure(&mut self, mut rra_list: Vec<Archive>) -> Result<(), Error> {
+        verify_archives(&rra_list)?;
+
+        for rra in rra_list.iter_mut() {
+            rra.data.fill(f64::NAN);
+            rra.last_count = 0;
+        }
+
+        resample_archives(&self.rra_list, &mut rra_list, self.source.last_update);
+        self.rra_list = rra_list;
+
+        Ok(())
+    }
+}
+
+impl MultiDatabase {
+    /// Replace the RRA definitions of all data sources (in memory)
+    ///
+    /// See [Database::reconfigure].
+    ///
+    /// Note: This does not call [Self::save].
+    pub fn reconfigure(&mut self, rra_list: Vec<ArchiveDefinition>) -> Result<(), Error> {
+        let new_list = |rra_list: &[ArchiveDefinition]| -> Vec<Archive> {
+            rra_list
+                .iter()
+                .map(|def| Archive::new(def.cf, def.resolution, def.points))
+                .collect()
+        };
+
+        verify_archives(&new_list(&rra_list))?;
+
+        for named in self.sources.iter_mut() {
+            let mut list = new_list(&rra_list);
+            resample_archives(&named.rra_list, &mut list, named.source.last_update);
+            named.rra_list = list;
+        }
+        self.rra_list = rra_list;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rrd::DataSourceType;
+
+    fn test_database() -> Database {
+        let mut rrd = Database::new(
+            DataSourceType::Gauge,
+            vec![
+                Archive::new(AggregationFn::Average, 60, 60),
+                Archive::new(AggregationFn::Maximum, 60, 60),
+                Archive::new(AggregationFn::Average, 600, 60),
+            ],
+        );
+
+        // one value per minute for 5 hours, last_update is 18000
+        for i in 1..=300 {
+            rrd.update((i * 60) as f64, i as f64);
+        }
+
+        rrd
+    }
+
+    #[test]
+    fn keep_identical_rra() -> Result<(), Error> {
+        let mut rrd = test_database();
+        let expected = rrd.extract_data(AggregationFn::Average, 60, Some(14400), Some(18000))?;
+
+        rrd.reconfigure(vec![Archive::new(AggregationFn::Average, 60, 60)])?;
+
+        let entry = rrd.extract_data(AggregationFn::Average, 60, Some(14400), Some(18000))?;
+        assert_eq!(entry.data, expected.data);
+        assert_eq!(rrd.rra_list[0].last_count, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn resample_to_coarser_rra() -> Result<(), Error> {
+        let mut rrd = test_database();
+
+        rrd.reconfigure(vec![
+            Archive::new(AggregationFn::Average, 300, 60),
+            Archive::new(AggregationFn::Maximum, 300, 60),
+            Archive::new(AggregationFn::Minimum, 300, 60),
+        ])?;
+        assert!(rrd.has_layout(&[
+            Archive::new(AggregationFn::Average, 300, 60),
+            Archive::new(AggregationFn::Maximum, 300, 60),
+            Archive::new(AggregationFn::Minimum, 300, 60),
+        ]));
+
+        // fine data covers the last hour, values 241 to 300
+        let entry = rrd.extract_data(AggregationFn::Average, 300, Some(14400), Some(17700))?;
+        assert_eq!(entry.data[0], Some(242.5));
+        assert_eq!(entry.data[11], Some(297.0));
+
+        let entry = rrd.extract_data(AggregationFn::Maximum, 300, Some(14400), Some(17700))?;
+        assert_eq!(entry.data[0], Some(244.0));
+
+        // no minimum RRA, so this uses the average data
+        let entry = rrd.extract_data(AggregationFn::Minimum, 300, Some(14400), Some(17700))?;
+        assert_eq!(entry.data[0], Some(241.0));
+
+        // older data only exists in the 10 minute RRA, which is repeated
+        let entry = rrd.extract_data(AggregationFn::Average, 300, Some(12000), Some(12300))?;
+        assert_eq!(entry.data, [Some(204.5), Some(204.5)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reconfigure_empty_rrd() -> Result<(), Error> {
+        let mut rrd = Database::new(
+            DataSourceType::Gauge,
+            vec![Archive::new(AggregationFn::Average, 60, 10)],
+        );
+
+        rrd.reconfigure(vec![Archive::new(AggregationFn::Average, 300, 10)])?;
+        assert!(rrd.rra_list[0].data.iter().all(|value| value.is_nan()));
+
+        assert!(rrd
+            .reconfigure(vec![Archive::new(AggregationFn::Average, 0, 10)])
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@ use std::process::Command;
 
 use anyhow::{bail, Error};
 
-use proxmox_rrd::rrd::Database;
+use proxmox_rrd::rrd::{AggregationFn, Database, MultiDatabase};
 use proxmox_sys::fs::CreateOptions;
 
 fn compare_file(fn1: &str, fn2: &str) -> Result<(), Error> {
@@ -55,3 +55,27 @@ fn load_and_save_rrd_v2() -> Result<(), Error> {
 
     Ok(())
 }
+
+// make sure we can convert RRD v2 to v3, and load and save RRD v3
+#[test]
+fn convert_rrd_v2_to_v3() -> Result<(), Error> {
+    let v2 = Database::load(Path::new(RRD_V2_FN), true)?;
+    let rrd = MultiDatabase::load(Path::new(RRD_V2_FN), true)?;
+
+    assert_eq!(rrd.source_names().collect::<Vec<_>>(), ["cpu"]);
+    assert_eq!(rrd.last_update(), v2.last_update());
+
+    const RRD_V3_NEW_FN: &str = "./tests/testdata/cpu.rrd_v3.saved";
+    let new_path = Path::new(RRD_V3_NEW_FN);
+    rrd.save(new_path, CreateOptions::new(), true)?;
+    let loaded = MultiDatabase::load(new_path, true);
+    let _ = std::fs::remove_file(RRD_V3_NEW_FN);
+    let loaded = loaded?;
+
+    let end = Some(v2.last_update() as u64);
+    let expected = v2.extract_data(AggregationFn::Average, 60, None, end)?;
+    let data = loaded.extract_data(AggregationFn::Average, 60, None, end, Some(&["cpu"]))?;
+    assert_eq!(data[0].1.data, expected.data);
+
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::path::Path;
+
+use anyhow::Error;
+
+use proxmox_rrd::rrd::{AggregationFn, Archive, DataSourceType, Database};
+use proxmox_rrd::Cache;
+
+fn load_rrd(path: &Path, _rel_path: &str) -> Option<Database> {
+    Database::load(path, true).ok()
+}
+
+fn create_rrd(dst: DataSourceType) -> Database {
+    Database::new(dst, vec![Archive::new(AggregationFn::Average, 60, 10)])
+}
+
+// two cache instances on the same directory behave like two processes
+#[test]
+fn shared_cache_access() -> Result<(), Error> {
+    let basedir = proxmox_sys::fs::make_tmp_dir("/tmp", None)?;
+
+    let result = (|| -> Result<(), Error> {
+        let writer = Cache::new_shared(&basedir, None, None, load_rrd, create_rrd)?;
+        let reader = Cache::new_shared(&basedir, None, None, load_rrd, create_rrd)?;
+
+        let read = |cache: &Cache| {
+            cache
+                .extract_cached_data(
+                    "host",
+                    "cpu",
+                    AggregationFn::Average,
+                    60,
+                    Some(60),
+                    Some(120),
+                )
+                .map(|entry| entry.map(|entry| entry.data))
+        };
+
+        assert_eq!(read(&reader)?, None);
+
+        writer.update_value("host/cpu", 60.0, 1.0, DataSourceType::Gauge)?;
+        assert_eq!(read(&reader)?, Some(vec![Some(1.0), None]));
+
+        reader.update_value("host/cpu", 120.0, 2.0, DataSourceType::Gauge)?;
+        writer.update_value("host/cpu", 130.0, 4.0, DataSourceType::Gauge)?;
+        assert_eq!(read(&reader)?, Some(vec![Some(1.0), Some(3.0)]));
+        assert_eq!(read(&writer)?, Some(vec![Some(1.0), Some(3.0)]));
+
+        Ok(())
+    })();
+
+    std::fs::remove_dir_all(&basedir)?;
+
+    result
+}
+
+#[test]
+fn shared_cache_multi_source() -> Result<(), Error> {
+    let basedir = proxmox_sys::fs::make_tmp_dir("/tmp", None)?;
+
+    let result = (|| -> Result<(), Error> {
+        let writer = Cache::new_shared(&basedir, None, None, load_rrd, create_rrd)?;
+        let reader = Cache::new_shared(&basedir, None, None, load_rrd, create_rrd)?;
+
+        let read = |cache: &Cache, sources: Option<&[&str]>| {
+            cache
+                .extract_cached_sources(
+                    "guest/100",
+                    AggregationFn::Average,
+                    60,
+                    Some(60),
+                    Some(120),
+                    sources,
+                )
+                .map(|list| {
+                    list.map(|list| {
+                        list.into_iter()
+                            .map(|(name, entry)| (name, entry.data))
+                            .collect::<Vec<_>>()
+                    })
+                })
+        };
+
+        assert_eq!(read(&reader, None)?, None);
+
+        writer.update_values("guest/100", 60.0, &[("cpu", 1.0, DataSourceType::Gauge)])?;
+        reader.update_values(
+            "guest/100",
+            120.0,
+            &[
+                ("cpu", 2.0, DataSourceType::Gauge),
+                ("mem", 5.0, DataSourceType::Gauge),
+            ],
+        )?;
+
+        assert_eq!(
+            read(&writer, None)?,
+            Some(vec![
+                ("cpu".to_string(), vec![Some(1.0), Some(2.0)]),
+                ("mem".to_string(), vec![None, Some(5.0)]),
+            ])
+        );
+        assert_eq!(
+            read(&reader, Some(&["mem"]))?,
+            Some(vec![("mem".to_string(), vec![None, Some(5.0)])])
+        );
+
+        assert!(writer
+            .update_value("guest/100", 180.0, 1.0, DataSourceType::Gauge)
+            .is_err());
+        assert!(writer
+            .update_values(
+                "guest/100",
+                180.0,
+                &[("net:in", 1.0, DataSourceType::Derive)]
+            )
+            .is_err());
+        assert!(Database::load(&basedir.join("guest/100"), false).is_err());
+
+        Ok(())
+    })();
+
+    std::fs::remove_dir_all(&basedir)?;
+
+    result
+}
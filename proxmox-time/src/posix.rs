@@ -389,6 +389,89 @@ pub fn epoch_to_http_date(epoch: i64) -> Result<String, Error> {
     strftime_l("%a, %d %b %Y %H:%M:%S GMT", &gmtime, &locale)
 }
 
+/// Parse an RFC9110 preferred HTTP Date (IMF-fixdate) into Unix epoch
+///
+/// For example `Sun, 06 Nov 1994 08:49:37 GMT`. The obsolete formats are not supported.
+pub fn parse_http_date(input_str: &str) -> Result<i64, Error> {
+    parse_http_date_do(input_str)
+        .map_err(|err| format_err!("failed to parse http date ({input_str:?}) - {err}"))
+}
+
+fn parse_http_date_do(input_str: &str) -> Result<i64, Error> {
+    const MONTHS: [&[u8]; 12] = [
+        b"Jan", b"Feb", b"Mar", b"Apr", b"May", b"Jun", b"Jul", b"Aug", b"Sep", b"Oct", b"Nov",
+        b"Dec",
+    ];
+
+    let input = input_str.as_bytes();
+
+    if input.len() != 29 {
+        bail!("date of unexpected length");
+    }
+
+    let expect = |pos: usize, expected: &[u8]| {
+        if &input[pos..(pos + expected.len())] != expected {
+            bail!("unexpected char at pos {pos}");
+        }
+        Ok(())
+    };
+
+    let number = |pos: usize, len: usize, max: i32| -> Result<i32, Error> {
+        let mut value = 0;
+        for (i, digit) in input[pos..(pos + len)].iter().enumerate() {
+            if !digit.is_ascii_digit() {
+                bail!("unexpected char at pos {}", pos + i);
+            }
+            value = value * 10 + (digit - b'0') as i32;
+        }
+        if value > max {
+            bail!("value too large ({value} > {max})");
+        }
+        Ok(value)
+    };
+
+    // the day name is redundant, so we do not check if it matches
+    expect(3, b", ")?;
+    let mday = number(5, 2, 31)?;
+    expect(7, b" ")?;
+    let month = match MONTHS.iter().position(|name| *name == &input[8..11]) {
+        Some(index) => index as i32 + 1,
+        None => bail!("unknown month"),
+    };
+    expect(11, b" ")?;
+    let year = number(12, 4, 9999)?;
+    expect(16, b" ")?;
+    let hour = number(17, 2, 23)?;
+    expect(19, b":")?;
+    let min = number(20, 2, 59)?;
+    expect(22, b":")?;
+    let sec = number(23, 2, 60)?;
+    expect(25, b" GMT")?;
+
+    let mut tm = crate::TmEditor::new(true);
+    tm.set_year(year)?;
+    tm.set_mon(month)?;
+    tm.set_mday(mday)?;
+    tm.set_hour(hour)?;
+    tm.set_min(min)?;
+    tm.set_sec(sec)?;
+
+    tm.into_epoch()
+}
+
+#[test]
+fn test_http_date() {
+    let epoch = 784111777;
+    let date = "Sun, 06 Nov 1994 08:49:37 GMT";
+    assert_eq!(epoch_to_http_date(epoch).unwrap(), date);
+    assert_eq!(parse_http_date(date).unwrap(), epoch);
+
+    assert!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT").is_err());
+    assert!(parse_http_date("Sun, 06 Nov 1994 08:49:37 UTC").is_err());
+    assert!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT").is_err());
+    assert!(parse_http_date("Sun, 06 Nov 1994 24:49:37 GMT").is_err());
+}
+
 #[test]
 fn test_leap_seconds() {
     let convert_reconvert = |epoch| {